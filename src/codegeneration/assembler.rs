@@ -0,0 +1,498 @@
+//! two pass assembler for the assembly emitted by the code generator
+//!
+//! the first pass encodes every line and records the address of every label, operands that
+//! refer to labels are encoded as zero and recorded as relocations. the second pass patches the
+//! relocations with the addresses of the labels
+//!
+//! directives:
+//! - `.org addr` places the following lines at addr
+//! - `.byte a, b, ...` emits bytes
+//! - `.word a, b, ...` emits words, a word may be the address of a label
+//! - `.string "text"` emits a null terminated string
+//!
+//! the image starts at address 0, gaps between the placed lines are filled with zeros
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::isa::OperandKind;
+use super::target::Target;
+
+///a field in the image that holds the address of a label
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Relocation {
+    pub addr: u64,
+    pub symbol: String,
+    line: usize,
+}
+
+///a source line with the bytes it was encoded to
+struct ListedLine {
+    addr: u64,
+    len: usize,
+    source: String,
+    //comments and empty lines have no address
+    has_addr: bool,
+}
+
+pub struct Assembly {
+    pub image: Vec<u8>,
+    //labels in the order they are defined
+    pub symbols: Vec<(String, u64)>,
+    pub relocations: Vec<Relocation>,
+    lines: Vec<ListedLine>,
+    addr_digits: usize,
+}
+
+struct Assembler<'a> {
+    target: &'a Target,
+    addr: u64,
+    image: Vec<u8>,
+    //bytes of the image that were written, to find overlapping lines
+    used: Vec<bool>,
+    symbols: Vec<(String, u64)>,
+    relocations: Vec<Relocation>,
+    lines: Vec<ListedLine>,
+    errors: Vec<String>,
+}
+
+fn parse_number(s: &str) -> Option<i64> {
+    let (neg, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse().ok()?,
+        None => return None,
+    };
+    Some(if neg { -value } else { value })
+}
+
+///labels can contain `$`, which is not allowed in names of the language, to not collide with them
+fn is_label(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+///removes a comment, semicolons in strings don't start one
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+///parses a quoted string literal, the reverse of the escaping done when assembly is rendered
+fn unescape(s: &str) -> Result<String, String> {
+    let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) else {
+        return Err(format!("expected a string in quotes, found `{}`", s));
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('0') => out.push('\0'),
+            Some('\\') => out.push('\\'),
+            Some('"') => out.push('"'),
+            other => return Err(format!("unknown escape `\\{}`", other.unwrap_or(' '))),
+        }
+    }
+    Ok(out)
+}
+
+impl<'a> Assembler<'a> {
+    fn new(target: &'a Target) -> Self {
+        Self {
+            target,
+            addr: 0,
+            image: Vec::new(),
+            used: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+            lines: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    ///writes bytes to the image at addr
+    fn write_at(&mut self, addr: u64, bytes: &[u8]) -> Result<(), String> {
+        let end = addr + bytes.len() as u64;
+        if end > self.target.memory_size {
+            return Err(format!("address {:#x} is outside of the memory", end - 1));
+        }
+        let (start, end) = (addr as usize, end as usize);
+        if self.image.len() < end {
+            self.image.resize(end, 0);
+            self.used.resize(end, false);
+        }
+        if self.used[start..end].iter().any(|used| *used) {
+            return Err(format!("overlaps code placed before at {:#x}", addr));
+        }
+        self.image[start..end].copy_from_slice(bytes);
+        self.used[start..end].fill(true);
+        Ok(())
+    }
+
+    ///encodes a word that is a number or the address of a label
+    fn encode_value(&mut self, value: &str, line: usize, out: &mut Vec<u8>) -> Result<(), String> {
+        if let Some(num) = parse_number(value) {
+            if !self.target.fits_word(num) {
+                return Err(format!("`{}` does not fit into a word", num));
+            }
+            out.extend(self.target.encode_word(num));
+        } else if is_label(value) {
+            self.relocations.push(Relocation {
+                addr: self.addr + out.len() as u64,
+                symbol: value.to_string(),
+                line,
+            });
+            out.extend(self.target.encode_word(0));
+        } else {
+            return Err(format!("expected a number or a label, found `{}`", value));
+        }
+        Ok(())
+    }
+
+    fn parse_register(&self, s: &str) -> Result<u8, String> {
+        s.strip_prefix('r')
+            .and_then(|reg| reg.parse::<u8>().ok())
+            .filter(|reg| *reg < self.target.registers)
+            .ok_or_else(|| format!("expected a register, found `{}`", s))
+    }
+
+    ///encodes `[rN]`, `[rN + off]` or `[rN - off]`
+    fn encode_memory(&self, s: &str, out: &mut Vec<u8>) -> Result<(), String> {
+        let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) else {
+            return Err(format!("expected a memory operand, found `{}`", s));
+        };
+        let (reg, offset) = match inner.find(['+', '-']) {
+            Some(i) => {
+                let offset = parse_number(inner[i + 1..].trim())
+                    .ok_or_else(|| format!("invalid offset in `{}`", s))?;
+                let sign = if inner[i..].starts_with('-') { -1 } else { 1 };
+                (inner[..i].trim(), sign * offset)
+            }
+            None => (inner.trim(), 0),
+        };
+        out.push(self.parse_register(reg)?);
+        if !self.target.fits_word(offset) {
+            return Err(format!("offset `{}` does not fit into a word", offset));
+        }
+        out.extend(self.target.encode_word(offset));
+        Ok(())
+    }
+
+    fn encode_instruction(&mut self, line: &str, nr: usize) -> Result<Vec<u8>, String> {
+        let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let op = self
+            .target
+            .op_by_mnemonic(mnemonic)
+            .ok_or_else(|| format!("unknown instruction `{}`", mnemonic))?;
+        let operands: Vec<&str> = match rest.trim() {
+            "" => Vec::new(),
+            rest => rest.split(',').map(str::trim).collect(),
+        };
+        let kinds = op.operand_kinds();
+        if operands.len() != kinds.len() {
+            return Err(format!(
+                "`{}` takes {} operand(s) but {} were given",
+                mnemonic,
+                kinds.len(),
+                operands.len()
+            ));
+        }
+        let mut out = vec![self.target.opcode(op)];
+        for (operand, kind) in operands.into_iter().zip(kinds) {
+            match kind {
+                OperandKind::Reg => out.push(self.parse_register(operand)?),
+                OperandKind::Imm => self.encode_value(operand, nr, &mut out)?,
+                OperandKind::Mem => self.encode_memory(operand, &mut out)?,
+            }
+        }
+        Ok(out)
+    }
+
+    fn encode_directive(&mut self, line: &str, nr: usize) -> Result<Vec<u8>, String> {
+        let (directive, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        let mut out = Vec::new();
+        match directive {
+            ".org" => {
+                let addr = parse_number(args)
+                    .filter(|addr| *addr >= 0)
+                    .ok_or_else(|| format!("invalid address `{}`", args))?;
+                self.addr = addr as u64;
+            }
+            ".byte" => {
+                for arg in args.split(',').map(str::trim) {
+                    let byte = parse_number(arg)
+                        .filter(|byte| (-128..=255).contains(byte))
+                        .ok_or_else(|| format!("expected a byte, found `{}`", arg))?;
+                    out.push(byte as u8);
+                }
+            }
+            ".word" => {
+                for arg in args.split(',').map(str::trim) {
+                    self.encode_value(arg, nr, &mut out)?;
+                }
+            }
+            ".string" => {
+                out.extend(unescape(args)?.bytes());
+                out.push(0);
+            }
+            other => return Err(format!("unknown directive `{}`", other)),
+        }
+        Ok(out)
+    }
+
+    ///encodes one line and places it in the image
+    fn assemble_line(&mut self, source: &str, nr: usize) -> Result<(), String> {
+        let line = strip_comment(source).trim();
+        let mut listed = ListedLine {
+            addr: self.addr,
+            len: 0,
+            source: source.trim_end().to_string(),
+            has_addr: !line.is_empty(),
+        };
+        if let Some(label) = line.strip_suffix(':') {
+            if !is_label(label) {
+                return Err(format!("invalid label `{}`", label));
+            }
+            if self.symbols.iter().any(|(name, _)| name == label) {
+                return Err(format!("label `{}` is defined twice", label));
+            }
+            self.symbols.push((label.to_string(), self.addr));
+        } else if line.starts_with('.') {
+            let bytes = self.encode_directive(line, nr)?;
+            //.org moves the line to its new address
+            listed.addr = self.addr;
+            self.write_at(self.addr, &bytes)?;
+            listed.len = bytes.len();
+        } else if !line.is_empty() {
+            let bytes = self.encode_instruction(line, nr)?;
+            self.write_at(self.addr, &bytes)?;
+            listed.len = bytes.len();
+        }
+        self.addr += listed.len as u64;
+        self.lines.push(listed);
+        Ok(())
+    }
+
+    fn assemble(mut self, source: &str) -> Result<Assembly, Vec<String>> {
+        //first pass, encode everything and find the labels
+        for (i, line) in source.lines().enumerate() {
+            let relocations = self.relocations.len();
+            if let Err(err) = self.assemble_line(line, i + 1) {
+                //the line was not placed, so its relocations can't be patched
+                self.relocations.truncate(relocations);
+                self.errors.push(format!("line {}: {}", i + 1, err));
+            }
+        }
+
+        //second pass, patch the addresses of the labels
+        let symbols: HashMap<&str, u64> = self
+            .symbols
+            .iter()
+            .map(|(name, addr)| (name.as_str(), *addr))
+            .collect();
+        for reloc in &self.relocations {
+            let Some(addr) = symbols.get(reloc.symbol.as_str()) else {
+                self.errors.push(format!(
+                    "line {}: undefined label `{}`",
+                    reloc.line, reloc.symbol
+                ));
+                continue;
+            };
+            let bytes = self.target.encode_word(*addr as i64);
+            let start = reloc.addr as usize;
+            self.image[start..start + bytes.len()].copy_from_slice(&bytes);
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        Ok(Assembly {
+            image: self.image,
+            symbols: self.symbols,
+            relocations: self.relocations,
+            lines: self.lines,
+            addr_digits: self.target.address_bits.div_ceil(4) as usize,
+        })
+    }
+}
+
+///assembles the source for the target, returns all errors with the line they occurred in
+pub fn assemble(source: &str, target: &Target) -> Result<Assembly, Vec<String>> {
+    Assembler::new(target).assemble(source)
+}
+
+//bytes shown per row of the hex dump and the listing
+const DUMP_WIDTH: usize = 16;
+const LISTING_WIDTH: usize = 8;
+
+impl Assembly {
+    ///hex dump of the image, 16 bytes per row prefixed with their address
+    pub fn hex_dump(&self) -> String {
+        let mut out = String::new();
+        for (i, row) in self.image.chunks(DUMP_WIDTH).enumerate() {
+            let bytes: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+            let addr = i * DUMP_WIDTH;
+            writeln!(
+                out,
+                "{:0w$x}: {}",
+                addr,
+                bytes.join(" "),
+                w = self.addr_digits
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    ///every source line with its address and encoding, followed by the symbols and relocations
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+        let digits = self.addr_digits;
+        let bytes_width = LISTING_WIDTH * 3;
+        for line in &self.lines {
+            let start = line.addr as usize;
+            let bytes = &self.image[start..start + line.len];
+            let mut rows = bytes.chunks(LISTING_WIDTH);
+            let first: Vec<String> = rows
+                .next()
+                .unwrap_or_default()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            let text = if line.has_addr {
+                format!(
+                    "{:0digits$x}  {:bytes_width$}{}",
+                    line.addr,
+                    first.join(" "),
+                    line.source
+                )
+            } else {
+                format!("{:digits$}  {:bytes_width$}{}", "", "", line.source)
+            };
+            writeln!(out, "{}", text.trim_end()).unwrap();
+            //long lines continue on the next rows
+            for (i, row) in rows.enumerate() {
+                let addr = line.addr as usize + (i + 1) * LISTING_WIDTH;
+                let row: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+                writeln!(out, "{:0digits$x}  {}", addr, row.join(" ")).unwrap();
+            }
+        }
+        writeln!(out, "\nsymbols:").unwrap();
+        for (name, addr) in &self.symbols {
+            writeln!(out, "{:0digits$x}  {}", addr, name).unwrap();
+        }
+        writeln!(out, "\nrelocations:").unwrap();
+        for reloc in &self.relocations {
+            writeln!(out, "{:0digits$x}  {}", reloc.addr, reloc.symbol).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encoding_and_labels() {
+        let target = Target::default();
+        let asm = assemble(
+            "_start:
+                LDI r15, 0xff00   ; stack
+                CALL fn_main
+                HALT
+            fn_main:
+                LDW r0, [r14 - 4]
+                RET",
+            &target,
+        )
+        .unwrap();
+        assert_eq!(
+            asm.image,
+            [
+                0x01, 15, 0x00, 0xff, 0x00, 0x00, //LDI
+                0x42, 0x0c, 0x00, 0x00, 0x00, //CALL fn_main
+                0xff, //HALT
+                0x30, 0, 14, 0xfc, 0xff, 0xff, 0xff, //LDW
+                0x43, //RET
+            ]
+        );
+        assert_eq!(asm.symbols[1], ("fn_main".to_string(), 12));
+        assert_eq!(asm.relocations[0].addr, 7);
+    }
+
+    #[test]
+    fn test_directives() {
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        let asm = assemble(
+            "    .org 0x0004
+            data:
+                .byte 1, -1
+                .word data, 0x1234
+                .string \"a;\\n\"",
+            &target,
+        )
+        .unwrap();
+        //big endian words
+        assert_eq!(
+            asm.image[4..],
+            [1, 0xff, 0x00, 0x04, 0x12, 0x34, b'a', b';', b'\n', 0]
+        );
+        assert!(asm.hex_dump().starts_with("0000: 00 00 00 00 01 ff"));
+        let listing = asm.listing();
+        let byte_line = listing.lines().find(|l| l.contains(".byte")).unwrap();
+        assert!(byte_line.starts_with("0004  01 ff "));
+        assert!(listing.ends_with("relocations:\n0006  data\n"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let target = Target::default();
+        let errors = assemble(
+            "start:
+                FOO r1
+                LDI r16, 1
+                JMP nowhere
+                MOV r1
+            start:
+                .org 0
+                HALT",
+            &target,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            errors,
+            [
+                "line 2: unknown instruction `FOO`",
+                "line 3: expected a register, found `r16`",
+                "line 5: `MOV` takes 2 operand(s) but 1 were given",
+                "line 6: label `start` is defined twice",
+                "line 8: overlaps code placed before at 0x0",
+                "line 4: undefined label `nowhere`",
+            ]
+        );
+    }
+}
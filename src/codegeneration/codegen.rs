@@ -0,0 +1,1849 @@
+//! lowers a typechecked program to assembly for the minecraft cpu described by a target
+//!
+//! registers are one word wide, memory is byte addressed and the stack grows downwards from the
+//! stack top of the target, every value takes one word on the stack
+//!
+//! calling convention:
+//! - the caller pushes the arguments from left to right, nested functions get the frame pointer of
+//!   the closest frame of their enclosing function after them, the static link
+//! - `CALL` pushes the return address, the callee pushes the frame pointer of the caller, points
+//!   the frame pointer at it and reserves the space for its locals
+//! - the return value is passed in r0, the caller pops the arguments after the call returns
+//! - r0 to r2 are scratch registers and are not preserved across calls
+//! - the second to last register is the frame pointer, the last one the stack pointer
+//!
+//! stack frame of a function with n arguments, W is the size of a word in bytes:
+//! ```text
+//! fp + 2W + (n-1)W    first argument
+//! ...
+//! fp + 2W             last argument
+//! fp + W              return address
+//! fp                  frame pointer of the caller
+//! fp - W              first local
+//! ...
+//! sp                  last local
+//! ```
+//!
+//! in nested functions the arguments are one word further up, the static link is at fp + 2W. a
+//! variable of an enclosing function is accessed through the frame that is found by following the
+//! static links once for each level of nesting between the two functions
+//!
+//! the program starts at `_start`, which sets up the stack, calls main and halts with the return
+//! value of main in r0. functions are labeled `fn_<name>`, nested ones `<parent>.<name>_<id>` with
+//! the label of the enclosing function and the symbol id of the nested one
+//!
+//! `print` writes one byte at a time to the output port of the target, values that are not chars
+//! are formatted by routines that are only emitted if they are used:
+//! - `__print_str` writes the null terminated string at the address in r0
+//! - `__print_uint` writes r0 as an unsigned decimal number
+//! - `__print_int` writes r0 as a signed decimal number
+//!
+//! the routines only use r0 to r2 and the stack
+//!
+//! structs are laid out like described in `layout`, an expression of a struct type leaves the
+//! address of the struct in r0 instead of its value. struct locals take their size rounded up to
+//! whole words, struct literals are built in a hidden local of the function. copies of structs are
+//! done by a loop that moves one word at a time
+//!
+//! arrays are handled like structs, `a[i]` adds `i` times the size of an element to the address
+//! of the array. indices are not checked at runtime, the typechecker only rejects constant
+//! indices that are out of bounds
+//!
+//! pointers are addresses, `p + n` adds `n` times the size of the pointee. a pointer to a scalar
+//! local points to the bytes of its type inside of the word of the local, locals whose address is
+//! taken are loaded from those bytes, because stores through the pointer only write them
+//!
+//! values of enums are their discriminants. `match` compares the value with the patterns one
+//! after another, unless there are enough of them and they are dense, then the address of the arm
+//! is loaded from a jump table in the data section. there is no indirect jump, so the address is
+//! pushed and jumped to with `RET`
+//!
+//! global variables are placed in the data section at `global_<name>`, laid out like struct
+//! fields from the start of a slot of whole words that holds their evaluated value. they are
+//! always accessed through their address. constants have no storage, their value is loaded
+//! wherever they are used
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::parser::{
+    ast::{
+        ArrayLiteralAST, ArrayRepeatAST, BinaryExpressionAST, BodyAST, CallAST, DeclAssignAST,
+        DeclarationAST, ElseAST, ExprAST, FunctionAST, IfStmtAST, MatchStmtAST, PatternAST,
+        PrintFormat, PrintStmtAST, StmtAST, StructLiteralAST, SymbolId, TypeAST, VariableAST,
+    },
+    lexer::{Span, Token},
+};
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::isa::{AsmLine, Instr, Op, Operand};
+use super::layout::{self, StructLayout};
+use super::resolver::TypeTable;
+use super::target::Target;
+
+//accumulator, holds the result of every expression and the return value
+const ACC: u8 = 0;
+const TMP: u8 = 1;
+const TMP2: u8 = 2;
+
+//a match with at least this many values is lowered to a jump table, if at least every second
+//entry of the table belongs to a value
+const JUMP_TABLE_MIN_CASES: usize = 4;
+
+#[derive(Clone)]
+struct Local {
+    var_type: TypeAST,
+    //offset from the frame pointer
+    offset: i64,
+    //nesting depth of the function it belongs to, top level functions have depth 0
+    depth: usize,
+    //a pointer to it may exist
+    addressed: bool,
+}
+
+#[derive(Clone)]
+struct Global {
+    var_type: TypeAST,
+    label: String,
+}
+
+#[derive(Clone)]
+struct Callee {
+    label: String,
+    depth: usize,
+}
+
+///a function that is generated after the current one
+struct PendingFunction {
+    func: FunctionAST,
+    callee: Callee,
+}
+
+pub struct Codegen<'a> {
+    target: &'a Target,
+    word_bits: u32,
+    word_bytes: i64,
+    //frame and stack pointer of the target
+    fp: u8,
+    sp: u8,
+    text: Vec<AsmLine>,
+    data: Vec<AsmLine>,
+    errors: Vec<Diagnostic>,
+    types: TypeTable,
+    label_count: usize,
+    //labels of the functions that are generated or queued
+    functions: HashMap<SymbolId, Callee>,
+    pending: Vec<PendingFunction>,
+    globals: HashMap<SymbolId, Global>,
+    //the evaluated values of the constants
+    constants: HashMap<SymbolId, ExprAST>,
+    //locals of the current function and the functions around it
+    locals: HashMap<SymbolId, Local>,
+    //the variables of the current function whose address is taken
+    addressed: HashSet<SymbolId>,
+    frame_size: i64,
+    //set once the frame of the current function is reported as too large
+    frame_too_large: bool,
+    fn_label: String,
+    //nesting depth of the current function
+    depth: usize,
+    //labels continue and break jump to, the innermost loop is at the end
+    loops: Vec<(String, String)>,
+    //labels of the print routines that are called
+    routines: BTreeSet<&'static str>,
+    //the strings bools are printed as are needed
+    bool_strings: bool,
+}
+
+fn unsupported(what: &str, span: Span) -> Diagnostic {
+    Diagnostic::error(
+        diagnostics::UNSUPPORTED_BY_BACKEND,
+        format!("{} is not supported by the code generator", what),
+        span,
+    )
+}
+
+impl<'a> Codegen<'a> {
+    pub fn new(target: &'a Target) -> Self {
+        Self {
+            target,
+            word_bits: target.word_bits,
+            word_bytes: target.word_bytes(),
+            fp: target.frame_pointer(),
+            sp: target.stack_pointer(),
+            text: Vec::new(),
+            data: Vec::new(),
+            errors: Vec::new(),
+            types: TypeTable::new(),
+            label_count: 0,
+            functions: HashMap::new(),
+            pending: Vec::new(),
+            globals: HashMap::new(),
+            constants: HashMap::new(),
+            locals: HashMap::new(),
+            addressed: HashSet::new(),
+            frame_size: 0,
+            frame_too_large: false,
+            fn_label: String::new(),
+            depth: 0,
+            loops: Vec::new(),
+            routines: BTreeSet::new(),
+            bool_strings: false,
+        }
+    }
+
+    fn emit(&mut self, op: Op, operands: Vec<Operand>) {
+        self.text.push(AsmLine::Instr(Instr { op, operands }));
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label_count += 1;
+        format!(".L{}", self.label_count)
+    }
+
+    ///labels the functions defined directly in body and queues them for generation, parent is the
+    ///label of the enclosing function. nested functions can be shadowed, so their labels contain
+    ///their id
+    fn add_functions(&mut self, body: &BodyAST, parent: Option<&str>) {
+        for stmt in &body.stmts {
+            if let StmtAST::Function(func) = stmt {
+                let id = func
+                    .fn_signt
+                    .id
+                    .expect("the typechecker gave every function an id");
+                let name = &func.fn_signt.name;
+                let callee = match parent {
+                    Some(parent) => Callee {
+                        label: format!("{}.{}_{}", parent, name, id.0),
+                        depth: self.depth + 1,
+                    },
+                    None => Callee {
+                        label: format!("fn_{}", name),
+                        depth: 0,
+                    },
+                };
+                self.functions.insert(id, callee.clone());
+                self.pending.push(PendingFunction {
+                    func: func.clone(),
+                    callee,
+                });
+            }
+        }
+    }
+
+    fn resolve_global(&self, var: &VariableAST) -> Option<Global> {
+        var.id.and_then(|id| self.globals.get(&id)).cloned()
+    }
+
+    ///the local var refers to, it can belong to an enclosing function, which is generated before
+    fn resolve_local(&self, var: &VariableAST) -> Local {
+        var.id
+            .and_then(|id| self.locals.get(&id))
+            .cloned()
+            .expect("the typechecker resolved every variable")
+    }
+
+    ///reports types that don't fit into a register of the target
+    fn check_width(&mut self, tp: &TypeAST, span: Span) {
+        if let TypeAST::Array(element, _) = tp {
+            return self.check_width(element, span);
+        }
+        if tp.int_bits().is_some_and(|bits| bits > self.word_bits) {
+            self.errors.push(
+                unsupported(&format!("`{}` on this target", tp), span).with_note(format!(
+                    "`{}` has {}-bit registers",
+                    self.target.name, self.word_bits
+                )),
+            );
+        }
+    }
+
+    ///the integer type values of tp are stored as, enums are stored as their representation
+    fn scalar_type(&self, tp: &TypeAST) -> TypeAST {
+        self.types
+            .enum_of(tp)
+            .map_or_else(|| tp.clone(), |def| def.repr())
+    }
+
+    ///layout of the struct if tp is one
+    fn struct_layout(&self, tp: &TypeAST) -> Option<StructLayout> {
+        let def = self.types.struct_of(tp)?;
+        Some(layout::struct_layout(def, self.target, &self.types))
+    }
+
+    ///size of the value if tp is a struct or an array, which are passed around by address
+    fn aggregate_size(&self, tp: &TypeAST) -> Option<i64> {
+        if self.types.struct_of(tp).is_none() && !matches!(tp, TypeAST::Array(..)) {
+            return None;
+        }
+        Some(layout::size_align(tp, self.target, &self.types).0)
+    }
+
+    ///reserves size bytes rounded up to whole words in the current frame and returns the offset
+    fn reserve(&mut self, size: i64) -> i64 {
+        let words = (size + self.word_bytes - 1) / self.word_bytes;
+        self.frame_size += words.max(1) * self.word_bytes;
+        -self.frame_size
+    }
+
+    ///reports the current frame once if it does not fit between the stack top and the start of the
+    ///memory
+    fn check_frame(&mut self, span: Span, label: &str) {
+        //the return address and the frame pointer of the caller are stored above the locals
+        let needed = self.frame_size + 2 * self.word_bytes;
+        if self.frame_too_large || needed as u64 <= self.target.stack_top {
+            return;
+        }
+        self.frame_too_large = true;
+        self.errors.push(
+            Diagnostic::error(
+                diagnostics::OUT_OF_MEMORY,
+                "the stack frame does not fit into the memory",
+                span,
+            )
+            .with_label(label)
+            .with_note(format!(
+                "the frame needs {} bytes, the stack of `{}` has {} bytes",
+                needed, self.target.name, self.target.stack_top
+            )),
+        );
+    }
+
+    ///places the global variable with its value in the data section
+    fn add_global(&mut self, global: &DeclAssignAST) {
+        let decl = &global.decl;
+        self.check_width(&decl.var_type, decl.span);
+        let (size, _) = layout::size_align(&decl.var_type, self.target, &self.types);
+        if size as u64 > self.target.memory_size {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::OUT_OF_MEMORY,
+                    "the global variable does not fit into the memory",
+                    decl.span,
+                )
+                .with_label(format!("`{}` needs {} bytes", decl.name, size))
+                .with_note(format!(
+                    "the memory of `{}` has {} bytes",
+                    self.target.name, self.target.memory_size
+                )),
+            );
+            return;
+        }
+        let words = ((size + self.word_bytes - 1) / self.word_bytes).max(1);
+        let mut bytes = vec![0; (words * self.word_bytes) as usize];
+        self.write_literal(&global.value, &mut bytes, 0);
+        let words = bytes
+            .chunks(self.word_bytes as usize)
+            .map(|word| Operand::Imm(self.target.decode_word(word) as i64))
+            .collect();
+        let label = format!("global_{}", decl.name);
+        self.data.push(AsmLine::Label(label.clone()));
+        self.data.push(AsmLine::Word(words));
+        let id = decl
+            .id
+            .expect("the typechecker gave every declaration an id");
+        let global = Global {
+            var_type: decl.var_type.clone(),
+            label,
+        };
+        self.globals.insert(id, global);
+    }
+
+    ///writes the evaluated value at offset into bytes, laid out like in memory
+    fn write_literal(&mut self, value: &ExprAST, bytes: &mut [u8], offset: usize) {
+        match value {
+            ExprAST::ArrayLiteral(ArrayLiteralAST { elements, tp, .. }) => {
+                let TypeAST::Array(element_tp, _) = tp else {
+                    unreachable!("the typechecker gave every array literal an array type");
+                };
+                let stride = layout::size_align(element_tp, self.target, &self.types).0 as usize;
+                for (i, element) in elements.iter().enumerate() {
+                    self.write_literal(element, bytes, offset + i * stride);
+                }
+            }
+            ExprAST::ArrayRepeat(lit) => {
+                let TypeAST::Array(element_tp, len) = &lit.tp else {
+                    unreachable!("the typechecker gave every array literal an array type");
+                };
+                let stride = layout::size_align(element_tp, self.target, &self.types).0 as usize;
+                for i in 0..*len as usize {
+                    self.write_literal(&lit.value, bytes, offset + i * stride);
+                }
+            }
+            ExprAST::StructLiteral(lit) => {
+                let layout = self
+                    .struct_layout(&TypeAST::Custom(lit.name.clone()))
+                    .expect("the typechecker resolved every struct literal");
+                for field in &lit.fields {
+                    let field_offset = layout.field(&field.name).offset as usize;
+                    self.write_literal(&field.value, bytes, offset + field_offset);
+                }
+            }
+            ExprAST::StringLiteral(lit) => self
+                .errors
+                .push(unsupported("strings in global variables", lit.span)),
+            scalar => {
+                let (tp, value) = match scalar {
+                    ExprAST::Number(num) => (num.tp.clone(), num.num),
+                    ExprAST::BoolLiteral(bl) => (TypeAST::Bool, bl.value as i64),
+                    ExprAST::CharLiteral(ch) => (TypeAST::Char, ch.value as i64),
+                    ExprAST::EnumVariant(variant) => {
+                        let tp = TypeAST::Custom(variant.enum_name.clone());
+                        let value = self
+                            .types
+                            .enum_of(&tp)
+                            .and_then(|def| def.discriminant(&variant.variant))
+                            .expect("the typechecker resolved every variant");
+                        (tp, value)
+                    }
+                    _ => unreachable!("the values of global variables are evaluated to literals"),
+                };
+                let size = layout::size_align(&tp, self.target, &self.types).0 as usize;
+                let le = value.to_le_bytes();
+                for i in 0..size {
+                    let byte = if self.target.big_endian {
+                        size - 1 - i
+                    } else {
+                        i
+                    };
+                    bytes[offset + i] = le[byte];
+                }
+            }
+        }
+    }
+
+    ///reserves a slot in the current frame for the declared variable
+    fn add_local(&mut self, decl: &DeclarationAST) -> Local {
+        self.check_width(&decl.var_type, decl.span);
+        let size = self
+            .aggregate_size(&decl.var_type)
+            .unwrap_or(self.word_bytes);
+        let offset = self.reserve(size);
+        self.check_frame(decl.span, "the stack is full after this declaration");
+        let id = decl
+            .id
+            .expect("the typechecker gave every declaration an id");
+        let local = Local {
+            var_type: decl.var_type.clone(),
+            offset,
+            depth: self.depth,
+            addressed: self.addressed.contains(&id),
+        };
+        self.locals.insert(id, local.clone());
+        local
+    }
+
+    ///wraps the accumulator around to the width of tp, like an overflow in a register of that size
+    fn normalize(&mut self, tp: &TypeAST) {
+        let Some(bits) = tp.int_bits() else {
+            return;
+        };
+        if bits >= self.word_bits {
+            return;
+        }
+        if tp.is_signed() {
+            //move the sign bit to the top and shift it back to extend it
+            let shift = (self.word_bits - bits) as i64;
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(shift)]);
+            self.emit(
+                Op::Shl,
+                vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+            );
+            self.emit(
+                Op::Sar,
+                vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+            );
+        } else {
+            let mask = (1i64 << bits) - 1;
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(mask)]);
+            self.emit(
+                Op::And,
+                vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+            );
+        }
+    }
+
+    ///sets reg to the address of the slot at offset in the current frame
+    fn gen_frame_address(&mut self, reg: u8, offset: i64) {
+        self.emit(Op::Ldi, vec![Operand::Reg(reg), Operand::Imm(offset)]);
+        self.emit(
+            Op::Add,
+            vec![Operand::Reg(reg), Operand::Reg(self.fp), Operand::Reg(reg)],
+        );
+    }
+
+    ///sets reg to the frame pointer of the frame hops levels of nesting out, by following the
+    ///static links
+    fn gen_frame_base(&mut self, reg: u8, hops: usize) {
+        self.emit(Op::Mov, vec![Operand::Reg(reg), Operand::Reg(self.fp)]);
+        for _ in 0..hops {
+            self.emit(
+                Op::Ldw,
+                vec![Operand::Reg(reg), Operand::Mem(reg, 2 * self.word_bytes)],
+            );
+        }
+    }
+
+    ///the register that holds the frame pointer of the function of local, the current frame pointer
+    ///or reg, which is set to the frame of an enclosing function
+    fn local_base(&mut self, reg: u8, local: &Local) -> u8 {
+        if local.depth == self.depth {
+            return self.fp;
+        }
+        self.gen_frame_base(reg, self.depth - local.depth);
+        reg
+    }
+
+    ///sets reg to the address at offset in the frame of local, overwrites TMP2 for locals of an
+    ///enclosing function
+    fn gen_local_address(&mut self, reg: u8, local: &Local, offset: i64) {
+        if local.depth == self.depth {
+            return self.gen_frame_address(reg, offset);
+        }
+        self.local_base(reg, local);
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(offset)]);
+        self.emit(
+            Op::Add,
+            vec![Operand::Reg(reg), Operand::Reg(reg), Operand::Reg(TMP2)],
+        );
+    }
+
+    ///loads the size bytes at the address in ACC + offset into ACC, zero extended, overwrites TMP2
+    fn gen_load(&mut self, size: i64, offset: i64) {
+        self.emit(Op::Ldw, vec![Operand::Reg(ACC), Operand::Mem(ACC, offset)]);
+        if size >= self.word_bytes {
+            return;
+        }
+        let arith = vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP2)];
+        if self.target.big_endian {
+            //the bytes are the highest ones of the loaded word
+            let shift = (self.word_bytes - size) * 8;
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(shift)]);
+            self.emit(Op::Shr, arith.clone());
+        }
+        let mask = (1i64 << (size * 8)) - 1;
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(mask)]);
+        self.emit(Op::And, arith);
+    }
+
+    ///stores the lowest size bytes of ACC at the address in TMP, the other bytes of the word at
+    ///that address are kept, overwrites ACC and TMP2
+    fn gen_store(&mut self, size: i64) {
+        let store = vec![Operand::Mem(TMP, 0), Operand::Reg(ACC)];
+        if size >= self.word_bytes {
+            self.emit(Op::Stw, store);
+            return;
+        }
+        let arith = vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP2)];
+        let shift = if self.target.big_endian {
+            (self.word_bytes - size) * 8
+        } else {
+            0
+        };
+        let mask = (1i64 << (size * 8)) - 1;
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(mask)]);
+        self.emit(Op::And, arith.clone());
+        if shift > 0 {
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(shift)]);
+            self.emit(Op::Shl, arith.clone());
+        }
+        self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+        self.emit(Op::Ldw, vec![Operand::Reg(ACC), Operand::Mem(TMP, 0)]);
+        //clear the bytes that are overwritten, the mask is written as an unsigned word
+        let keep = !(mask << shift) & ((1i64 << self.word_bits) - 1);
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(keep)]);
+        self.emit(Op::And, arith.clone());
+        self.emit(Op::Pop, vec![Operand::Reg(TMP2)]);
+        self.emit(Op::Or, arith);
+        self.emit(Op::Stw, store);
+    }
+
+    ///copies size bytes from the address in ACC to the address in TMP, overwrites all scratch
+    ///registers
+    fn gen_copy(&mut self, size: i64) {
+        let words = size / self.word_bytes;
+        if words > 0 {
+            let word = self.word_bytes;
+            let start = self.new_label();
+            //the end of the whole words of the source lives on the stack during the loop
+            self.emit(
+                Op::Ldi,
+                vec![Operand::Reg(TMP2), Operand::Imm(words * word)],
+            );
+            self.emit(
+                Op::Add,
+                vec![Operand::Reg(TMP2), Operand::Reg(ACC), Operand::Reg(TMP2)],
+            );
+            self.emit(Op::Push, vec![Operand::Reg(TMP2)]);
+            self.text.push(AsmLine::Label(start.clone()));
+            self.emit(Op::Ldw, vec![Operand::Reg(TMP2), Operand::Mem(ACC, 0)]);
+            self.emit(Op::Stw, vec![Operand::Mem(TMP, 0), Operand::Reg(TMP2)]);
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(word)]);
+            self.emit(
+                Op::Add,
+                vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP2)],
+            );
+            self.emit(
+                Op::Add,
+                vec![Operand::Reg(TMP), Operand::Reg(TMP), Operand::Reg(TMP2)],
+            );
+            self.emit(Op::Ldw, vec![Operand::Reg(TMP2), Operand::Mem(self.sp, 0)]);
+            self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP2)]);
+            self.emit(Op::Jb, vec![Operand::Label(start)]);
+            self.emit(Op::Pop, vec![Operand::Reg(TMP2)]);
+        }
+        //the bytes after the last whole word, ACC and TMP point at them after the loop
+        let rest = size % self.word_bytes;
+        if rest > 0 {
+            self.gen_load(rest, 0);
+            self.gen_store(rest);
+        }
+    }
+
+    ///stores the value in ACC at the address in TMP, structs and arrays are copied from the address
+    ///in ACC
+    fn gen_store_value(&mut self, tp: &TypeAST, size: i64) {
+        if self.aggregate_size(tp).is_some() {
+            self.gen_copy(size);
+        } else {
+            self.gen_store(size);
+        }
+    }
+
+    ///offset of the bytes of the local from the frame pointer, scalars are the low bytes of their
+    ///word
+    fn scalar_offset(&self, local: &Local) -> i64 {
+        if !self.target.big_endian || self.aggregate_size(&local.var_type).is_some() {
+            return local.offset;
+        }
+        let (size, _) = layout::size_align(&local.var_type, self.target, &self.types);
+        local.offset + self.word_bytes - size
+    }
+
+    ///loads the value of type tp at the address in ACC, structs and arrays stay addresses
+    fn gen_load_value(&mut self, tp: &TypeAST, size: i64) {
+        if self.aggregate_size(tp).is_some() {
+            return;
+        }
+        self.gen_load(size, 0);
+        let scalar = self.scalar_type(tp);
+        if scalar.is_signed() {
+            self.normalize(&scalar);
+        }
+    }
+
+    ///leaves the address of a field, an element or a pointee in ACC and returns its type and size
+    fn gen_place_address(&mut self, place: &ExprAST) -> Option<(TypeAST, i64)> {
+        match place {
+            ExprAST::FieldAccess(access) => {
+                //the address of a struct behind a pointer is the value of the pointer
+                let tp = match self.gen_expr(&access.expr) {
+                    TypeAST::Pointer(pointee) => *pointee,
+                    tp => tp,
+                };
+                let field = self.struct_layout(&tp)?.field(&access.field).clone();
+                if field.offset != 0 {
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(field.offset)]);
+                    self.emit(
+                        Op::Add,
+                        vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+                    );
+                }
+                Some((field.field_type, field.size))
+            }
+            ExprAST::Index(index) => {
+                let TypeAST::Array(element, _) = self.gen_expr(&index.expr) else {
+                    return None;
+                };
+                let (stride, _) = layout::size_align(&element, self.target, &self.types);
+                self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+                self.gen_expr(&index.index);
+                if stride != 1 {
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(stride)]);
+                    self.emit(
+                        Op::Mul,
+                        vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+                    );
+                }
+                self.emit(Op::Pop, vec![Operand::Reg(TMP)]);
+                self.emit(
+                    Op::Add,
+                    vec![Operand::Reg(ACC), Operand::Reg(TMP), Operand::Reg(ACC)],
+                );
+                Some((*element, stride))
+            }
+            ExprAST::Unary(unary) => {
+                let TypeAST::Pointer(pointee) = self.gen_expr(&unary.expr) else {
+                    return None;
+                };
+                let (size, _) = layout::size_align(&pointee, self.target, &self.types);
+                Some((*pointee, size))
+            }
+            _ => unreachable!("only fields, elements and pointees are places in memory"),
+        }
+    }
+
+    ///builds the array in a hidden local and leaves its address in ACC
+    fn gen_array_literal(&mut self, lit: &ArrayLiteralAST) -> TypeAST {
+        let TypeAST::Array(element_tp, len) = &lit.tp else {
+            unreachable!("the typechecker gave every array literal an array type");
+        };
+        let stride = layout::size_align(element_tp, self.target, &self.types).0;
+        let base = self.reserve(stride * *len as i64);
+        for (i, element) in lit.elements.iter().enumerate() {
+            self.gen_expr(element);
+            self.gen_frame_address(TMP, base + i as i64 * stride);
+            self.gen_store_value(element_tp, stride);
+        }
+        self.gen_frame_address(ACC, base);
+        lit.tp.clone()
+    }
+
+    ///fills a hidden local with copies of the value in a loop and leaves its address in ACC
+    fn gen_array_repeat(&mut self, lit: &ArrayRepeatAST) -> TypeAST {
+        let element = self.gen_expr(&lit.value);
+        let len = lit.len.num;
+        let stride = layout::size_align(&element, self.target, &self.types).0;
+        let base = self.reserve(stride * len);
+        if len > 0 {
+            let word = self.word_bytes;
+            let start = self.new_label();
+            //the value and the address of the next element live on the stack during the loop
+            self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+            self.gen_frame_address(ACC, base);
+            self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+            self.text.push(AsmLine::Label(start.clone()));
+            self.emit(Op::Ldw, vec![Operand::Reg(TMP), Operand::Mem(self.sp, 0)]);
+            self.emit(
+                Op::Ldw,
+                vec![Operand::Reg(ACC), Operand::Mem(self.sp, word)],
+            );
+            self.gen_store_value(&element, stride);
+            self.emit(Op::Ldw, vec![Operand::Reg(ACC), Operand::Mem(self.sp, 0)]);
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(stride)]);
+            self.emit(
+                Op::Add,
+                vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+            );
+            self.emit(Op::Stw, vec![Operand::Mem(self.sp, 0), Operand::Reg(ACC)]);
+            self.gen_frame_address(TMP, base + stride * len);
+            self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+            self.emit(Op::Jb, vec![Operand::Label(start)]);
+            self.emit(Op::Pop, vec![Operand::Reg(TMP)]);
+            self.emit(Op::Pop, vec![Operand::Reg(TMP)]);
+        }
+        self.gen_frame_address(ACC, base);
+        TypeAST::Array(Box::new(element), len as u64)
+    }
+
+    ///builds the struct in a hidden local and leaves its address in ACC
+    fn gen_struct_literal(&mut self, lit: &StructLiteralAST) -> TypeAST {
+        let tp = TypeAST::Custom(lit.name.clone());
+        let Some(layout) = self.struct_layout(&tp) else {
+            return TypeAST::Error;
+        };
+        let base = self.reserve(layout.size);
+        for init in &lit.fields {
+            let field = layout.field(&init.name);
+            self.gen_expr(&init.value);
+            self.gen_frame_address(TMP, base + field.offset);
+            self.gen_store_value(&field.field_type, field.size);
+        }
+        self.gen_frame_address(ACC, base);
+        tp
+    }
+
+    ///sets the accumulator to 1 if the jump is taken after comparing the operands, 0 otherwise
+    fn gen_compare(&mut self, jump: Op, lhs: u8, rhs: u8) {
+        let done = self.new_label();
+        self.emit(Op::Cmp, vec![Operand::Reg(lhs), Operand::Reg(rhs)]);
+        self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Imm(1)]);
+        self.emit(jump, vec![Operand::Label(done.clone())]);
+        self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Imm(0)]);
+        self.text.push(AsmLine::Label(done));
+    }
+
+    fn gen_call(&mut self, call: &CallAST) -> TypeAST {
+        for arg in &call.args {
+            self.gen_expr(arg);
+            self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+        }
+        let callee = call
+            .id
+            .and_then(|id| self.functions.get(&id))
+            .expect("the typechecker resolved every call")
+            .clone();
+        //the static link, the callee is visible here, so its enclosing function is the current one
+        //or one around it
+        let mut words = call.args.len() as i64;
+        if callee.depth > 0 {
+            self.gen_frame_base(ACC, self.depth + 1 - callee.depth);
+            self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+            words += 1;
+        }
+        self.emit(Op::Call, vec![Operand::Label(callee.label)]);
+        if words > 0 {
+            let size = words * self.word_bytes;
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(size)]);
+            self.emit(
+                Op::Add,
+                vec![
+                    Operand::Reg(self.sp),
+                    Operand::Reg(self.sp),
+                    Operand::Reg(TMP),
+                ],
+            );
+        }
+        call.tp.clone()
+    }
+
+    fn gen_binary_expr(&mut self, expr: &BinaryExpressionAST) -> TypeAST {
+        let mut tp = self.gen_expr(&expr.lhs);
+        self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+        let rhs_tp = self.gen_expr(&expr.rhs);
+        self.emit(Op::Mov, vec![Operand::Reg(TMP), Operand::Reg(ACC)]);
+        self.emit(Op::Pop, vec![Operand::Reg(ACC)]);
+
+        //the integer added to a pointer counts pointees
+        let scaled = match (&tp, &rhs_tp) {
+            (TypeAST::Pointer(pointee), int) if int.is_integer() => Some((pointee.clone(), TMP)),
+            (int, TypeAST::Pointer(pointee)) if int.is_integer() => Some((pointee.clone(), ACC)),
+            _ => None,
+        };
+        if let Some((pointee, reg)) = scaled {
+            let (size, _) = layout::size_align(&pointee, self.target, &self.types);
+            if size != 1 {
+                self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(size)]);
+                self.emit(
+                    Op::Mul,
+                    vec![Operand::Reg(reg), Operand::Reg(reg), Operand::Reg(TMP2)],
+                );
+            }
+            tp = TypeAST::Pointer(pointee);
+        }
+
+        let arith = |op| vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(op)];
+        let signed = tp.is_signed();
+        match &expr.op {
+            Token::Plus if tp == TypeAST::Str => {
+                self.errors
+                    .push(unsupported("string concatenation", expr.span));
+                return TypeAST::Str;
+            }
+            Token::Plus => self.emit(Op::Add, arith(TMP)),
+            Token::Minus => self.emit(Op::Sub, arith(TMP)),
+            Token::Mult => self.emit(Op::Mul, arith(TMP)),
+            Token::Divide if signed => self.emit(Op::Divs, arith(TMP)),
+            Token::Divide => self.emit(Op::Div, arith(TMP)),
+            Token::AndInt | Token::AndBool => self.emit(Op::And, arith(TMP)),
+            Token::OrInt | Token::OrBool => self.emit(Op::Or, arith(TMP)),
+            Token::XorInt | Token::XorBool => self.emit(Op::Xor, arith(TMP)),
+            Token::LeftShift | Token::RightShift => {
+                //the shift amount wraps around at the width of the type
+                let bits = tp.int_bits().unwrap_or(self.word_bits) as i64;
+                self.emit(Op::Ldi, vec![Operand::Reg(TMP2), Operand::Imm(bits - 1)]);
+                self.emit(
+                    Op::And,
+                    vec![Operand::Reg(TMP), Operand::Reg(TMP), Operand::Reg(TMP2)],
+                );
+                let op = match (&expr.op, signed) {
+                    (Token::LeftShift, _) => Op::Shl,
+                    (_, true) => Op::Sar,
+                    (_, false) => Op::Shr,
+                };
+                self.emit(op, arith(TMP));
+            }
+            Token::Equal | Token::Unequal if tp == TypeAST::Str => {
+                self.errors
+                    .push(unsupported("comparing strings", expr.span));
+                return TypeAST::Bool;
+            }
+            Token::Equal => {
+                self.gen_compare(Op::Jeq, ACC, TMP);
+                return TypeAST::Bool;
+            }
+            Token::Unequal => {
+                self.gen_compare(Op::Jne, ACC, TMP);
+                return TypeAST::Bool;
+            }
+            Token::LessThan | Token::GreaterThan => {
+                let jump = if signed { Op::Jlt } else { Op::Jb };
+                //a > b is checked as b < a
+                if expr.op == Token::LessThan {
+                    self.gen_compare(jump, ACC, TMP);
+                } else {
+                    self.gen_compare(jump, TMP, ACC);
+                }
+                return TypeAST::Bool;
+            }
+            other => {
+                self.errors
+                    .push(unsupported(&format!("the operator `{}`", other), expr.span));
+                return tp;
+            }
+        }
+        self.normalize(&tp);
+        tp
+    }
+
+    ///generates code that leaves the value of expr in the accumulator and returns its type
+    fn gen_expr(&mut self, expr: &ExprAST) -> TypeAST {
+        match expr {
+            ExprAST::Variable(var) => {
+                if let Some(value) = var.id.and_then(|id| self.constants.get(&id)) {
+                    return self.gen_expr(&value.clone());
+                }
+                if let Some(global) = self.resolve_global(var) {
+                    let (size, _) = layout::size_align(&global.var_type, self.target, &self.types);
+                    self.emit(
+                        Op::Ldi,
+                        vec![Operand::Reg(ACC), Operand::Label(global.label)],
+                    );
+                    self.gen_load_value(&global.var_type, size);
+                    return global.var_type;
+                }
+                let local = self.resolve_local(var);
+                let tp = local.var_type.clone();
+                if self.aggregate_size(&tp).is_some() {
+                    self.gen_local_address(ACC, &local, local.offset);
+                } else if local.addressed {
+                    let (size, _) = layout::size_align(&tp, self.target, &self.types);
+                    self.gen_local_address(ACC, &local, self.scalar_offset(&local));
+                    self.gen_load_value(&tp, size);
+                } else {
+                    let base = self.local_base(ACC, &local);
+                    self.emit(
+                        Op::Ldw,
+                        vec![Operand::Reg(ACC), Operand::Mem(base, local.offset)],
+                    );
+                }
+                tp
+            }
+            ExprAST::Call(call) => self.gen_call(call),
+            ExprAST::BinaryExpression(bin_expr) => self.gen_binary_expr(bin_expr),
+            ExprAST::Number(num) => {
+                self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Imm(num.num)]);
+                num.tp.clone()
+            }
+            ExprAST::StringLiteral(lit) => {
+                let label = format!("str{}", self.data.len());
+                self.data.push(AsmLine::Label(label.clone()));
+                self.data.push(AsmLine::Str(lit.str.clone()));
+                self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Label(label)]);
+                TypeAST::Str
+            }
+            ExprAST::BoolLiteral(bl) => {
+                self.emit(
+                    Op::Ldi,
+                    vec![Operand::Reg(ACC), Operand::Imm(bl.value as i64)],
+                );
+                TypeAST::Bool
+            }
+            ExprAST::CharLiteral(ch) => {
+                self.emit(
+                    Op::Ldi,
+                    vec![Operand::Reg(ACC), Operand::Imm(ch.value as i64)],
+                );
+                TypeAST::Char
+            }
+            ExprAST::StructLiteral(lit) => self.gen_struct_literal(lit),
+            ExprAST::Cast(cast) => {
+                let from = self.gen_expr(&cast.expr);
+                self.check_width(&cast.target, cast.span);
+                match &cast.target {
+                    TypeAST::Bool if from != TypeAST::Bool => {
+                        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                        self.gen_compare(Op::Jne, ACC, TMP);
+                    }
+                    //chars are bytes
+                    TypeAST::Char => self.normalize(&TypeAST::U8),
+                    tp => self.normalize(tp),
+                }
+                cast.target.clone()
+            }
+            ExprAST::Unary(unary) if unary.op == Token::AndInt => {
+                let ExprAST::Variable(var) = &unary.expr else {
+                    return match self.gen_place_address(&unary.expr) {
+                        Some((tp, _)) => TypeAST::Pointer(Box::new(tp)),
+                        None => TypeAST::Error,
+                    };
+                };
+                if let Some(global) = self.resolve_global(var) {
+                    self.emit(
+                        Op::Ldi,
+                        vec![Operand::Reg(ACC), Operand::Label(global.label)],
+                    );
+                    return TypeAST::Pointer(Box::new(global.var_type));
+                }
+                let local = self.resolve_local(var);
+                self.gen_local_address(ACC, &local, self.scalar_offset(&local));
+                TypeAST::Pointer(Box::new(local.var_type))
+            }
+            ExprAST::Unary(unary) if unary.op == Token::Minus => {
+                let tp = self.gen_expr(&unary.expr);
+                self.emit(Op::Mov, vec![Operand::Reg(TMP), Operand::Reg(ACC)]);
+                self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Imm(0)]);
+                self.emit(
+                    Op::Sub,
+                    vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(TMP)],
+                );
+                self.normalize(&tp);
+                tp
+            }
+            ExprAST::FieldAccess(_) | ExprAST::Index(_) | ExprAST::Unary(_) => {
+                let Some((tp, size)) = self.gen_place_address(expr) else {
+                    return TypeAST::Error;
+                };
+                self.gen_load_value(&tp, size);
+                tp
+            }
+            ExprAST::ArrayLiteral(lit) => self.gen_array_literal(lit),
+            ExprAST::ArrayRepeat(lit) => self.gen_array_repeat(lit),
+            ExprAST::EnumVariant(variant) => {
+                let tp = TypeAST::Custom(variant.enum_name.clone());
+                let value = self
+                    .types
+                    .enum_of(&tp)
+                    .and_then(|def| def.discriminant(&variant.variant))
+                    .expect("the typechecker resolved every variant");
+                self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Imm(value)]);
+                tp
+            }
+        }
+    }
+
+    ///writes the low byte of reg to the output port, overwrites TMP
+    fn gen_write_byte(&mut self, reg: u8) {
+        let port = self.target.io_out as i64;
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(port)]);
+        self.emit(Op::Stw, vec![Operand::Mem(TMP, 0), Operand::Reg(reg)]);
+    }
+
+    fn call_routine(&mut self, routine: &'static str) {
+        self.routines.insert(routine);
+        self.emit(Op::Call, vec![Operand::Label(routine.to_string())]);
+    }
+
+    fn gen_print(&mut self, print: &PrintStmtAST) {
+        self.gen_expr(&print.expr);
+        match print.format {
+            PrintFormat::Char => self.gen_write_byte(ACC),
+            PrintFormat::Str => self.call_routine("__print_str"),
+            PrintFormat::Bool => {
+                let done = self.new_label();
+                self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+                let text = |value| Operand::Label(format!("__str_{}", value));
+                self.emit(Op::Ldi, vec![Operand::Reg(ACC), text("true")]);
+                self.emit(Op::Jne, vec![Operand::Label(done.clone())]);
+                self.emit(Op::Ldi, vec![Operand::Reg(ACC), text("false")]);
+                self.text.push(AsmLine::Label(done));
+                self.call_routine("__print_str");
+                self.bool_strings = true;
+            }
+            PrintFormat::Unsigned => self.call_routine("__print_uint"),
+            PrintFormat::Signed => self.call_routine("__print_int"),
+            //the typechecker resolves the format of every print
+            PrintFormat::Unresolved => unreachable!("print of an unchecked expression"),
+        }
+    }
+
+    ///emits the print routines that were called and the strings they need
+    fn gen_routines(&mut self) {
+        let label = |name: &str| AsmLine::Label(name.to_string());
+        let jump = |name: &str| vec![Operand::Label(name.to_string())];
+        let regs = |a, b, c| vec![Operand::Reg(a), Operand::Reg(b), Operand::Reg(c)];
+        if self.routines.contains("__print_int") {
+            self.routines.insert("__print_uint");
+        }
+        if self.bool_strings {
+            for value in ["true", "false"] {
+                self.data.push(label(&format!("__str_{}", value)));
+                self.data.push(AsmLine::Str(value.to_string()));
+            }
+        }
+        for routine in std::mem::take(&mut self.routines) {
+            self.text.push(label(routine));
+            match routine {
+                "__print_int" => {
+                    //negative numbers are written as '-' and the unsigned negated number
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                    self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+                    self.emit(Op::Jlt, jump("__print_int.neg"));
+                    self.emit(Op::Jmp, jump("__print_uint"));
+                    self.text.push(label("__print_int.neg"));
+                    self.emit(Op::Mov, vec![Operand::Reg(TMP2), Operand::Reg(ACC)]);
+                    self.emit(Op::Ldi, vec![Operand::Reg(ACC), Operand::Imm(b'-' as i64)]);
+                    self.gen_write_byte(ACC);
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                    self.emit(Op::Sub, regs(ACC, TMP, TMP2));
+                    self.emit(Op::Jmp, jump("__print_uint"));
+                }
+                "__print_uint" => {
+                    //the digits are pushed from the lowest one up, above a 0 that ends them
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                    self.emit(Op::Push, vec![Operand::Reg(TMP)]);
+                    self.text.push(label("__print_uint.digit"));
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(10)]);
+                    self.emit(Op::Mov, vec![Operand::Reg(TMP2), Operand::Reg(ACC)]);
+                    self.emit(Op::Div, regs(ACC, ACC, TMP));
+                    self.emit(Op::Mul, regs(TMP, ACC, TMP));
+                    self.emit(Op::Sub, regs(TMP2, TMP2, TMP));
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(b'0' as i64)]);
+                    self.emit(Op::Add, regs(TMP2, TMP2, TMP));
+                    self.emit(Op::Push, vec![Operand::Reg(TMP2)]);
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                    self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+                    self.emit(Op::Jne, jump("__print_uint.digit"));
+                    self.text.push(label("__print_uint.write"));
+                    self.emit(Op::Pop, vec![Operand::Reg(ACC)]);
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                    self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+                    self.emit(Op::Jeq, jump("__print_uint.ret"));
+                    self.gen_write_byte(ACC);
+                    self.emit(Op::Jmp, jump("__print_uint.write"));
+                    self.text.push(label("__print_uint.ret"));
+                    self.emit(Op::Ret, vec![]);
+                }
+                _ => {
+                    //__print_str, TMP2 points at the next character
+                    self.emit(Op::Mov, vec![Operand::Reg(TMP2), Operand::Reg(ACC)]);
+                    self.text.push(label("__print_str.loop"));
+                    self.emit(Op::Ldw, vec![Operand::Reg(ACC), Operand::Mem(TMP2, 0)]);
+                    if self.target.big_endian {
+                        //the character is the highest byte of the loaded word
+                        let shift = (self.word_bytes - 1) * 8;
+                        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(shift)]);
+                        self.emit(Op::Shr, regs(ACC, ACC, TMP));
+                    }
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0xff)]);
+                    self.emit(Op::And, regs(ACC, ACC, TMP));
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+                    self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+                    self.emit(Op::Jeq, jump("__print_str.ret"));
+                    self.gen_write_byte(ACC);
+                    self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(1)]);
+                    self.emit(Op::Add, regs(TMP2, TMP2, TMP));
+                    self.emit(Op::Jmp, jump("__print_str.loop"));
+                    self.text.push(label("__print_str.ret"));
+                    self.emit(Op::Ret, vec![]);
+                }
+            }
+        }
+    }
+
+    ///generates one link of an else if chain, every taken branch jumps to end
+    fn gen_if(&mut self, if_st: &IfStmtAST, end: &str) {
+        let Some(else_branch) = &if_st.else_branch else {
+            self.gen_jump_if_false(&if_st.condition, end);
+            self.gen_body(&if_st.body);
+            return;
+        };
+        let next = self.new_label();
+        self.gen_jump_if_false(&if_st.condition, &next);
+        self.gen_body(&if_st.body);
+        self.emit(Op::Jmp, vec![Operand::Label(end.to_string())]);
+        self.text.push(AsmLine::Label(next));
+        match else_branch {
+            ElseAST::If(else_if) => self.gen_if(else_if, end),
+            ElseAST::Body(body) => self.gen_body(body),
+        }
+    }
+
+    ///the value a pattern compares against, None for `_`
+    fn pattern_value(&self, pattern: &PatternAST) -> Option<i64> {
+        match pattern {
+            PatternAST::Variant(variant) => self
+                .types
+                .resolve_enum(&variant.enum_name)
+                .and_then(|def| def.discriminant(&variant.variant)),
+            PatternAST::Number(num) => Some(num.num),
+            PatternAST::Char(ch) => Some(ch.value as i64),
+            PatternAST::Bool(bl) => Some(bl.value as i64),
+            PatternAST::Wildcard(_) => None,
+        }
+    }
+
+    ///jumps to the label of the value in ACC, or to default if the value is outside of the table
+    fn gen_jump_table(&mut self, cases: &[(i64, String)], default: &str) {
+        let min = cases.iter().map(|(value, _)| *value).min().unwrap_or(0);
+        let max = cases.iter().map(|(value, _)| *value).max().unwrap_or(0);
+        let entries = (min..=max)
+            .map(|value| {
+                let label = cases
+                    .iter()
+                    .find(|(case, _)| *case == value)
+                    .map_or(default, |(_, label)| label.as_str());
+                Operand::Label(label.to_string())
+            })
+            .collect();
+        let table = self.new_label();
+        self.data.push(AsmLine::Label(table.clone()));
+        self.data.push(AsmLine::Word(entries));
+
+        let arith = |op| vec![Operand::Reg(ACC), Operand::Reg(ACC), Operand::Reg(op)];
+        //values below min wrap around to large unsigned indices, so one check covers both ends
+        if min != 0 {
+            self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(min)]);
+            self.emit(Op::Sub, arith(TMP));
+        }
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(max - min)]);
+        self.emit(Op::Cmp, vec![Operand::Reg(TMP), Operand::Reg(ACC)]);
+        self.emit(Op::Jb, vec![Operand::Label(default.to_string())]);
+        let shift = self.word_bytes.trailing_zeros() as i64;
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(shift)]);
+        self.emit(Op::Shl, arith(TMP));
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Label(table)]);
+        self.emit(Op::Add, arith(TMP));
+        self.emit(Op::Ldw, vec![Operand::Reg(ACC), Operand::Mem(ACC, 0)]);
+        self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+        self.emit(Op::Ret, vec![]);
+    }
+
+    fn gen_match(&mut self, match_st: &MatchStmtAST) {
+        let end = self.new_label();
+        let arms: Vec<String> = match_st.arms.iter().map(|_| self.new_label()).collect();
+        let mut cases: Vec<(i64, String)> = Vec::new();
+        let mut default = end.clone();
+        //the typechecker rejects unreachable patterns, so every value and `_` appears once
+        for (arm, label) in match_st.arms.iter().zip(&arms) {
+            for pattern in &arm.patterns {
+                match self.pattern_value(pattern) {
+                    Some(value) => cases.push((value, label.clone())),
+                    None => default = label.clone(),
+                }
+            }
+        }
+
+        self.gen_expr(&match_st.scrutinee);
+        let min = cases.iter().map(|(value, _)| *value).min().unwrap_or(0);
+        let max = cases.iter().map(|(value, _)| *value).max().unwrap_or(0);
+        if cases.len() >= JUMP_TABLE_MIN_CASES && max - min < 2 * cases.len() as i64 {
+            self.gen_jump_table(&cases, &default);
+        } else {
+            for (value, label) in &cases {
+                self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(*value)]);
+                self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+                self.emit(Op::Jeq, vec![Operand::Label(label.clone())]);
+            }
+            self.emit(Op::Jmp, vec![Operand::Label(default)]);
+        }
+
+        for (arm, label) in match_st.arms.iter().zip(arms) {
+            self.text.push(AsmLine::Label(label));
+            self.gen_body(&arm.body);
+            self.emit(Op::Jmp, vec![Operand::Label(end.clone())]);
+        }
+        self.text.push(AsmLine::Label(end));
+    }
+
+    ///evaluates the condition and jumps to label if it is false
+    fn gen_jump_if_false(&mut self, condition: &ExprAST, label: &str) {
+        self.gen_expr(condition);
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+        self.emit(Op::Cmp, vec![Operand::Reg(ACC), Operand::Reg(TMP)]);
+        self.emit(Op::Jeq, vec![Operand::Label(label.to_string())]);
+    }
+
+    ///stores the value in ACC in local, structs and arrays are copied from the address in ACC
+    fn store_local(&mut self, local: &Local) {
+        if let Some(size) = self.aggregate_size(&local.var_type) {
+            self.gen_local_address(TMP, local, local.offset);
+            self.gen_copy(size);
+            return;
+        }
+        let base = self.local_base(TMP, local);
+        self.emit(
+            Op::Stw,
+            vec![Operand::Mem(base, local.offset), Operand::Reg(ACC)],
+        );
+    }
+
+    fn gen_body(&mut self, body: &BodyAST) {
+        let parent = self.fn_label.clone();
+        self.add_functions(body, Some(&parent));
+
+        for stmt in &body.stmts {
+            match stmt {
+                StmtAST::Declaration(decl) => {
+                    self.add_local(decl);
+                }
+                StmtAST::DeclAssign(declassg) => {
+                    self.gen_expr(&declassg.value);
+                    let local = self.add_local(&declassg.decl);
+                    self.store_local(&local);
+                }
+                StmtAST::Assign(assign) => {
+                    self.gen_expr(&assign.value);
+                    match &assign.target {
+                        ExprAST::Variable(var) => {
+                            if let Some(global) = self.resolve_global(var) {
+                                let tp = global.var_type;
+                                let (size, _) = layout::size_align(&tp, self.target, &self.types);
+                                self.emit(
+                                    Op::Ldi,
+                                    vec![Operand::Reg(TMP), Operand::Label(global.label)],
+                                );
+                                self.gen_store_value(&tp, size);
+                            } else {
+                                let local = self.resolve_local(var);
+                                self.store_local(&local);
+                            }
+                        }
+                        place => {
+                            self.emit(Op::Push, vec![Operand::Reg(ACC)]);
+                            let field = self.gen_place_address(place);
+                            self.emit(Op::Mov, vec![Operand::Reg(TMP), Operand::Reg(ACC)]);
+                            self.emit(Op::Pop, vec![Operand::Reg(ACC)]);
+                            if let Some((tp, size)) = field {
+                                self.gen_store_value(&tp, size);
+                            }
+                        }
+                    }
+                }
+                StmtAST::Call(call) => {
+                    self.gen_call(call);
+                }
+                //nested functions are generated after the current one
+                StmtAST::Function(_) => {}
+                //only the layout is needed, it is computed where the struct is used
+                StmtAST::Struct(_) | StmtAST::Enum(_) => {}
+                //only at the top level, where they are handled before the functions
+                StmtAST::Const(_) => {}
+                StmtAST::Return(rt) => {
+                    self.gen_expr(&rt.expr);
+                    let ret = format!("{}$ret", self.fn_label);
+                    self.emit(Op::Jmp, vec![Operand::Label(ret)]);
+                }
+                StmtAST::If(if_st) => {
+                    let end = self.new_label();
+                    self.gen_if(if_st, &end);
+                    self.text.push(AsmLine::Label(end));
+                }
+                StmtAST::Match(match_st) => self.gen_match(match_st),
+                StmtAST::While(while_st) => {
+                    let start = self.new_label();
+                    let end = self.new_label();
+                    self.text.push(AsmLine::Label(start.clone()));
+                    self.gen_jump_if_false(&while_st.condition, &end);
+                    self.loops.push((start.clone(), end.clone()));
+                    self.gen_body(&while_st.body);
+                    self.loops.pop();
+                    self.emit(Op::Jmp, vec![Operand::Label(start)]);
+                    self.text.push(AsmLine::Label(end));
+                }
+                StmtAST::Print(print) => self.gen_print(print),
+                StmtAST::Break(_) | StmtAST::Continue(_) => {
+                    //the typechecker only allows them inside of loops
+                    let (start, end) = self.loops.last().cloned().expect("jump outside of loop");
+                    let target = if matches!(stmt, StmtAST::Break(_)) {
+                        end
+                    } else {
+                        start
+                    };
+                    self.emit(Op::Jmp, vec![Operand::Label(target)]);
+                }
+            }
+        }
+    }
+
+    fn gen_function(&mut self, pending: PendingFunction) {
+        let PendingFunction { func, callee } = pending;
+        let label = callee.label;
+        //the locals of the enclosing functions are kept, the nested ones can use them
+        self.locals.retain(|_, local| local.depth < callee.depth);
+        self.depth = callee.depth;
+        self.addressed.clear();
+        addressed_variables(&func.body, &mut self.addressed);
+        self.frame_size = 0;
+        self.frame_too_large = false;
+        self.fn_label = label.clone();
+
+        self.check_width(&func.fn_signt.rt_type, func.fn_signt.span);
+        let signature_types = func
+            .fn_signt
+            .args
+            .iter()
+            .map(|arg| (&arg.var_type, arg.span))
+            .chain([(&func.fn_signt.rt_type, func.fn_signt.span)]);
+        for (tp, span) in signature_types {
+            if self.aggregate_size(tp).is_some() {
+                self.errors.push(unsupported(
+                    "passing structs and arrays to and from functions",
+                    span,
+                ));
+            }
+        }
+        let n = func.fn_signt.args.len() as i64;
+        //the static link is between the return address and the arguments
+        let first = if self.depth > 0 { 3 } else { 2 } * self.word_bytes;
+        for (i, arg) in func.fn_signt.args.iter().enumerate() {
+            self.check_width(&arg.var_type, arg.span);
+            let id = arg.id.expect("the typechecker gave every argument an id");
+            let local = Local {
+                var_type: arg.var_type.clone(),
+                offset: first + (n - 1 - i as i64) * self.word_bytes,
+                depth: self.depth,
+                addressed: self.addressed.contains(&id),
+            };
+            self.locals.insert(id, local);
+        }
+
+        self.text.push(AsmLine::Label(label.clone()));
+        self.emit(Op::Push, vec![Operand::Reg(self.fp)]);
+        self.emit(Op::Mov, vec![Operand::Reg(self.fp), Operand::Reg(self.sp)]);
+        //the size of the frame is known after the body is generated
+        let reserve = self.text.len();
+        self.emit(Op::Ldi, vec![Operand::Reg(TMP), Operand::Imm(0)]);
+        self.emit(
+            Op::Sub,
+            vec![
+                Operand::Reg(self.sp),
+                Operand::Reg(self.sp),
+                Operand::Reg(TMP),
+            ],
+        );
+
+        self.gen_body(&func.body);
+        //literals are built in hidden locals, which can fill the stack after the last declaration
+        self.check_frame(
+            func.fn_signt.span,
+            "the values computed in this function don't fit on the stack",
+        );
+        self.text[reserve] = AsmLine::Instr(Instr {
+            op: Op::Ldi,
+            operands: vec![Operand::Reg(TMP), Operand::Imm(self.frame_size)],
+        });
+
+        //`$` can't be part of a name, so a nested function called ret doesn't collide
+        self.text.push(AsmLine::Label(format!("{}$ret", label)));
+        self.emit(Op::Mov, vec![Operand::Reg(self.sp), Operand::Reg(self.fp)]);
+        self.emit(Op::Pop, vec![Operand::Reg(self.fp)]);
+        self.emit(Op::Ret, vec![]);
+    }
+
+    ///generates the assembly for the whole program
+    pub fn generate(mut self, program: &BodyAST) -> (String, Vec<Diagnostic>) {
+        self.add_functions(program, None);
+        self.types.add_from_body(program);
+        for stmt in &program.stmts {
+            match stmt {
+                StmtAST::Struct(def) => {
+                    for field in &def.fields {
+                        self.check_width(&field.field_type, field.span);
+                    }
+                }
+                StmtAST::Enum(def) => self.check_width(&def.repr(), def.span),
+                StmtAST::Const(constant) => {
+                    self.check_width(&constant.decl.var_type, constant.decl.span);
+                    let id = constant
+                        .decl
+                        .id
+                        .expect("the typechecker gave every constant an id");
+                    self.constants.insert(id, constant.value.clone());
+                }
+                StmtAST::DeclAssign(global) => self.add_global(global),
+                _ => {}
+            }
+        }
+        let main = program.stmts.iter().find_map(|stmt| match stmt {
+            StmtAST::Function(func) if func.fn_signt.name == "main" => func.fn_signt.id,
+            _ => None,
+        });
+        let Some(main) = main.map(|id| self.functions[&id].label.clone()) else {
+            self.errors.push(Diagnostic::error(
+                diagnostics::MISSING_MAIN,
+                "`main` function not found",
+                program.span,
+            ));
+            return (String::new(), self.errors);
+        };
+
+        self.text.push(AsmLine::Org(self.target.code_start));
+        self.text.push(AsmLine::Label("_start".to_string()));
+        let stack_top = self.target.stack_top as i64;
+        self.emit(
+            Op::Ldi,
+            vec![Operand::Reg(self.sp), Operand::Imm(stack_top)],
+        );
+        self.emit(Op::Mov, vec![Operand::Reg(self.fp), Operand::Reg(self.sp)]);
+        self.emit(Op::Call, vec![Operand::Label(main)]);
+        self.emit(Op::Halt, vec![]);
+
+        //functions are generated in the order they are defined, nested ones after their parent
+        let mut queue = std::mem::take(&mut self.pending);
+        while !queue.is_empty() {
+            let pending = queue.remove(0);
+            self.gen_function(pending);
+            let nested = std::mem::take(&mut self.pending);
+            queue.splice(0..0, nested);
+        }
+        self.gen_routines();
+
+        let mut asm = String::new();
+        let lines = [
+            AsmLine::Comment("generated by MyLang".to_string()),
+            AsmLine::Comment("text".to_string()),
+        ];
+        let mut data_start = vec![AsmLine::Comment("data".to_string())];
+        //without a data section the data follows the code
+        if let Some(addr) = self.target.data_start {
+            data_start.push(AsmLine::Org(addr));
+        }
+        for line in lines
+            .iter()
+            .chain(&self.text)
+            .chain(&data_start)
+            .chain(&self.data)
+        {
+            asm.push_str(&line.render(self.target));
+            asm.push('\n');
+        }
+        (asm, self.errors)
+    }
+}
+
+///collects the variables whose address is taken in the body, including the ones nested functions
+///take the address of, as they can point into the frame of the enclosing function
+fn addressed_variables(body: &BodyAST, ids: &mut HashSet<SymbolId>) {
+    for stmt in &body.stmts {
+        let exprs: Vec<&ExprAST> = match stmt {
+            StmtAST::Assign(assign) => vec![&assign.target, &assign.value],
+            StmtAST::Call(call) => call.args.iter().collect(),
+            StmtAST::DeclAssign(declassg) => vec![&declassg.value],
+            StmtAST::Return(rt) => vec![&rt.expr],
+            StmtAST::Print(print) => vec![&print.expr],
+            StmtAST::If(if_st) => {
+                let mut conditions = Vec::new();
+                let mut branch = Some(if_st);
+                while let Some(if_st) = branch {
+                    conditions.push(&if_st.condition);
+                    addressed_variables(&if_st.body, ids);
+                    branch = match &if_st.else_branch {
+                        Some(ElseAST::If(else_if)) => Some(else_if),
+                        Some(ElseAST::Body(body)) => {
+                            addressed_variables(body, ids);
+                            None
+                        }
+                        None => None,
+                    };
+                }
+                conditions
+            }
+            StmtAST::While(while_st) => {
+                addressed_variables(&while_st.body, ids);
+                vec![&while_st.condition]
+            }
+            StmtAST::Match(match_st) => {
+                for arm in &match_st.arms {
+                    addressed_variables(&arm.body, ids);
+                }
+                vec![&match_st.scrutinee]
+            }
+            StmtAST::Function(func) => {
+                addressed_variables(&func.body, ids);
+                vec![]
+            }
+            StmtAST::Declaration(_)
+            | StmtAST::Const(_)
+            | StmtAST::Struct(_)
+            | StmtAST::Enum(_)
+            | StmtAST::Break(_)
+            | StmtAST::Continue(_) => vec![],
+        };
+        for expr in exprs {
+            addressed_in_expr(expr, ids);
+        }
+    }
+}
+
+fn addressed_in_expr(expr: &ExprAST, ids: &mut HashSet<SymbolId>) {
+    let children: Vec<&ExprAST> = match expr {
+        ExprAST::Unary(unary) => {
+            if let (Token::AndInt, ExprAST::Variable(var)) = (&unary.op, &unary.expr) {
+                ids.extend(var.id);
+            }
+            vec![&unary.expr]
+        }
+        ExprAST::Call(call) => call.args.iter().collect(),
+        ExprAST::BinaryExpression(bin_expr) => vec![&bin_expr.lhs, &bin_expr.rhs],
+        ExprAST::StructLiteral(lit) => lit.fields.iter().map(|init| &init.value).collect(),
+        ExprAST::FieldAccess(access) => vec![&access.expr],
+        ExprAST::ArrayLiteral(lit) => lit.elements.iter().collect(),
+        ExprAST::ArrayRepeat(lit) => vec![&lit.value],
+        ExprAST::Index(index) => vec![&index.expr, &index.index],
+        ExprAST::Cast(cast) => vec![&cast.expr],
+        ExprAST::Variable(_)
+        | ExprAST::Number(_)
+        | ExprAST::StringLiteral(_)
+        | ExprAST::BoolLiteral(_)
+        | ExprAST::CharLiteral(_)
+        | ExprAST::EnumVariant(_) => vec![],
+    };
+    for child in children {
+        addressed_in_expr(child, ids);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegeneration::typechecks::Typechecker;
+    use crate::parser::{lexer::Lexer, parser::Parser};
+
+    fn generate(program: &str) -> (String, Vec<Diagnostic>) {
+        generate_for(program, &Target::default())
+    }
+
+    fn generate_for(program: &str, target: &Target) -> (String, Vec<Diagnostic>) {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        let (checked, errors) = Typechecker::new(body, None, TypeAST::Void).check_types();
+        assert!(errors.is_empty());
+        Codegen::new(target).generate(&checked)
+    }
+
+    #[test]
+    fn test_function_frame_and_call() {
+        let (asm, errors) = generate(
+            "fn add(a: i8, b: i8) -> i8 {
+                return a + b;
+            }
+            fn main() -> i8 {
+                let c: i8 = add(1, 2);
+                return c;
+            }",
+        );
+        assert!(errors.is_empty());
+        let lines: Vec<&str> = asm.lines().map(|line| line.trim()).collect();
+        //arguments are pushed left to right and popped by the caller
+        let call = lines.iter().position(|l| *l == "CALL fn_add").unwrap();
+        assert_eq!(
+            lines[call - 4..call + 3],
+            [
+                "LDI r0, 1",
+                "PUSH r0",
+                "LDI r0, 2",
+                "PUSH r0",
+                "CALL fn_add",
+                "LDI r1, 8",
+                "ADD r15, r15, r1"
+            ]
+        );
+        //a is the first argument, b the last one
+        assert!(asm.contains("LDW r0, [r14 + 12]"));
+        assert!(asm.contains("LDW r0, [r14 + 8]"));
+        //c is the only local of main
+        assert!(asm.contains("STW [r14 - 4], r0"));
+        //i8 results are sign extended
+        assert!(asm.contains("SAR r0, r0, r1"));
+    }
+
+    #[test]
+    fn test_if_and_comparison() {
+        let (asm, errors) = generate(
+            "fn main() -> u8 {
+                let a: bool = true;
+                if a == false {
+                    return foo();
+                }
+                return foo();
+            }
+            fn foo() -> u8 {
+                return foo();
+            }",
+        );
+        assert!(errors.is_empty());
+        assert!(asm.contains("JEQ .L1"));
+        assert!(asm.contains("JEQ .L2"));
+        assert!(asm.contains("JMP fn_main$ret"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let (_, errors) = generate(
+            "fn main() -> str {
+                return \"a\" + \"b\";
+            }",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, diagnostics::UNSUPPORTED_BY_BACKEND);
+
+        let (_, errors) = generate("fn foo() -> void {}");
+        assert_eq!(errors[0].code, diagnostics::MISSING_MAIN);
+
+        let (_, errors) = generate(
+            "struct P { x: i8 }
+            fn get(p: P) -> i8 {
+                return p.x;
+            }
+            fn main() -> i8 {
+                return get(P { x: 1 });
+            }",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].primary.span.line, 2);
+    }
+
+    #[test]
+    fn test_out_of_memory() {
+        let (_, errors) = generate(
+            "let mut big: [u8; 70000] = [0; 70000];
+            fn main() -> u8 {
+                let a: u8 = 1;
+                let b: [u16; 40000] = [0; 40000];
+                let c: u8 = 2;
+                return a;
+            }",
+        );
+        //the global and the first local that does not fit
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].code, diagnostics::OUT_OF_MEMORY);
+        assert_eq!(errors[0].primary.span.line, 1);
+        assert_eq!(errors[1].code, diagnostics::OUT_OF_MEMORY);
+        assert_eq!(errors[1].primary.span.line, 4);
+
+        let (_, errors) = generate(
+            "fn main() -> u8 {
+                let a: u8 = [1; 70000][0];
+                return a;
+            }",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].primary.span.line, 2);
+    }
+
+    #[test]
+    fn test_other_target() {
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        let (asm, errors) = generate_for(
+            "fn main() -> i16 {
+                let a: i16 = 0;
+                let b: i16 = a + a;
+                return b;
+            }",
+            &target,
+        );
+        assert!(errors.is_empty());
+        //8 registers, so r6 is the frame pointer and r7 the stack pointer
+        assert!(asm.contains("IMM r7, 32752"));
+        assert!(asm.contains("STR [r6 - 4], r0"));
+        assert!(asm.contains(".org 0x4000"));
+
+        let (_, errors) = generate_for(
+            "fn main() -> i8 {
+                let a: i32 = 0;
+                return 1;
+            }",
+            &target,
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, diagnostics::UNSUPPORTED_BY_BACKEND);
+    }
+
+    #[test]
+    fn test_print_routines() {
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        let (asm, errors) = generate_for(
+            "fn main() -> void {
+                print('a');
+                print(true);
+            }",
+            &target,
+        );
+        assert!(errors.is_empty());
+        let lines: Vec<&str> = asm.lines().map(|line| line.trim()).collect();
+        //chars are written directly to the port at 0x7ffe
+        let port = lines.iter().position(|l| *l == "IMM r0, 97").unwrap();
+        assert_eq!(lines[port + 1..port + 3], ["IMM r1, 32766", "STR [r1], r0"]);
+        //only the routine for strings is needed, the character is the high byte of a word
+        assert!(asm.contains("CAL __print_str"));
+        assert!(asm.contains("__print_str:"));
+        assert!(asm.contains("IMM r1, 8"));
+        assert!(!asm.contains("__print_uint"));
+        assert!(asm.contains("__str_false:"));
+    }
+
+    #[test]
+    fn test_match_lowering() {
+        let (asm, errors) = generate(
+            "enum Op { Nop, Load, Store, Add, Jump = 6, Halt = 100 }
+            fn main() -> i8 {
+                let op: Op = Op::Add;
+                match op {
+                    Op::Nop => {}
+                    Op::Load | Op::Store => {}
+                    Op::Add | Op::Jump => {}
+                    _ => {}
+                }
+                match op {
+                    Op::Nop | Op::Halt => {}
+                    _ => {}
+                }
+                return 0;
+            }",
+        );
+        assert!(errors.is_empty());
+        let lines: Vec<&str> = asm.lines().map(|line| line.trim()).collect();
+        //5 values spread over 7 entries use a table, the missing ones go to the `_` arm
+        assert!(lines.contains(&".word .L2, .L3, .L3, .L4, .L5, .L5, .L4"));
+        let ret = lines.iter().position(|l| *l == "RET").unwrap();
+        assert_eq!(lines[ret - 2..=ret], ["LDW r0, [r0]", "PUSH r0", "RET"]);
+        //the sparse match compares the values one after another
+        assert!(asm.contains("LDI r1, 100\n    CMP r0, r1\n    JEQ"));
+    }
+
+    #[test]
+    fn test_pointer_lowering() {
+        let (asm, errors) = generate(
+            "fn main() -> i32 {
+                let a: [i32; 3] = [0; 3];
+                let p: *i32 = 1 + &a[0];
+                return *p;
+            }",
+        );
+        assert!(errors.is_empty());
+        //the integer is scaled by the size of an i32
+        assert!(asm.contains("LDI r2, 4\n    MUL r0, r0, r2\n    ADD r0, r0, r1"));
+
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        let (asm, errors) = generate_for(
+            "fn main() -> i8 {
+                let x: i8 = 0;
+                let p: *i8 = &x;
+                return x;
+            }",
+            &target,
+        );
+        assert!(errors.is_empty());
+        //x is the low byte of its big endian word at fp - 2
+        assert!(asm.contains("IMM r0, -1\n    ADD r0, r6, r0"));
+        assert!(!asm.contains("LOD r0, [r6 - 2]"));
+    }
+
+    #[test]
+    fn test_global_data() {
+        let program = "struct P { a: u8, b: u16 }
+            const N: u8 = 3;
+            static mut X: i8 = -1;
+            static Q: P = P { b: 515, a: 1 };
+            fn main() -> i8 {
+                X = X + N as i8;
+                return X;
+            }";
+        let (asm, errors) = generate(program);
+        assert!(errors.is_empty());
+        //the fields are laid out from the start of the slot, b is 0x0203 at offset 2
+        assert!(asm.contains("global_X:\n    .word 255\n"));
+        assert!(asm.contains("global_Q:\n    .word 33751041\n"));
+        //the constant is loaded as an immediate
+        assert!(!asm.contains("global_N"));
+        assert!(asm.contains("LDI r0, 3"));
+
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        let (asm, errors) = generate_for(program, &target);
+        assert!(errors.is_empty());
+        //big endian, fields are not aligned so b follows a directly
+        assert!(asm.contains("global_X:\n    .word 65280\n"));
+        assert!(asm.contains("global_Q:\n    .word 258, 768\n"));
+    }
+}
@@ -0,0 +1,243 @@
+//! evaluation of the values of constants and global variables at compile time
+//!
+//! the values are replaced by literals, the backends place global variables in memory before the
+//! program starts and inline constants where they are used. values can use literals, constants
+//! defined before them, operators and casts. integers wrap around to the width of their type like
+//! at runtime, dividing by zero is an error
+
+use std::collections::HashMap;
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::parser::{
+    ast::{
+        ArrayLiteralAST, ArrayRepeatAST, BinaryExpressionAST, BodyAST, BoolAST, CastAST, CharAST,
+        ExprAST, FieldInitAST, NumberAST, StmtAST, StringLiteralAST, StructLiteralAST, SymbolId,
+        TypeAST,
+    },
+    lexer::{Span, Token},
+};
+
+use super::resolver::TypeTable;
+
+struct Evaluator<'a> {
+    types: &'a TypeTable,
+    //the values of the constants evaluated so far
+    constants: HashMap<SymbolId, ExprAST>,
+}
+
+///replaces the values of the constants and global variables of the typechecked program by
+///literals, they are defined before the functions
+pub fn eval_globals(body: &mut BodyAST, types: &TypeTable) -> Vec<Diagnostic> {
+    let mut evaluator = Evaluator {
+        types,
+        constants: HashMap::new(),
+    };
+    let mut errors = Vec::new();
+    for stmt in &mut body.stmts {
+        let (global, is_const) = match stmt {
+            StmtAST::Const(constant) => (constant, true),
+            StmtAST::DeclAssign(global) => (global, false),
+            _ => continue,
+        };
+        match evaluator.eval(&global.value) {
+            Ok(value) => {
+                if is_const {
+                    let id = global
+                        .decl
+                        .id
+                        .expect("the typechecker gave every constant an id");
+                    evaluator.constants.insert(id, value.clone());
+                }
+                global.value = value;
+            }
+            Err(err) => errors.push(
+                err.with_secondary(
+                    global.decl.span,
+                    format!(
+                        "the value of `{}` is computed at compile time",
+                        global.decl.name
+                    ),
+                )
+                .with_note(
+                    "constants and global variables can only be initialized with literals, \
+                     constants, operators and casts",
+                ),
+            ),
+        }
+    }
+    errors
+}
+
+fn not_constant(label: impl Into<String>, span: Span) -> Diagnostic {
+    Diagnostic::error(
+        diagnostics::NOT_CONSTANT,
+        "cannot evaluate this at compile time",
+        span,
+    )
+    .with_label(label)
+}
+
+fn int(num: i64, tp: TypeAST, span: Span) -> ExprAST {
+    ExprAST::Number(NumberAST {
+        num: tp.wrap(num),
+        tp,
+        span,
+    })
+}
+
+fn bool_literal(value: bool, span: Span) -> ExprAST {
+    ExprAST::BoolLiteral(BoolAST { value, span })
+}
+
+impl Evaluator<'_> {
+    ///the number a scalar literal is stored as, enums are their discriminants
+    fn scalar(&self, literal: &ExprAST) -> Option<i64> {
+        match literal {
+            ExprAST::Number(num) => Some(num.num),
+            ExprAST::BoolLiteral(bl) => Some(bl.value as i64),
+            ExprAST::CharLiteral(ch) => Some(ch.value as i64),
+            ExprAST::EnumVariant(variant) => self
+                .types
+                .resolve_enum(&variant.enum_name)
+                .and_then(|def| def.discriminant(&variant.variant)),
+            _ => None,
+        }
+    }
+
+    ///the literal expr evaluates to
+    fn eval(&self, expr: &ExprAST) -> Result<ExprAST, Diagnostic> {
+        match expr {
+            ExprAST::Number(_)
+            | ExprAST::StringLiteral(_)
+            | ExprAST::BoolLiteral(_)
+            | ExprAST::CharLiteral(_)
+            | ExprAST::EnumVariant(_) => Ok(expr.clone()),
+            ExprAST::Variable(var) => var
+                .id
+                .and_then(|id| self.constants.get(&id))
+                .cloned()
+                .ok_or_else(|| {
+                    not_constant(
+                        format!("`{}` is a variable, not a constant", var.name),
+                        var.span,
+                    )
+                }),
+            ExprAST::BinaryExpression(bin_expr) => self.eval_binary(bin_expr),
+            ExprAST::Cast(cast) => self.eval_cast(cast),
+            ExprAST::ArrayLiteral(lit) => {
+                let elements = lit
+                    .elements
+                    .iter()
+                    .map(|element| self.eval(element))
+                    .collect::<Result<_, _>>()?;
+                Ok(ExprAST::ArrayLiteral(ArrayLiteralAST {
+                    elements,
+                    ..lit.clone()
+                }))
+            }
+            ExprAST::ArrayRepeat(lit) => Ok(ExprAST::ArrayRepeat(Box::new(ArrayRepeatAST {
+                value: self.eval(&lit.value)?,
+                ..*lit.clone()
+            }))),
+            ExprAST::StructLiteral(lit) => {
+                let fields = lit
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        Ok(FieldInitAST {
+                            value: self.eval(&field.value)?,
+                            ..field.clone()
+                        })
+                    })
+                    .collect::<Result<_, Diagnostic>>()?;
+                Ok(ExprAST::StructLiteral(StructLiteralAST {
+                    fields,
+                    ..lit.clone()
+                }))
+            }
+            ExprAST::Call(call) => {
+                Err(not_constant("function calls are run at runtime", call.span))
+            }
+            ExprAST::Unary(unary) if unary.op == Token::Minus => {
+                let value = self.eval(&unary.expr)?;
+                let n = self
+                    .scalar(&value)
+                    .expect("the typechecker only allows negating integers");
+                Ok(int(n.wrapping_neg(), unary.tp.clone(), unary.span))
+            }
+            ExprAST::FieldAccess(_) | ExprAST::Index(_) | ExprAST::Unary(_) => Err(not_constant(
+                "not a literal, constant, operator or cast",
+                expr.span(),
+            )),
+        }
+    }
+
+    ///evaluates the operators the interpreter supports for values that are not pointers
+    fn eval_binary(&self, expr: &BinaryExpressionAST) -> Result<ExprAST, Diagnostic> {
+        let lhs = self.eval(&expr.lhs)?;
+        let rhs = self.eval(&expr.rhs)?;
+        let span = expr.span;
+        if let (ExprAST::StringLiteral(lhs), ExprAST::StringLiteral(rhs)) = (&lhs, &rhs) {
+            return match expr.op {
+                Token::Equal => Ok(bool_literal(lhs.str == rhs.str, span)),
+                Token::Unequal => Ok(bool_literal(lhs.str != rhs.str, span)),
+                Token::Plus => Ok(ExprAST::StringLiteral(StringLiteralAST {
+                    str: lhs.str.clone() + &rhs.str,
+                    span,
+                })),
+                _ => Err(not_constant("the operator can't be evaluated", span)),
+            };
+        }
+        let (Some(lhs), Some(rhs)) = (self.scalar(&lhs), self.scalar(&rhs)) else {
+            return Err(not_constant("the operator can't be evaluated", span));
+        };
+        let tp = expr.tp.clone();
+        let bits = tp.int_bits().unwrap_or(64) as i64;
+        let value = match expr.op {
+            Token::Equal => bool_literal(lhs == rhs, span),
+            Token::Unequal => bool_literal(lhs != rhs, span),
+            Token::LessThan => bool_literal(lhs < rhs, span),
+            Token::GreaterThan => bool_literal(lhs > rhs, span),
+            Token::AndBool => bool_literal(lhs != 0 && rhs != 0, span),
+            Token::OrBool => bool_literal(lhs != 0 || rhs != 0, span),
+            Token::XorBool => bool_literal((lhs != 0) ^ (rhs != 0), span),
+            Token::Plus => int(lhs.wrapping_add(rhs), tp, span),
+            Token::Minus => int(lhs.wrapping_sub(rhs), tp, span),
+            Token::Mult => int(lhs.wrapping_mul(rhs), tp, span),
+            Token::Divide if rhs == 0 => {
+                return Err(Diagnostic::error(
+                    diagnostics::CONST_EVAL_FAILED,
+                    "attempt to divide by zero at compile time",
+                    span,
+                )
+                .with_label("the divisor is zero"))
+            }
+            Token::Divide => int(lhs.wrapping_div(rhs), tp, span),
+            Token::AndInt => int(lhs & rhs, tp, span),
+            Token::OrInt => int(lhs | rhs, tp, span),
+            Token::XorInt => int(lhs ^ rhs, tp, span),
+            //the shift amount wraps around at the width of the type
+            Token::LeftShift => int(lhs << (rhs & (bits - 1)), tp, span),
+            Token::RightShift => int(lhs >> (rhs & (bits - 1)), tp, span),
+            _ => return Err(not_constant("the operator can't be evaluated", span)),
+        };
+        Ok(value)
+    }
+
+    ///converts like the interpreter, every value other than 0 is true
+    fn eval_cast(&self, cast: &CastAST) -> Result<ExprAST, Diagnostic> {
+        let value = self.eval(&cast.expr)?;
+        let n = self
+            .scalar(&value)
+            .expect("the typechecker only allows casting scalars");
+        let span = cast.span;
+        Ok(match &cast.target {
+            TypeAST::Bool => bool_literal(n != 0, span),
+            TypeAST::Char => ExprAST::CharLiteral(CharAST {
+                value: n as u8,
+                span,
+            }),
+            tp => int(n, tp.clone(), span),
+        })
+    }
+}
@@ -0,0 +1,610 @@
+//! control flow analysis of typechecked functions: every path of a function that returns a value
+//! ends in a `return`, variables are assigned on every path before they are read and nothing
+//! follows a `return`, `break` or `continue` in the same body
+//!
+//! the analysis tracks the set of variables that are assigned on every path to the current
+//! statement, branches continue with the variables all of their reachable ends assigned
+//!
+//! the typechecker uses `repeated_assignments` to find the assignments a loop can run again,
+//! which are those that can reach the end of its body or a `continue`
+
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::parser::{
+    ast::{
+        BodyAST, DeclarationAST, ElseAST, ExprAST, FunctionAST, IfStmtAST, StmtAST, SymbolId,
+        TypeAST, VariableAST,
+    },
+    lexer::{Span, Token},
+};
+
+///the state at the end of a branch
+struct BranchEnd {
+    assigned: HashSet<SymbolId>,
+    diverged: Option<Span>,
+}
+
+struct FlowChecker {
+    //the declarations of the variables of the analyzed function
+    locals: HashMap<SymbolId, Span>,
+    //variables assigned on every path to the current statement
+    assigned: HashSet<SymbolId>,
+    //the statement after which the rest of the current body is unreachable
+    diverged: Option<Span>,
+    //for each loop around the current statement the variables assigned on every path to a
+    //`break` of it, None if there is no `break` yet
+    breaks: Vec<Option<HashSet<SymbolId>>>,
+    errors: Vec<Diagnostic>,
+}
+
+///checks all functions of the program, including the nested ones
+pub fn check_flow(body: &BodyAST) -> Vec<Diagnostic> {
+    body.stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            StmtAST::Function(func) => Some(check_function(func)),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+fn check_function(func: &FunctionAST) -> Vec<Diagnostic> {
+    let mut checker = FlowChecker {
+        locals: HashMap::new(),
+        assigned: HashSet::new(),
+        diverged: None,
+        breaks: Vec::new(),
+        errors: Vec::new(),
+    };
+    for arg in &func.fn_signt.args {
+        checker.declare(arg, true);
+    }
+    checker.check_body(&func.body);
+    let rt_type = &func.fn_signt.rt_type;
+    if checker.diverged.is_none() && !matches!(rt_type, TypeAST::Void | TypeAST::Error) {
+        checker.errors.push(
+            Diagnostic::error(
+                diagnostics::MISSING_RETURN,
+                format!(
+                    "function `{}` does not return a value on every path",
+                    func.fn_signt.name
+                ),
+                func.fn_signt.span,
+            )
+            .with_label(format!(
+                "expected `{}` because of this return type",
+                rt_type
+            ))
+            .with_note("a path reaches the end of the body without a `return`"),
+        );
+    }
+    checker.errors
+}
+
+///the assignments on the paths through a loop body, with the variable they assign
+type Assignments = Vec<(SymbolId, Span)>;
+
+///the paths through a loop body that leave it with a `break` or `continue`
+#[derive(Default)]
+struct LoopPaths {
+    //None if no path reaches a `break`
+    breaks: Option<Assignments>,
+    continues: Assignments,
+}
+
+///the assignments of the body of a loop that can be followed by another iteration of it, nested
+///loops report the assignments they repeat themselves and only pass on those that reach a
+///`break` of theirs
+pub fn repeated_assignments(body: &BodyAST) -> Assignments {
+    let mut paths = LoopPaths::default();
+    let end = paths.walk(body, Vec::new());
+    union([end, Some(paths.continues)]).unwrap_or_default()
+}
+
+///the assignments of all reachable paths, None if none of them is reachable
+fn union(paths: impl IntoIterator<Item = Option<Assignments>>) -> Option<Assignments> {
+    paths.into_iter().flatten().reduce(|mut all, path| {
+        for assignment in path {
+            if !all.contains(&assignment) {
+                all.push(assignment);
+            }
+        }
+        all
+    })
+}
+
+///the variable an assignment to target changes, fields and elements behind pointers belong to
+///no variable
+fn assigned_variable(target: &ExprAST) -> Option<SymbolId> {
+    match target {
+        place if matches!(place.tp(), TypeAST::Pointer(_)) => None,
+        ExprAST::Variable(var) => var.id,
+        ExprAST::FieldAccess(access) => assigned_variable(&access.expr),
+        ExprAST::Index(index) => assigned_variable(&index.expr),
+        _ => None,
+    }
+}
+
+impl LoopPaths {
+    ///follows the paths that enter the body with the assignments made before, returns the
+    ///assignments of those that reach its end, None if none of them do
+    fn walk(&mut self, body: &BodyAST, mut assigned: Assignments) -> Option<Assignments> {
+        for stmt in &body.stmts {
+            match stmt {
+                StmtAST::Assign(ass) => {
+                    if let Some(id) = assigned_variable(&ass.target) {
+                        assigned.push((id, ass.span));
+                    }
+                }
+                StmtAST::Return(_) => return None,
+                StmtAST::Break(_) => {
+                    self.breaks = union([self.breaks.take(), Some(assigned)]);
+                    return None;
+                }
+                StmtAST::Continue(_) => {
+                    let continues = std::mem::take(&mut self.continues);
+                    self.continues = union([Some(continues), Some(assigned)]).unwrap_or_default();
+                    return None;
+                }
+                StmtAST::If(if_st) => assigned = self.walk_if(if_st, assigned)?,
+                StmtAST::Match(match_st) => {
+                    let ends: Vec<_> = match_st
+                        .arms
+                        .iter()
+                        .map(|arm| self.walk(&arm.body, assigned.clone()))
+                        .collect();
+                    assigned = union(ends)?;
+                }
+                StmtAST::While(while_st) => {
+                    let mut inner = LoopPaths::default();
+                    inner.walk(&while_st.body, assigned.clone());
+                    //`while true` is only left by a `break`, other loops may not run their body
+                    let endless =
+                        matches!(&while_st.condition, ExprAST::BoolLiteral(bl) if bl.value);
+                    assigned = if endless {
+                        inner.breaks?
+                    } else {
+                        union([Some(assigned), inner.breaks])?
+                    };
+                }
+                _ => {}
+            }
+        }
+        Some(assigned)
+    }
+
+    ///the assignments of the reachable ends of the branches of an if and its else if chain
+    fn walk_if(&mut self, if_st: &IfStmtAST, assigned: Assignments) -> Option<Assignments> {
+        let body = self.walk(&if_st.body, assigned.clone());
+        let other = match &if_st.else_branch {
+            Some(ElseAST::If(else_if)) => self.walk_if(else_if, assigned),
+            Some(ElseAST::Body(body)) => self.walk(body, assigned),
+            None => Some(assigned),
+        };
+        union([body, other])
+    }
+}
+
+impl FlowChecker {
+    ///the variable of the analyzed function that var refers to and its declaration, None for
+    ///variables of an enclosing function
+    fn find(&self, var: &VariableAST) -> Option<(SymbolId, Span)> {
+        let id = var.id?;
+        self.locals.get(&id).map(|span| (id, *span))
+    }
+
+    ///reports a read of var if it is not assigned on every path, partial is set for assignments
+    ///to a field or element of var
+    fn use_variable(&mut self, var: &VariableAST, partial: bool) {
+        let Some((id, decl_span)) = self.find(var) else {
+            return;
+        };
+        if self.assigned.contains(&id) {
+            return;
+        }
+        let err = if partial {
+            Diagnostic::error(
+                diagnostics::UNASSIGNED_VARIABLE,
+                format!("assignment to a part of unassigned variable `{}`", var.name),
+                var.span,
+            )
+            .with_label(format!("`{}` is not assigned as a whole yet", var.name))
+        } else {
+            Diagnostic::error(
+                diagnostics::UNASSIGNED_VARIABLE,
+                format!("use of possibly unassigned variable `{}`", var.name),
+                var.span,
+            )
+            .with_label(format!(
+                "`{}` used here but it is not assigned on every path",
+                var.name
+            ))
+        };
+        let err = err.with_secondary(decl_span, "declared here without a value");
+        //later uses are not reported again
+        self.assigned.insert(id);
+        self.errors.push(err);
+    }
+
+    fn check_expr(&mut self, expr: &ExprAST) {
+        let children: Vec<&ExprAST> = match expr {
+            ExprAST::Variable(var) => {
+                self.use_variable(var, false);
+                vec![]
+            }
+            ExprAST::Call(call) => call.args.iter().collect(),
+            ExprAST::BinaryExpression(bin_expr) => vec![&bin_expr.lhs, &bin_expr.rhs],
+            ExprAST::StructLiteral(lit) => lit.fields.iter().map(|init| &init.value).collect(),
+            ExprAST::FieldAccess(access) => vec![&access.expr],
+            ExprAST::ArrayLiteral(lit) => lit.elements.iter().collect(),
+            ExprAST::ArrayRepeat(lit) => vec![&lit.value],
+            ExprAST::Index(index) => vec![&index.expr, &index.index],
+            ExprAST::Unary(unary) if unary.op == Token::AndInt => {
+                self.check_borrowed(&unary.expr, false);
+                vec![]
+            }
+            ExprAST::Unary(unary) => vec![&unary.expr],
+            ExprAST::Cast(cast) => vec![&cast.expr],
+            ExprAST::Number(_)
+            | ExprAST::StringLiteral(_)
+            | ExprAST::BoolLiteral(_)
+            | ExprAST::CharLiteral(_)
+            | ExprAST::EnumVariant(_) => vec![],
+        };
+        for child in children {
+            self.check_expr(child);
+        }
+    }
+
+    ///checks the place that contains the assigned field or element, it has to be assigned already
+    fn check_assigned_part(&mut self, place: &ExprAST) {
+        match place {
+            //the field of a struct behind a pointer only needs the pointer
+            place if matches!(place.tp(), TypeAST::Pointer(_)) => self.check_expr(place),
+            ExprAST::Variable(var) => self.use_variable(var, true),
+            ExprAST::FieldAccess(access) => self.check_assigned_part(&access.expr),
+            ExprAST::Index(index) => {
+                self.check_expr(&index.index);
+                self.check_assigned_part(&index.expr);
+            }
+            place => self.check_expr(place),
+        }
+    }
+
+    ///checks a place whose address is taken, the variable that contains it counts as assigned
+    ///from then on as it can be written through the pointer, nested is set for fields and elements
+    fn check_borrowed(&mut self, place: &ExprAST, nested: bool) {
+        match place {
+            place if nested && matches!(place.tp(), TypeAST::Pointer(_)) => self.check_expr(place),
+            ExprAST::Variable(var) => {
+                if let Some((id, _)) = self.find(var) {
+                    self.assigned.insert(id);
+                }
+            }
+            ExprAST::FieldAccess(access) => self.check_borrowed(&access.expr, true),
+            ExprAST::Index(index) => {
+                self.check_expr(&index.index);
+                self.check_borrowed(&index.expr, true);
+            }
+            place => self.check_expr(place),
+        }
+    }
+
+    fn check_assign_target(&mut self, target: &ExprAST) {
+        match target {
+            ExprAST::Variable(var) => {
+                if let Some((id, _)) = self.find(var) {
+                    self.assigned.insert(id);
+                }
+            }
+            ExprAST::FieldAccess(access) => self.check_assigned_part(&access.expr),
+            ExprAST::Index(index) => {
+                self.check_expr(&index.index);
+                self.check_assigned_part(&index.expr);
+            }
+            //writes through a pointer only read the pointer
+            target => self.check_expr(target),
+        }
+    }
+
+    fn declare(&mut self, decl: &DeclarationAST, assigned: bool) {
+        let id = decl
+            .id
+            .expect("the typechecker gave every declaration an id");
+        if assigned {
+            self.assigned.insert(id);
+        }
+        self.locals.insert(id, decl.span);
+    }
+
+    ///checks a branch that starts with the variables assigned before it
+    fn check_branch(&mut self, body: &BodyAST, before: &HashSet<SymbolId>) -> BranchEnd {
+        self.assigned = before.clone();
+        self.diverged = None;
+        self.check_body(body);
+        BranchEnd {
+            assigned: std::mem::take(&mut self.assigned),
+            diverged: self.diverged.take(),
+        }
+    }
+
+    ///continues after branches, the whole statement at span diverges if all of them diverge
+    fn join(&mut self, ends: Vec<BranchEnd>, span: Span) {
+        let mut reachable = ends.into_iter().filter(|end| end.diverged.is_none());
+        match reachable.next() {
+            Some(first) => {
+                self.assigned = reachable.fold(first.assigned, |assigned, end| {
+                    assigned.intersection(&end.assigned).copied().collect()
+                });
+                self.diverged = None;
+            }
+            None => self.diverged = Some(span),
+        }
+    }
+
+    ///the ends of the branches of an if and its else if chain
+    fn check_if(&mut self, if_st: &IfStmtAST, before: &HashSet<SymbolId>) -> Vec<BranchEnd> {
+        self.assigned = before.clone();
+        self.check_expr(&if_st.condition);
+        let before = std::mem::take(&mut self.assigned);
+        let mut ends = vec![self.check_branch(&if_st.body, &before)];
+        match &if_st.else_branch {
+            Some(ElseAST::If(else_if)) => ends.extend(self.check_if(else_if, &before)),
+            Some(ElseAST::Body(body)) => ends.push(self.check_branch(body, &before)),
+            None => ends.push(BranchEnd {
+                assigned: before,
+                diverged: None,
+            }),
+        }
+        ends
+    }
+
+    fn check_stmt(&mut self, stmt: &StmtAST) {
+        match stmt {
+            StmtAST::Declaration(decl) => self.declare(decl, false),
+            StmtAST::DeclAssign(declassg) => {
+                self.check_expr(&declassg.value);
+                self.declare(&declassg.decl, true);
+            }
+            StmtAST::Assign(ass) => {
+                self.check_expr(&ass.value);
+                self.check_assign_target(&ass.target);
+            }
+            StmtAST::Call(call) => {
+                for arg in &call.args {
+                    self.check_expr(arg);
+                }
+            }
+            StmtAST::Print(print) => self.check_expr(&print.expr),
+            StmtAST::Return(rtstmt) => {
+                self.check_expr(&rtstmt.expr);
+                self.diverged = Some(rtstmt.span);
+            }
+            StmtAST::If(if_st) => {
+                let before = std::mem::take(&mut self.assigned);
+                let ends = self.check_if(if_st, &before);
+                self.join(ends, if_st.span);
+            }
+            StmtAST::Match(match_st) => {
+                self.check_expr(&match_st.scrutinee);
+                let before = std::mem::take(&mut self.assigned);
+                //the typechecker made sure one of the arms matches
+                let ends = match_st
+                    .arms
+                    .iter()
+                    .map(|arm| self.check_branch(&arm.body, &before))
+                    .collect();
+                self.join(ends, match_st.span);
+            }
+            StmtAST::While(while_st) => {
+                self.check_expr(&while_st.condition);
+                let before = std::mem::take(&mut self.assigned);
+                self.breaks.push(None);
+                self.check_branch(&while_st.body, &before);
+                let breaks = self.breaks.pop().expect("pushed above");
+                //`while true` is only left by a `break`, other loops may not run their body
+                let endless = matches!(&while_st.condition, ExprAST::BoolLiteral(bl) if bl.value);
+                match breaks {
+                    Some(assigned) if endless => self.assigned = assigned,
+                    None if endless => {
+                        self.assigned = before;
+                        self.diverged = Some(while_st.span);
+                    }
+                    _ => self.assigned = before,
+                }
+            }
+            StmtAST::Break(span) => {
+                let assigned = self.assigned.clone();
+                if let Some(breaks) = self.breaks.last_mut() {
+                    *breaks = Some(match breaks.take() {
+                        Some(other) => assigned.intersection(&other).copied().collect(),
+                        None => assigned,
+                    });
+                }
+                self.diverged = Some(*span);
+            }
+            StmtAST::Continue(span) => self.diverged = Some(*span),
+            StmtAST::Function(func) => self.errors.extend(check_function(func)),
+            StmtAST::Struct(_) | StmtAST::Enum(_) | StmtAST::Const(_) => {}
+        }
+    }
+
+    ///checks the statements of the body, the first one that can not be reached is reported
+    fn check_body(&mut self, body: &BodyAST) {
+        for stmt in &body.stmts {
+            if let Some(diverged) = self.diverged {
+                self.errors.push(
+                    Diagnostic::warning(
+                        diagnostics::UNREACHABLE_STATEMENT,
+                        "unreachable statement",
+                        stmt.span(),
+                    )
+                    .with_label("unreachable statement")
+                    .with_secondary(diverged, "any code following this is unreachable"),
+                );
+                break;
+            }
+            self.check_stmt(stmt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegeneration::typechecks::Typechecker;
+    use crate::diagnostics::Severity;
+    use crate::parser::{lexer::Lexer, parser::Parser};
+
+    fn check(program: &str) -> Vec<Diagnostic> {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        let (_, errors) = Typechecker::new(body, None, TypeAST::Void).check_types();
+        errors
+    }
+
+    fn codes(errors: &[Diagnostic]) -> Vec<(&str, u32)> {
+        errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_return_paths() {
+        let errors = check(
+            "fn a(b: bool) -> i8 {
+                if b { return 1; } else { return 2; }
+            }
+            fn c(b: bool) -> i8 {
+                if b { return 1; }
+            }
+            fn d(n: i8) -> i8 {
+                match n { 0 => { return 1; } _ => { return 2; } }
+            }
+            fn e() -> i8 {
+                while true { return 1; }
+            }
+            fn f() -> i8 {
+                while true { break; }
+            }
+            fn g(b: bool) -> i8 {
+                while b { return 1; }
+            }
+            fn main() -> void {}",
+        );
+        assert_eq!(
+            codes(&errors),
+            [
+                (diagnostics::MISSING_RETURN, 4),
+                (diagnostics::MISSING_RETURN, 13),
+                (diagnostics::MISSING_RETURN, 16)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unassigned_variables() {
+        let errors = check(
+            "struct P { x: i8 }
+            fn main() -> void {
+                let mut a: i8;
+                print(a);
+                let mut b: i8;
+                if true { b = 1; }
+                print(b);
+                let c: i8;
+                if true { c = 1; } else { c = 2; }
+                print(c);
+                let mut d: i8;
+                while true { d = 1; break; }
+                print(d);
+                let mut e: i8;
+                while false { e = 1; }
+                print(e + e);
+                let mut p: P;
+                p.x = 1;
+                let mut q: *P;
+                q.x = 1;
+                let mut r: i8;
+                let s: *i8 = &r;
+                print(r);
+            }",
+        );
+        assert_eq!(
+            codes(&errors),
+            [
+                (diagnostics::UNASSIGNED_VARIABLE, 4),
+                (diagnostics::UNASSIGNED_VARIABLE, 7),
+                (diagnostics::UNASSIGNED_VARIABLE, 16),
+                (diagnostics::UNASSIGNED_VARIABLE, 18),
+                (diagnostics::UNASSIGNED_VARIABLE, 20)
+            ]
+        );
+        assert_eq!(errors[0].message, "use of possibly unassigned variable `a`");
+        assert_eq!(
+            errors[3].message,
+            "assignment to a part of unassigned variable `p`"
+        );
+        assert_eq!(errors[4].message, "use of possibly unassigned variable `q`");
+    }
+
+    #[test]
+    fn test_unreachable_statements() {
+        let errors = check(
+            "fn main() -> i8 {
+                let mut i: i8 = 0;
+                while i < 3 {
+                    i = i + 1;
+                    continue;
+                    print(i);
+                }
+                if i == 3 { return 1; } else { return 2; }
+                print(i);
+                return 0;
+            }",
+        );
+        assert_eq!(
+            codes(&errors),
+            [
+                (diagnostics::UNREACHABLE_STATEMENT, 6),
+                (diagnostics::UNREACHABLE_STATEMENT, 9)
+            ]
+        );
+        assert!(errors.iter().all(|err| err.severity == Severity::Warning));
+        assert_eq!(errors[1].secondary[0].span.line, 8);
+    }
+
+    #[test]
+    fn test_repeated_assignments() {
+        let errors = check(
+            "fn main() -> u8 {
+                let b: bool = true;
+                let x: u8;
+                while true { if true { x = 1; break; } }
+                let y: u8;
+                while true { if b { y = 1; } if b { break; } }
+                let z: u8;
+                while b { z = 1; continue; }
+                let v: u8;
+                while b { while true { v = 1; break; } }
+                let w: u8;
+                while true { while true { w = 1; break; } break; }
+                let u: u8;
+                while b { match x { 1 => { u = 1; return u; } _ => { break; } } }
+                return x + y + w;
+            }",
+        );
+        assert_eq!(
+            codes(&errors),
+            [
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 6),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 8),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 10)
+            ]
+        );
+        assert_eq!(errors[0].notes, ["the assignment is repeated by the loop"]);
+    }
+}
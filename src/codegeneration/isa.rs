@@ -0,0 +1,233 @@
+use std::fmt::Display;
+
+use super::target::Target;
+
+///operations of the minecraft cpu, mnemonics and encodings are defined by the target
+///
+///arithmetic works on whole registers and wraps around, `CMP` is the only instruction that sets
+///the flags: zero (a == b), negative (a < b signed) and carry (a < b unsigned)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Op {
+    //rd = imm
+    Ldi,
+    //rd = rs
+    Mov,
+    //rd = ra op rb
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Divs,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Sar,
+    //sets the flags from ra - rb
+    Cmp,
+    //jumps to the address, the conditional ones depend on the flags
+    Jmp,
+    Jeq,
+    Jne,
+    Jlt,
+    Jb,
+    //rd = word at [rs + offset]
+    Ldw,
+    //word at [rd + offset] = rs
+    Stw,
+    Push,
+    Pop,
+    //pushes the return address and jumps
+    Call,
+    Ret,
+    //stops the cpu, r0 holds the exit code
+    Halt,
+}
+
+impl Op {
+    pub const ALL: [Op; 26] = [
+        Op::Ldi,
+        Op::Mov,
+        Op::Add,
+        Op::Sub,
+        Op::Mul,
+        Op::Div,
+        Op::Divs,
+        Op::And,
+        Op::Or,
+        Op::Xor,
+        Op::Shl,
+        Op::Shr,
+        Op::Sar,
+        Op::Cmp,
+        Op::Jmp,
+        Op::Jeq,
+        Op::Jne,
+        Op::Jlt,
+        Op::Jb,
+        Op::Ldw,
+        Op::Stw,
+        Op::Push,
+        Op::Pop,
+        Op::Call,
+        Op::Ret,
+        Op::Halt,
+    ];
+
+    ///operands of the operation in the order they are written and encoded
+    pub fn operand_kinds(&self) -> &'static [OperandKind] {
+        use OperandKind::*;
+        match self {
+            Op::Ldi => &[Reg, Imm],
+            Op::Mov => &[Reg, Reg],
+            Op::Add
+            | Op::Sub
+            | Op::Mul
+            | Op::Div
+            | Op::Divs
+            | Op::And
+            | Op::Or
+            | Op::Xor
+            | Op::Shl
+            | Op::Shr
+            | Op::Sar => &[Reg, Reg, Reg],
+            Op::Cmp => &[Reg, Reg],
+            Op::Jmp | Op::Jeq | Op::Jne | Op::Jlt | Op::Jb | Op::Call => &[Imm],
+            Op::Ldw => &[Reg, Mem],
+            Op::Stw => &[Mem, Reg],
+            Op::Push | Op::Pop => &[Reg],
+            Op::Ret | Op::Halt => &[],
+        }
+    }
+
+    ///name of the operation in target descriptions, the mnemonic is defined by the target
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Ldi => "ldi",
+            Op::Mov => "mov",
+            Op::Add => "add",
+            Op::Sub => "sub",
+            Op::Mul => "mul",
+            Op::Div => "div",
+            Op::Divs => "divs",
+            Op::And => "and",
+            Op::Or => "or",
+            Op::Xor => "xor",
+            Op::Shl => "shl",
+            Op::Shr => "shr",
+            Op::Sar => "sar",
+            Op::Cmp => "cmp",
+            Op::Jmp => "jmp",
+            Op::Jeq => "jeq",
+            Op::Jne => "jne",
+            Op::Jlt => "jlt",
+            Op::Jb => "jb",
+            Op::Ldw => "ldw",
+            Op::Stw => "stw",
+            Op::Push => "push",
+            Op::Pop => "pop",
+            Op::Call => "call",
+            Op::Ret => "ret",
+            Op::Halt => "halt",
+        }
+    }
+}
+
+///kind of an operand in the encoding, registers take one byte, immediates one word and memory
+///operands a register byte followed by an offset word
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OperandKind {
+    Reg,
+    //a number or the address of a label
+    Imm,
+    Mem,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Operand {
+    Reg(u8),
+    Imm(i64),
+    //address of a label, resolved by the assembler
+    Label(String),
+    //[register + offset]
+    Mem(u8, i64),
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Reg(reg) => write!(f, "r{}", reg),
+            Operand::Imm(imm) => write!(f, "{}", imm),
+            Operand::Label(label) => write!(f, "{}", label),
+            Operand::Mem(reg, 0) => write!(f, "[r{}]", reg),
+            Operand::Mem(reg, off) if *off < 0 => write!(f, "[r{} - {}]", reg, -off),
+            Operand::Mem(reg, off) => write!(f, "[r{} + {}]", reg, off),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Instr {
+    pub op: Op,
+    pub operands: Vec<Operand>,
+}
+
+impl Instr {
+    pub fn render(&self, target: &Target) -> String {
+        let mut out = target.mnemonic(self.op).to_string();
+        for (i, operand) in self.operands.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            out.push_str(sep);
+            out.push_str(&operand.to_string());
+        }
+        out
+    }
+}
+
+///one line of assembly
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsmLine {
+    Label(String),
+    Instr(Instr),
+    //places the following code at the address
+    Org(u64),
+    //null terminated string
+    Str(String),
+    //words that are numbers or addresses of labels
+    Word(Vec<Operand>),
+    Comment(String),
+}
+
+///escapes a string so it can be written between quotes in assembly
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => escaped.push_str("\\0"),
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl AsmLine {
+    ///renders the line with the mnemonics of the target
+    pub fn render(&self, target: &Target) -> String {
+        match self {
+            AsmLine::Label(label) => format!("{}:", label),
+            AsmLine::Instr(instr) => format!("    {}", instr.render(target)),
+            AsmLine::Org(addr) => format!("    .org {:#06x}", addr),
+            AsmLine::Str(s) => format!("    .string \"{}\"", escape(s)),
+            AsmLine::Word(words) => {
+                let words: Vec<String> = words.iter().map(Operand::to_string).collect();
+                format!("    .word {}", words.join(", "))
+            }
+            AsmLine::Comment(cmt) => format!("; {}", cmt),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! memory layout of values on the target
+//!
+//! integers take as many bytes as they have bits, bools and chars one byte and strings one word
+//! for their address, pointers as many bytes as addresses of the target need. enums are stored
+//! like the integer type of their representation. every field of a struct is aligned to its size,
+//! but at most to the alignment of the target. a struct is aligned like its most aligned field and
+//! its size is padded to a multiple of that alignment, so structs placed one after another stay
+//! aligned. the elements of an array follow each other without padding, their size is already a
+//! multiple of their alignment
+//!
+//! on the stack every scalar still takes a whole word, only structs and arrays use this layout.
+//! the bytes of a scalar whose address is taken are the low bytes of its word
+
+use crate::parser::ast::{StructAST, TypeAST};
+
+use super::resolver::TypeTable;
+use super::target::Target;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldLayout {
+    pub name: String,
+    pub field_type: TypeAST,
+    //bytes from the start of the struct
+    pub offset: i64,
+    pub size: i64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StructLayout {
+    pub size: i64,
+    pub align: i64,
+    pub fields: Vec<FieldLayout>,
+}
+
+impl StructLayout {
+    pub fn field(&self, name: &str) -> &FieldLayout {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .expect("the typechecker resolved every field")
+    }
+}
+
+fn align_to(offset: i64, align: i64) -> i64 {
+    (offset + align - 1) / align * align
+}
+
+///size and alignment of a value of type tp in bytes
+pub fn size_align(tp: &TypeAST, target: &Target, types: &TypeTable) -> (i64, i64) {
+    if let Some(def) = types.struct_of(tp) {
+        let layout = struct_layout(def, target, types);
+        return (layout.size, layout.align);
+    }
+    if let Some(def) = types.enum_of(tp) {
+        return size_align(&def.repr(), target, types);
+    }
+    if let TypeAST::Array(element, len) = tp {
+        let (size, align) = size_align(element, target, types);
+        return (size * *len as i64, align);
+    }
+    let size = match tp {
+        TypeAST::Bool | TypeAST::Char => 1,
+        TypeAST::Pointer(_) => target.pointer_bytes(),
+        tp => tp
+            .int_bits()
+            .map_or(target.word_bytes(), |bits| bits as i64 / 8),
+    };
+    (size, size.min(target.align as i64))
+}
+
+///places the fields in the order they are defined, the typechecker rejects recursive structs
+pub fn struct_layout(def: &StructAST, target: &Target, types: &TypeTable) -> StructLayout {
+    let mut offset = 0;
+    let mut struct_align = 1;
+    let mut fields = Vec::new();
+    for field in &def.fields {
+        let (size, align) = size_align(&field.field_type, target, types);
+        offset = align_to(offset, align);
+        struct_align = struct_align.max(align);
+        fields.push(FieldLayout {
+            name: field.name.clone(),
+            field_type: field.field_type.clone(),
+            offset,
+            size,
+        });
+        offset += size;
+    }
+    StructLayout {
+        size: align_to(offset, struct_align),
+        align: struct_align,
+        fields,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{ast::StmtAST, lexer::Lexer, parser::Parser};
+
+    fn layouts(program: &str, target: &Target) -> Vec<StructLayout> {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        let mut types = TypeTable::new();
+        types.add_from_body(&body);
+        body.stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                StmtAST::Struct(def) => Some(struct_layout(def, target, &types)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn offsets(layout: &StructLayout) -> Vec<i64> {
+        layout.fields.iter().map(|field| field.offset).collect()
+    }
+
+    #[test]
+    fn test_aligned_fields() {
+        let program = "struct A { a: u8, b: i32, c: bool, d: u16 }
+            struct B { x: u8, a: A }";
+        let structs = layouts(program, &Target::default());
+        //padding after a and c, the size is a multiple of the largest alignment
+        assert_eq!(offsets(&structs[0]), [0, 4, 8, 10]);
+        assert_eq!((structs[0].size, structs[0].align), (12, 4));
+        assert_eq!(offsets(&structs[1]), [0, 4]);
+        assert_eq!(structs[1].size, 16);
+    }
+
+    #[test]
+    fn test_packed_fields() {
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        let program = "struct A { a: u8, b: i16, s: str }
+            struct B { x: u8, a: A, y: char }";
+        let structs = layouts(program, &target);
+        assert_eq!(offsets(&structs[0]), [0, 1, 3]);
+        assert_eq!((structs[0].size, structs[0].align), (5, 1));
+        assert_eq!(offsets(&structs[1]), [0, 1, 6]);
+        assert_eq!(structs[1].size, 7);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod assembler;
+pub mod codegen;
+mod consteval;
+pub mod flow;
+pub mod isa;
+mod layout;
 mod resolver;
+pub mod target;
 pub mod typechecks;
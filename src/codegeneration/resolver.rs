@@ -1,83 +1,199 @@
-use crate::parser::ast::{
-    BodyAST, BoolAST, CallAST, DeclAssignAST, DeclarationAST, ExprAST, FnSignatureAST, NumberAST,
-    StmtAST, TypeAST, VariableAST,
+use std::collections::HashMap;
+
+use crate::parser::{
+    ast::{
+        BodyAST, DeclarationAST, EnumAST, FnSignatureAST, StmtAST, StructAST, SymbolId, TypeAST,
+        VariableAST,
+    },
+    lexer::Span,
 };
 
-pub struct VarResolver {
-    vars: Vec<DeclarationAST>,
+enum Symbol {
+    Variable(DeclarationAST),
+    Function(FnSignatureAST),
+}
+
+///the names visible in one body, variables and functions don't share names
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<String, SymbolId>,
+    functions: HashMap<String, SymbolId>,
 }
 
-pub struct FunctionResolver {
-    signt: Vec<FnSignatureAST>,
+///the variables and functions of the program, names are looked up from the innermost scope
+///outwards so inner declarations shadow outer ones
+pub struct SymbolTable {
+    //indexed by SymbolId
+    symbols: Vec<Symbol>,
+    //the innermost scope is at the end
+    scopes: Vec<Scope>,
 }
 
-impl VarResolver {
-    ///creates a fresh resolver with no variables stored
+///the struct and enum definitions of the program by name, they are only defined at the top level
+///so they are visible everywhere
+pub struct TypeTable {
+    structs: HashMap<String, StructAST>,
+    enums: HashMap<String, EnumAST>,
+}
+
+impl SymbolTable {
+    ///creates a table with only the top level scope
     pub fn new() -> Self {
-        Self { vars: Vec::new() }
+        Self {
+            symbols: Vec::new(),
+            scopes: vec![Scope::default()],
+        }
     }
 
-    ///creates a new Resolver with access to all variables with lower scope
-    pub fn new_scoped(&self) -> Self {
-        Self {
-            vars: self.vars.clone(),
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    ///leaves the innermost scope, its symbols keep their ids
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+        assert!(!self.scopes.is_empty(), "popped the top level scope");
+    }
+
+    fn innermost(&mut self) -> &mut Scope {
+        self.scopes
+            .last_mut()
+            .expect("the top level scope is never popped")
+    }
+
+    ///adds a variable to the innermost scope and gives decl its id, it shadows all variables of
+    ///the same name including the ones declared before it in the same scope
+    pub fn add_variable(&mut self, decl: &mut DeclarationAST) -> SymbolId {
+        let id = SymbolId(self.symbols.len());
+        decl.id = Some(id);
+        self.symbols.push(Symbol::Variable(decl.clone()));
+        self.innermost().variables.insert(decl.name.clone(), id);
+        id
+    }
+
+    ///adds an argument to the innermost scope, which only holds the arguments of one function,
+    ///returns the previous argument if the name is taken
+    pub fn add_argument(&mut self, arg: &mut DeclarationAST) -> Result<SymbolId, &DeclarationAST> {
+        if let Some(&previous) = self.innermost().variables.get(&arg.name) {
+            return Err(self.variable(previous));
+        }
+        Ok(self.add_variable(arg))
+    }
+
+    ///adds the signatures of all functions defined directly in body to the innermost scope and
+    ///gives them their ids, so they can be called before their definition. returns the names and
+    ///spans of the functions whose name was already taken in the scope, these are left out of
+    ///the table
+    pub fn add_functions(&mut self, body: &mut BodyAST) -> Vec<(String, Span)> {
+        let mut duplicates = Vec::new();
+        for stmt in &mut body.stmts {
+            if let StmtAST::Function(func) = stmt {
+                let signt = &mut func.fn_signt;
+                if self.innermost().functions.contains_key(&signt.name) {
+                    duplicates.push((signt.name.clone(), signt.span));
+                    continue;
+                }
+                let id = SymbolId(self.symbols.len());
+                signt.id = Some(id);
+                self.symbols.push(Symbol::Function(signt.clone()));
+                self.innermost().functions.insert(signt.name.clone(), id);
+            }
+        }
+        duplicates
+    }
+
+    pub fn variable(&self, id: SymbolId) -> &DeclarationAST {
+        match &self.symbols[id.0] {
+            Symbol::Variable(decl) => decl,
+            Symbol::Function(_) => panic!("{:?} is a function", id),
         }
     }
 
-    ///add a declaration of a variable to the resolver
-    pub fn add_decl(&mut self, decl: DeclarationAST) {
-        self.vars.push(decl)
+    pub fn function(&self, id: SymbolId) -> &FnSignatureAST {
+        match &self.symbols[id.0] {
+            Symbol::Function(signt) => signt,
+            Symbol::Variable(_) => panic!("{:?} is a variable", id),
+        }
     }
 
-    ///get the coresponding Declaration to a Variable
-    pub fn resolve_variable(&self, var: &VariableAST) -> Option<DeclarationAST> {
-        self.vars.iter().find(|dec| dec.name == var.name).cloned()
+    ///the innermost declaration of the variable
+    pub fn resolve_variable(&self, var: &VariableAST) -> Option<(SymbolId, &DeclarationAST)> {
+        let id = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(&var.name))?;
+        Some((*id, self.variable(*id)))
+    }
+
+    ///the innermost function called name
+    pub fn resolve_function(&self, name: &str) -> Option<(SymbolId, &FnSignatureAST)> {
+        let id = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.functions.get(name))?;
+        Some((*id, self.function(*id)))
     }
 }
 
-impl FunctionResolver {
-    ///creates a fresh resolver with no variables stored
+impl TypeTable {
     pub fn new() -> Self {
-        Self { signt: Vec::new() }
+        Self {
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+        }
     }
 
-    pub fn new_from_body(body: &BodyAST) -> Self {
-        let mut new = Self::new();
+    ///adds the structs and enums defined in the program, returns the names and spans of the
+    ///definitions whose name was already taken, these are left out of the table
+    pub fn add_from_body(&mut self, body: &BodyAST) -> Vec<(String, Span)> {
+        let mut duplicates = Vec::new();
         for stmt in &body.stmts {
             match stmt {
-                StmtAST::Function(func) => new.add_signature(func.fn_signt.clone()),
+                StmtAST::Struct(def) if self.definition_span(&def.name).is_none() => {
+                    self.structs.insert(def.name.clone(), def.clone());
+                }
+                StmtAST::Enum(def) if self.definition_span(&def.name).is_none() => {
+                    self.enums.insert(def.name.clone(), def.clone());
+                }
+                StmtAST::Struct(StructAST { name, span, .. })
+                | StmtAST::Enum(EnumAST { name, span, .. }) => {
+                    duplicates.push((name.clone(), *span))
+                }
                 _ => {}
             }
         }
-        new
+        duplicates
     }
 
-    pub fn add_from_body(&mut self, body: &BodyAST) {
-        for stmt in &body.stmts {
-            match stmt {
-                StmtAST::Function(func) => self.add_signature(func.fn_signt.clone()),
-                _ => {}
-            }
-        }
+    ///where the struct or enum called name is defined
+    pub fn definition_span(&self, name: &str) -> Option<Span> {
+        let def = self.structs.get(name).map(|def| def.span);
+        def.or_else(|| self.enums.get(name).map(|def| def.span))
     }
 
-    ///creates a new Resolver with access to all variables with lower scope
-    pub fn new_scoped(&self) -> Self {
-        Self {
-            signt: self.signt.clone(),
-        }
+    pub fn resolve_struct(&self, name: &str) -> Option<&StructAST> {
+        self.structs.get(name)
     }
 
-    ///add a fn signature to the resolver
-    pub fn add_signature(&mut self, decl: FnSignatureAST) {
-        self.signt.push(decl)
+    pub fn resolve_enum(&self, name: &str) -> Option<&EnumAST> {
+        self.enums.get(name)
     }
 
-    ///get the coresponding Declaration to a Variable
-    pub fn resolve_call(&self, call: CallAST) -> Option<FnSignatureAST> {
-        self.signt
-            .iter()
-            .find(|sign| sign.name == call.callee)
-            .cloned()
+    ///the definition of the enum if tp is one
+    pub fn enum_of(&self, tp: &TypeAST) -> Option<&EnumAST> {
+        match tp {
+            TypeAST::Custom(name) => self.resolve_enum(name),
+            _ => None,
+        }
+    }
+
+    ///the definition of the struct if tp is one
+    pub fn struct_of(&self, tp: &TypeAST) -> Option<&StructAST> {
+        match tp {
+            TypeAST::Custom(name) => self.resolve_struct(name),
+            _ => None,
+        }
     }
 }
@@ -0,0 +1,340 @@
+use super::isa::Op;
+
+///description of the cpu the program is compiled for, read from a target description file
+///
+///instructions are encoded as their opcode byte followed by the operands, registers take one
+///byte, immediates, addresses and memory offsets take one word
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Target {
+    pub name: String,
+    pub word_bits: u32,
+    pub address_bits: u32,
+    pub registers: u8,
+    pub big_endian: bool,
+    pub memory_size: u64,
+    pub code_start: u64,
+    //data is placed right after the code if there is no data_start
+    pub data_start: Option<u64>,
+    pub stack_top: u64,
+    //memory mapped output port, one word wide
+    pub io_out: u64,
+    //largest alignment of struct fields in bytes
+    pub align: u64,
+    instructions: Vec<InstrDef>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InstrDef {
+    pub op: Op,
+    pub mnemonic: String,
+    pub opcode: u8,
+}
+
+///the target that is used if no target description is given
+const DEFAULT_TARGET: &str = include_str!("../../targets/mcpu32.target");
+
+fn parse_number(value: &str) -> Result<u64, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>(),
+    };
+    parsed.map_err(|_| format!("invalid number `{}`", value))
+}
+
+impl Target {
+    ///parses a target description, errors contain the line they occurred in
+    pub fn parse(description: &str) -> Result<Self, String> {
+        let mut target = Target {
+            name: String::new(),
+            word_bits: 0,
+            address_bits: 0,
+            registers: 0,
+            big_endian: false,
+            memory_size: 0,
+            code_start: 0,
+            data_start: None,
+            stack_top: 0,
+            io_out: 0,
+            align: 0,
+            instructions: Vec::new(),
+        };
+        let mut section = String::new();
+        for (i, line) in description.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            target
+                .parse_line(&mut section, line)
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+        }
+        target.validate()?;
+        if target.align == 0 {
+            target.align = target.word_bytes() as u64;
+        }
+        Ok(target)
+    }
+
+    fn parse_line(&mut self, section: &mut String, line: &str) -> Result<(), String> {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            *section = name.trim().to_string();
+            return Ok(());
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("expected `key = value`, found `{}`", line));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match (section.as_str(), key) {
+            ("cpu", "name") => self.name = value.to_string(),
+            ("cpu", "word_bits") => self.word_bits = parse_number(value)? as u32,
+            ("cpu", "address_bits") => self.address_bits = parse_number(value)? as u32,
+            ("cpu", "registers") => {
+                self.registers = u8::try_from(parse_number(value)?)
+                    .map_err(|_| "at most 255 registers are supported".to_string())?
+            }
+            ("cpu", "endian") => {
+                self.big_endian = match value {
+                    "big" => true,
+                    "little" => false,
+                    other => return Err(format!("unknown endianness `{}`", other)),
+                }
+            }
+            ("memory", "size") => self.memory_size = parse_number(value)?,
+            ("memory", "code_start") => self.code_start = parse_number(value)?,
+            ("memory", "data_start") => self.data_start = Some(parse_number(value)?),
+            ("memory", "stack_top") => self.stack_top = parse_number(value)?,
+            ("memory", "io_out") => self.io_out = parse_number(value)?,
+            ("memory", "align") => {
+                self.align = parse_number(value)?;
+                if !self.align.is_power_of_two() {
+                    return Err("align has to be a power of two".to_string());
+                }
+            }
+            ("instructions", name) => {
+                let op = Op::ALL
+                    .iter()
+                    .find(|op| op.name() == name)
+                    .ok_or_else(|| format!("unknown operation `{}`", name))?;
+                let mut parts = value.split_whitespace();
+                let (Some(mnemonic), Some(opcode), None) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(format!("expected `{} = MNEMONIC opcode`", name));
+                };
+                let opcode = u8::try_from(parse_number(opcode)?)
+                    .map_err(|_| format!("opcode of `{}` does not fit into a byte", name))?;
+                self.instructions.push(InstrDef {
+                    op: *op,
+                    mnemonic: mnemonic.to_uppercase(),
+                    opcode,
+                });
+            }
+            (section, key) => return Err(format!("unknown key `{}` in [{}]", key, section)),
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if ![8, 16, 32].contains(&self.word_bits) {
+            return Err("word_bits has to be 8, 16 or 32".to_string());
+        }
+        if self.address_bits == 0 || self.address_bits > self.word_bits {
+            return Err("addresses have to fit into a word".to_string());
+        }
+        //r0 to r2 are scratch registers, the last two are the frame and stack pointer
+        if self.registers < 5 {
+            return Err("at least 5 registers are required".to_string());
+        }
+        if self.align > self.word_bytes() as u64 {
+            return Err("align can't be larger than a word".to_string());
+        }
+        if self.memory_size > 1 << self.address_bits {
+            return Err("the memory is larger than the address space".to_string());
+        }
+        if self.stack_top > self.memory_size
+            || self.code_start >= self.memory_size
+            || self.io_out + self.word_bytes() as u64 > self.memory_size
+            || self
+                .data_start
+                .is_some_and(|start| start >= self.memory_size)
+        {
+            return Err("the memory map does not fit into the memory".to_string());
+        }
+        for op in Op::ALL {
+            let defs = self.instructions.iter().filter(|def| def.op == op).count();
+            if defs != 1 {
+                return Err(format!(
+                    "operation `{}` has to be defined exactly once",
+                    op.name()
+                ));
+            }
+        }
+        for def in &self.instructions {
+            if self
+                .instructions
+                .iter()
+                .any(|other| other.op != def.op && other.opcode == def.opcode)
+            {
+                return Err(format!("opcode {:#04x} is used twice", def.opcode));
+            }
+            //the assembler ignores the case of mnemonics
+            if self.instructions.iter().any(|other| {
+                other.op != def.op && other.mnemonic.eq_ignore_ascii_case(&def.mnemonic)
+            }) {
+                return Err(format!("mnemonic `{}` is used twice", def.mnemonic));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let description =
+            std::fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+        Self::parse(&description)
+    }
+
+    pub fn word_bytes(&self) -> i64 {
+        self.word_bits as i64 / 8
+    }
+
+    ///bytes of a pointer in memory, the address bits rounded up to a power of two bytes
+    pub fn pointer_bytes(&self) -> i64 {
+        self.address_bits.div_ceil(8).next_power_of_two() as i64
+    }
+
+    pub fn mnemonic(&self, op: Op) -> &str {
+        //every operation is defined, this is checked when the target is parsed
+        &self
+            .instructions
+            .iter()
+            .find(|def| def.op == op)
+            .expect("operation missing in target")
+            .mnemonic
+    }
+
+    pub fn opcode(&self, op: Op) -> u8 {
+        self.instructions
+            .iter()
+            .find(|def| def.op == op)
+            .expect("operation missing in target")
+            .opcode
+    }
+
+    pub fn op_by_opcode(&self, opcode: u8) -> Option<Op> {
+        self.instructions
+            .iter()
+            .find(|def| def.opcode == opcode)
+            .map(|def| def.op)
+    }
+
+    pub fn op_by_mnemonic(&self, mnemonic: &str) -> Option<Op> {
+        self.instructions
+            .iter()
+            .find(|def| def.mnemonic.eq_ignore_ascii_case(mnemonic))
+            .map(|def| def.op)
+    }
+
+    ///true if the value fits into a word, either as signed or as unsigned number
+    pub fn fits_word(&self, value: i64) -> bool {
+        let bits = self.word_bits;
+        value >= -(1 << (bits - 1)) && value < 1 << bits
+    }
+
+    ///encodes the lower bits of value as one word in the byte order of the target
+    pub fn encode_word(&self, value: i64) -> Vec<u8> {
+        let bytes = &value.to_le_bytes()[..self.word_bytes() as usize];
+        if self.big_endian {
+            bytes.iter().rev().copied().collect()
+        } else {
+            bytes.to_vec()
+        }
+    }
+
+    ///decodes a word in the byte order of the target, the result is not sign extended
+    pub fn decode_word(&self, bytes: &[u8]) -> u64 {
+        let fold = |word: u64, byte: &u8| word << 8 | *byte as u64;
+        if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        }
+    }
+
+    pub fn frame_pointer(&self) -> u8 {
+        self.registers - 2
+    }
+
+    pub fn stack_pointer(&self) -> u8 {
+        self.registers - 1
+    }
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TARGET).expect("invalid default target")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_target() {
+        let target = Target::default();
+        assert_eq!(target.name, "mcpu32");
+        assert_eq!(target.word_bytes(), 4);
+        assert_eq!(target.mnemonic(Op::Divs), "DIVS");
+        assert_eq!(target.stack_pointer(), 15);
+    }
+
+    #[test]
+    fn test_bundled_targets() {
+        let target = Target::load("./targets/redstone16.target").unwrap();
+        assert_eq!(target.word_bits, 16);
+        assert!(target.big_endian);
+        assert_eq!(target.data_start, Some(0x4000));
+        assert_eq!(target.mnemonic(Op::Jeq), "BEQ");
+        assert_eq!(target.align, 1);
+        //without align fields are aligned to words
+        let unaligned = DEFAULT_TARGET.replace("align = 4", "");
+        assert_eq!(Target::parse(&unaligned).unwrap().align, 4);
+    }
+
+    #[test]
+    fn test_invalid_targets() {
+        let broken = DEFAULT_TARGET.replace("word_bits = 32", "word_bits = thirty");
+        assert_eq!(
+            Target::parse(&broken).unwrap_err(),
+            "line 6: invalid number `thirty`"
+        );
+        let missing = DEFAULT_TARGET.replace("mul = MUL 0x12", "");
+        assert_eq!(
+            Target::parse(&missing).unwrap_err(),
+            "operation `mul` has to be defined exactly once"
+        );
+        let wide = DEFAULT_TARGET.replace("align = 4", "align = 8");
+        assert_eq!(
+            Target::parse(&wide).unwrap_err(),
+            "align can't be larger than a word"
+        );
+        let twice = DEFAULT_TARGET.replace("mul = MUL 0x12", "mul = MUL 0x11");
+        assert_eq!(
+            Target::parse(&twice).unwrap_err(),
+            "opcode 0x11 is used twice"
+        );
+        let same_name = DEFAULT_TARGET.replace("mul = MUL 0x12", "mul = sub 0x12");
+        assert_eq!(
+            Target::parse(&same_name).unwrap_err(),
+            "mnemonic `SUB` is used twice"
+        );
+        let data = DEFAULT_TARGET.replace(
+            "code_start = 0x0000",
+            "code_start = 0\ndata_start = 0x10000",
+        );
+        assert_eq!(
+            Target::parse(&data).unwrap_err(),
+            "the memory map does not fit into the memory"
+        );
+    }
+}
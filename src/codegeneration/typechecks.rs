@@ -1,266 +1,2702 @@
+use crate::diagnostics::{self, Diagnostic};
 use crate::parser::{
     ast::{
-        BinaryExpressionAST, BodyAST, CallAST, DeclarationAST, ExprAST, NumberAST, StmtAST, TypeAST,
+        ArrayLiteralAST, AssignStmtAST, BinaryExpressionAST, BodyAST, CallAST, CastAST,
+        DeclarationAST, ElseAST, EnumAST, EnumVariantAST, ExprAST, FieldAccessAST, IfStmtAST,
+        IndexAST, MatchStmtAST, NumberAST, PatternAST, PrintFormat, PrintStmtAST, StmtAST,
+        StructAST, StructLiteralAST, SymbolId, TypeAST, UnaryExpressionAST, VariableAST,
     },
-    lexer::Token,
+    lexer::{Span, Token},
 };
 
-use super::resolver::{FunctionResolver, VarResolver};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use super::consteval::eval_globals;
+use super::flow::{check_flow, repeated_assignments};
+use super::resolver::{SymbolTable, TypeTable};
 
 pub struct Typechecker {
-    var_resolver: VarResolver,
-    funct_resolver: FunctionResolver,
+    //shared by all nested checkers, each of them checks its body in its own scope
+    symbols: SymbolTable,
+    types: TypeTable,
     body: BodyAST,
     expected_rt_tp: TypeAST,
+    //number of loops around the body, break and continue need at least one
+    loops: usize,
+    //type of integer literals that are not constrained by their context
+    default_int: TypeAST,
+    //indexed by TypeAST::Infer, shared by all nested checkers
+    vars: Vec<TypeVar>,
+    //the immutable variables, shared by all nested checkers
+    bindings: HashMap<SymbolId, Binding>,
+    //set for the checker of the whole program, its variables are global
+    top_level: bool,
+    errors: Vec<Diagnostic>,
+}
+
+///an immutable variable, assignments to it are checked against the way it was declared
+#[derive(Clone)]
+struct Binding {
+    decl: DeclarationAST,
+    kind: BindingKind,
+    //the first assignment on any path checked so far
+    assigned: Option<Span>,
+}
+
+#[derive(Clone, Copy)]
+enum BindingKind {
+    Argument,
+    //declared with a value, it can not be assigned at all
+    Initialized,
+    //declared without a value, it can be assigned once outside of the loops around the declaration
+    Deferred { loops: usize },
+    //a constant, its value is inlined where it is used, so it can't be assigned and has no address
+    Constant,
+    //a global variable declared without `mut`
+    Static,
+}
+
+///the unknown type of a declaration without annotation or of an unconstrained literal, it is
+///bound by the first use that requires a type
+struct TypeVar {
+    name: String,
+    span: Span,
+    tp: Option<TypeAST>,
+    //only integers can be bound, set once an untyped literal flows into it
+    integer: bool,
+}
+
+fn mismatched_types(expected: &TypeAST, found: &TypeAST, span: Span) -> Diagnostic {
+    Diagnostic::error(diagnostics::MISMATCHED_TYPES, "mismatched types", span)
+        .with_label(format!("expected `{}`, found `{}`", expected, found))
+}
+
+fn duplicate_definition(what: &str, name: &str, span: Span, previous: Span) -> Diagnostic {
+    Diagnostic::error(
+        diagnostics::DUPLICATE_DEFINITION,
+        format!("the {} `{}` is defined multiple times", what, name),
+        span,
+    )
+    .with_label(format!("`{}` redefined here", name))
+    .with_secondary(previous, format!("previous definition of `{}` here", name))
+}
+
+fn literal_out_of_range(num: &NumberAST, tp: &TypeAST, range: RangeInclusive<i64>) -> Diagnostic {
+    Diagnostic::error(
+        diagnostics::LITERAL_OUT_OF_RANGE,
+        format!("literal out of range for `{}`", tp),
+        num.span,
+    )
+    .with_note(format!(
+        "the literal `{}` does not fit into the type `{}` whose range is `{}..={}`",
+        num.num,
+        tp,
+        range.start(),
+        range.end()
+    ))
+}
+
+///true if tp still contains integer literals whose type is not known yet
+fn has_untyped(tp: &TypeAST) -> bool {
+    match tp {
+        TypeAST::UntypedInt => true,
+        TypeAST::Array(element, _) => has_untyped(element),
+        _ => false,
+    }
+}
+
+///where the type of expr is stored, literals and casts have none besides the type of numbers,
+///which is resolved on its own
+fn annotation(expr: &mut ExprAST) -> Option<&mut TypeAST> {
+    match expr {
+        ExprAST::Variable(var) => Some(&mut var.tp),
+        ExprAST::Call(call) => Some(&mut call.tp),
+        ExprAST::BinaryExpression(bin_expr) => Some(&mut bin_expr.tp),
+        ExprAST::FieldAccess(access) => Some(&mut access.tp),
+        ExprAST::ArrayLiteral(lit) => Some(&mut lit.tp),
+        ExprAST::ArrayRepeat(lit) => Some(&mut lit.tp),
+        ExprAST::Index(index) => Some(&mut index.tp),
+        ExprAST::Unary(unary) => Some(&mut unary.tp),
+        ExprAST::Number(_)
+        | ExprAST::StringLiteral(_)
+        | ExprAST::BoolLiteral(_)
+        | ExprAST::CharLiteral(_)
+        | ExprAST::StructLiteral(_)
+        | ExprAST::EnumVariant(_)
+        | ExprAST::Cast(_) => None,
+    }
+}
+
+///the value of an integer expression that only uses literals, None if it is not constant or
+///overflows
+fn const_int(expr: &ExprAST) -> Option<i64> {
+    match expr {
+        ExprAST::Number(num) => Some(num.num),
+        ExprAST::Unary(unary) if unary.op == Token::Minus => const_int(&unary.expr)?.checked_neg(),
+        ExprAST::BinaryExpression(bin_expr) => {
+            let (lhs, rhs) = (const_int(&bin_expr.lhs)?, const_int(&bin_expr.rhs)?);
+            match bin_expr.op {
+                Token::Plus => lhs.checked_add(rhs),
+                Token::Minus => lhs.checked_sub(rhs),
+                Token::Mult => lhs.checked_mul(rhs),
+                Token::Divide => lhs.checked_div(rhs),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn cannot_negate(tp: &TypeAST, span: Span) -> Diagnostic {
+    Diagnostic::error(
+        diagnostics::UNSUPPORTED_OPERATOR,
+        format!("unary operator `-` is not supported for type `{}`", tp),
+        span,
+    )
+    .with_note("only signed integers can be negated")
+}
+
+fn unsupported_operator(op: &Token, tp: &TypeAST, span: Span) -> Diagnostic {
+    Diagnostic::error(
+        diagnostics::UNSUPPORTED_OPERATOR,
+        format!(
+            "binary operator `{}` is not supported for type `{}`",
+            op, tp
+        ),
+        span,
+    )
 }
 
-//TODO: add type inference for declarations and declarations with assignments if type is undefined
 impl Typechecker {
-    pub fn new(
-        body: BodyAST,
-        var_resolver: Option<VarResolver>,
-        funct_resovler: Option<FunctionResolver>,
-        expected_rt_tp: TypeAST,
-    ) -> Self {
+    pub fn new(body: BodyAST, symbols: Option<SymbolTable>, expected_rt_tp: TypeAST) -> Self {
         Self {
-            var_resolver: var_resolver.unwrap_or(VarResolver::new()),
-            funct_resolver: funct_resovler.unwrap_or(FunctionResolver::new()),
+            symbols: symbols.unwrap_or_else(SymbolTable::new),
+            types: TypeTable::new(),
             body,
             expected_rt_tp,
+            loops: 0,
+            default_int: TypeAST::I32,
+            vars: Vec::new(),
+            bindings: HashMap::new(),
+            top_level: true,
+            errors: Vec::new(),
         }
     }
 
-    fn check_and_resolve_call(&self, call: &CallAST) -> TypeAST {
-        let signt = self
-            .funct_resolver
-            .resolve_call(call.clone())
-            .expect("function not found in scope");
-        assert_eq!(signt.args.len(), call.args.len());
-        //compare types between given and declared args
-        signt
-            .args
-            .iter()
-            .zip(call.args.iter())
-            .for_each(|(signt_arg, call_arg)| {
-                assert_eq!(
-                    signt_arg.var_type,
-                    self.check_and_resolve_expression(call_arg),
-                    "type of argument does not match type of expression"
-                )
-            });
-        if call.rt_value_ignored {
-            TypeAST::Void
-        } else {
-            signt.rt_type
+    ///sets the type of integer literals that are not constrained by their context
+    pub fn with_default_int(mut self, tp: TypeAST) -> Self {
+        self.default_int = tp;
+        self
+    }
+
+    fn new_var(&mut self, name: &str, span: Span, integer: bool) -> TypeAST {
+        self.vars.push(TypeVar {
+            name: name.to_string(),
+            span,
+            tp: None,
+            integer,
+        });
+        TypeAST::Infer(self.vars.len() - 1)
+    }
+
+    ///substitutes the bound type variables in tp
+    fn resolve(&self, tp: &TypeAST) -> TypeAST {
+        match tp {
+            TypeAST::Infer(id) => match &self.vars[*id].tp {
+                Some(bound) => self.resolve(bound),
+                None => tp.clone(),
+            },
+            TypeAST::Array(element, len) => TypeAST::Array(Box::new(self.resolve(element)), *len),
+            TypeAST::Pointer(pointee) => TypeAST::Pointer(Box::new(self.resolve(pointee))),
+            tp => tp.clone(),
         }
     }
 
-    fn check_iteger_bin_expr(&self, expr: &BinaryExpressionAST) -> TypeAST {
-        match self.check_and_resolve_expression(&expr.lhs) {
-            TypeAST::I8 => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::I8,
-                    "Incompatible Types, type should be i8"
-                );
-                TypeAST::I8
-            }
-            TypeAST::I16 => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::I16,
-                    "Incompatible Types, type should be i16"
-                );
-                TypeAST::I16
-            }
-            TypeAST::I32 => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::I32,
-                    "Incompatible Types, type should be i32"
-                );
-                TypeAST::I32
+    ///tp as it is shown in errors, unbound integer variables are shown as `{integer}`
+    fn shown(&self, tp: &TypeAST) -> TypeAST {
+        match self.resolve(tp) {
+            TypeAST::Infer(id) if self.vars[id].integer => TypeAST::UntypedInt,
+            TypeAST::Array(element, len) => TypeAST::Array(Box::new(self.shown(&element)), len),
+            TypeAST::Pointer(pointee) => TypeAST::Pointer(Box::new(self.shown(&pointee))),
+            tp => tp,
+        }
+    }
+
+    fn mismatch(&self, expected: &TypeAST, found: &TypeAST, span: Span) -> Diagnostic {
+        mismatched_types(&self.shown(expected), &self.shown(found), span)
+    }
+
+    ///binds the type variables of expected and found so that found can be used where expected is
+    ///required, false if that is not possible
+    fn unify(&mut self, expected: &TypeAST, found: &TypeAST) -> bool {
+        match (self.resolve(expected), self.resolve(found)) {
+            (TypeAST::Infer(a), TypeAST::Infer(b)) if a == b => true,
+            (TypeAST::Infer(id), tp) | (tp, TypeAST::Infer(id)) => self.bind(id, tp),
+            (TypeAST::Array(expected, len), TypeAST::Array(found, found_len)) => {
+                len == found_len && self.unify(&expected, &found)
             }
-            TypeAST::U8 => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::U8,
-                    "Incompatible Types, type should be u8"
-                );
-                TypeAST::U8
+            (TypeAST::Pointer(expected), TypeAST::Pointer(found)) => self.unify(&expected, &found),
+            (expected, found) => expected.accepts(&found),
+        }
+    }
+
+    ///binds the unbound variable id to tp, false if the variable can not be of that type
+    fn bind(&mut self, id: usize, tp: TypeAST) -> bool {
+        match &tp {
+            TypeAST::Infer(other) if self.vars[id].integer => self.vars[*other].integer = true,
+            TypeAST::Infer(_) | TypeAST::Error => {}
+            //untyped literals are typed through resolve_untyped
+            TypeAST::UntypedInt => return false,
+            tp if self.vars[id].integer && !tp.is_integer() => return false,
+            _ => {}
+        }
+        self.vars[id].tp = Some(tp);
+        true
+    }
+
+    ///true for integers and type variables, variables can only be bound to integers afterwards
+    fn expect_integer(&mut self, tp: &TypeAST) -> bool {
+        match self.resolve(tp) {
+            TypeAST::Infer(id) => {
+                self.vars[id].integer = true;
+                true
             }
-            TypeAST::U16 => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::U16,
-                    "Incompatible Types, type should be u16"
-                );
-                TypeAST::U16
+            tp => tp.is_integer(),
+        }
+    }
+
+    ///the type of a value whose type has to be known at span, unconstrained integers take the
+    ///default type
+    fn known(&mut self, tp: &TypeAST, span: Span) -> TypeAST {
+        let TypeAST::Infer(id) = self.resolve(tp) else {
+            return self.resolve(tp);
+        };
+        let known = if self.vars[id].integer {
+            self.default_int.clone()
+        } else {
+            let var = &self.vars[id];
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::TYPE_ANNOTATIONS_NEEDED,
+                    "type annotations needed",
+                    span,
+                )
+                .with_label("type must be known at this point")
+                .with_secondary(var.span, format!("consider giving `{}` a type", var.name)),
+            );
+            TypeAST::Error
+        };
+        self.vars[id].tp = Some(known.clone());
+        known
+    }
+
+    ///binds the variables nothing constrained, integers to the default type, others are
+    ///ambiguous
+    fn finish_inference(&mut self) {
+        for id in 0..self.vars.len() {
+            if self.vars[id].tp.is_some() {
+                continue;
             }
-            TypeAST::U32 => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::U32,
-                    "Incompatible Types, type should be u32"
+            let var = &self.vars[id];
+            let tp = if var.integer {
+                self.default_int.clone()
+            } else {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::TYPE_ANNOTATIONS_NEEDED,
+                        "type annotations needed",
+                        var.span,
+                    )
+                    .with_label(format!("consider giving `{}` a type", var.name))
+                    .with_note("the type can not be inferred from any assignment or use"),
                 );
-                TypeAST::U32
+                TypeAST::Error
+            };
+            self.vars[id].tp = Some(tp);
+        }
+    }
+
+    ///replaces the type variables in the checked body by their types, literals that got their
+    ///type through a variable are range checked now
+    fn substitute_body(&mut self, body: &mut BodyAST) {
+        for stmt in &mut body.stmts {
+            match stmt {
+                StmtAST::Declaration(decl) => decl.var_type = self.resolve(&decl.var_type),
+                StmtAST::DeclAssign(declassg) | StmtAST::Const(declassg) => {
+                    declassg.decl.var_type = self.resolve(&declassg.decl.var_type);
+                    self.substitute_expr(&mut declassg.value);
+                }
+                StmtAST::Assign(ass) => {
+                    self.substitute_expr(&mut ass.target);
+                    self.substitute_expr(&mut ass.value);
+                }
+                StmtAST::Call(call) => {
+                    for arg in &mut call.args {
+                        self.substitute_expr(arg);
+                    }
+                }
+                StmtAST::Function(func) => self.substitute_body(&mut func.body),
+                StmtAST::If(if_st) => self.substitute_if(if_st),
+                StmtAST::While(while_st) => {
+                    self.substitute_expr(&mut while_st.condition);
+                    self.substitute_body(&mut while_st.body);
+                }
+                StmtAST::Match(match_st) => {
+                    self.substitute_expr(&mut match_st.scrutinee);
+                    for arm in &mut match_st.arms {
+                        self.substitute_body(&mut arm.body);
+                    }
+                }
+                StmtAST::Print(print) => self.substitute_expr(&mut print.expr),
+                StmtAST::Return(rtstmt) => self.substitute_expr(&mut rtstmt.expr),
+                StmtAST::Struct(_)
+                | StmtAST::Enum(_)
+                | StmtAST::Break(_)
+                | StmtAST::Continue(_) => {}
             }
-            other => panic!(
-                "binary opperator XORINT is not supported for type: {}",
-                other
-            ),
         }
     }
 
-    fn check_and_resolve_binary_expression(&self, expr: &BinaryExpressionAST) -> TypeAST {
-        assert_eq!(
-            self.check_and_resolve_expression(&expr.rhs),
-            self.check_and_resolve_expression(&expr.lhs),
-            "incompatible types lhs and rhs"
-        );
-        match &expr.op {
-            Token::XorInt
-            | Token::OrInt
-            | Token::AndInt
-            | Token::Minus
-            | Token::Mult
-            | Token::Divide => self.check_iteger_bin_expr(expr),
-            Token::XorBool | Token::OrBool | Token::AndBool | Token::Not => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    TypeAST::Bool,
-                    "bool operators can only be applied to booleans"
-                );
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.lhs),
-                    TypeAST::Bool,
-                    "bool operators can only be applied to booleans"
-                );
-                TypeAST::Bool
+    fn substitute_if(&mut self, if_st: &mut IfStmtAST) {
+        self.substitute_expr(&mut if_st.condition);
+        self.substitute_body(&mut if_st.body);
+        match &mut if_st.else_branch {
+            Some(ElseAST::If(else_if)) => self.substitute_if(else_if),
+            Some(ElseAST::Body(body)) => self.substitute_body(body),
+            None => {}
+        }
+    }
+
+    fn substitute_expr(&mut self, expr: &mut ExprAST) {
+        //negated variables whose type was inferred are checked now like literals
+        if let ExprAST::Unary(unary) = expr {
+            if let (Token::Minus, TypeAST::Infer(_)) = (&unary.op, &unary.tp) {
+                let tp = self.resolve(&unary.tp);
+                if tp.is_integer() && !tp.is_signed() {
+                    self.errors.push(cannot_negate(&tp, unary.span));
+                }
             }
-            Token::Plus => {
-                if self.check_and_resolve_expression(&expr.rhs) == TypeAST::Str {
-                    TypeAST::Str
-                } else {
-                    self.check_iteger_bin_expr(expr)
+        }
+        if let Some(tp) = annotation(expr) {
+            *tp = self.resolve(tp);
+        }
+        let children: Vec<&mut ExprAST> = match expr {
+            ExprAST::Number(num) => {
+                if let TypeAST::Infer(_) = num.tp {
+                    num.tp = self.resolve(&num.tp);
+                    if let Some(range) =
+                        num.tp.int_range().filter(|range| !range.contains(&num.num))
+                    {
+                        self.errors.push(literal_out_of_range(num, &num.tp, range));
+                    }
                 }
+                vec![]
             }
-            Token::Equal => {
-                assert_eq!(
-                    self.check_and_resolve_expression(&expr.rhs),
-                    self.check_and_resolve_expression(&expr.lhs),
-                    "both sides of binary expression have to be of the same type"
-                );
-                TypeAST::Bool
+            ExprAST::Call(call) => call.args.iter_mut().collect(),
+            ExprAST::BinaryExpression(bin_expr) => vec![&mut bin_expr.lhs, &mut bin_expr.rhs],
+            ExprAST::StructLiteral(lit) => {
+                lit.fields.iter_mut().map(|init| &mut init.value).collect()
             }
-            other => panic!("unexpected operator: {}", other),
+            ExprAST::FieldAccess(access) => vec![&mut access.expr],
+            ExprAST::ArrayLiteral(lit) => lit.elements.iter_mut().collect(),
+            ExprAST::ArrayRepeat(lit) => vec![&mut lit.value],
+            ExprAST::Index(index) => vec![&mut index.expr, &mut index.index],
+            ExprAST::Unary(unary) => vec![&mut unary.expr],
+            ExprAST::Cast(cast) => vec![&mut cast.expr],
+            ExprAST::Variable(_)
+            | ExprAST::StringLiteral(_)
+            | ExprAST::BoolLiteral(_)
+            | ExprAST::CharLiteral(_)
+            | ExprAST::EnumVariant(_) => vec![],
+        };
+        for child in children {
+            self.substitute_expr(child);
         }
     }
 
-    fn check_and_resolve_expression(&self, expr: &ExprAST) -> TypeAST {
-        match expr {
-            //in case of variable resolve variable and return the type
+    fn add_binding(&mut self, id: SymbolId, decl: &DeclarationAST, kind: BindingKind) {
+        if !decl.is_mut {
+            let binding = Binding {
+                decl: decl.clone(),
+                kind,
+                assigned: None,
+            };
+            self.bindings.insert(id, binding);
+        }
+    }
+
+    ///adds the assignments of a branch, a variable is assigned after branches if any of them
+    ///assigns it
+    fn merge_bindings(&mut self, branch: HashMap<SymbolId, Binding>) {
+        for (id, binding) in branch {
+            let merged = self.bindings.entry(id).or_insert(binding.clone());
+            merged.assigned = merged.assigned.or(binding.assigned);
+        }
+    }
+
+    ///the type of a place without reporting errors, they are reported when it is checked
+    fn place_type(&self, place: &ExprAST) -> Option<TypeAST> {
+        match place {
             ExprAST::Variable(var) => {
-                self.var_resolver
-                    .resolve_variable(var)
-                    .expect("use of undeclared variable")
-                    .var_type
+                let (_, decl) = self.symbols.resolve_variable(var)?;
+                Some(self.resolve(&decl.var_type))
             }
-            //in case of call resolve call and return type
-            ExprAST::Call(call) => {
-                self.funct_resolver
-                    .resolve_call(call.clone())
-                    .expect("call of undefined function")
-                    .rt_type
-            }
-            ExprAST::Number(num_ast) => {
-                if -128 < num_ast.num && num_ast.num < 128 {
-                    TypeAST::I8
-                } else {
-                    panic!("Immediate exceeds bounds of i8")
+            ExprAST::FieldAccess(access) => {
+                let tp = match self.place_type(&access.expr)? {
+                    TypeAST::Pointer(pointee) => *pointee,
+                    tp => tp,
+                };
+                let def = self.types.struct_of(&tp)?;
+                let field = def.fields.iter().find(|field| field.name == access.field)?;
+                Some(field.field_type.clone())
+            }
+            ExprAST::Index(index) => match self.place_type(&index.expr)? {
+                TypeAST::Array(element, _) => Some(*element),
+                _ => None,
+            },
+            ExprAST::Unary(unary) if unary.op == Token::Mult => {
+                match self.place_type(&unary.expr)? {
+                    TypeAST::Pointer(pointee) => Some(*pointee),
+                    _ => None,
                 }
             }
-            ExprAST::BoolLiteral(_) => TypeAST::Bool,
-            ExprAST::StringLiteral(_) => TypeAST::Str,
-            ExprAST::BinaryExpression(bin_expr) => {
-                self.check_and_resolve_binary_expression(&bin_expr)
+            _ => None,
+        }
+    }
+
+    ///the variable an assignment to target changes, None if it writes through a pointer
+    fn assigned_variable<'a>(&self, target: &'a ExprAST) -> Option<&'a VariableAST> {
+        match target {
+            ExprAST::Variable(var) => Some(var),
+            ExprAST::Index(index) => self.assigned_variable(&index.expr),
+            ExprAST::FieldAccess(access) => match self.place_type(&access.expr)? {
+                TypeAST::Pointer(_) => None,
+                _ => self.assigned_variable(&access.expr),
+            },
+            _ => None,
+        }
+    }
+
+    ///reports assignments to immutable variables, a variable declared without a value and without
+    ///`mut` can be assigned once
+    fn check_mutability(&mut self, ass: &AssignStmtAST) {
+        let Some((id, _)) = self
+            .assigned_variable(&ass.target)
+            .and_then(|var| self.symbols.resolve_variable(var))
+        else {
+            return;
+        };
+        let Some(binding) = self.bindings.get_mut(&id) else {
+            return;
+        };
+        let name = &binding.decl.name;
+        //assigning to a field or element changes the variable without replacing it
+        let part = match &ass.target {
+            ExprAST::FieldAccess(_) => Some("a field"),
+            ExprAST::Index(_) => Some("an element"),
+            _ => None,
+        };
+        let twice = Diagnostic::error(
+            diagnostics::ASSIGN_TO_IMMUTABLE,
+            format!("cannot assign twice to immutable variable `{}`", name),
+            ass.span,
+        )
+        .with_label("cannot assign twice to immutable variable")
+        .with_note(format!(
+            "consider making the variable mutable: `let mut {}`",
+            name
+        ));
+        let err = match (binding.kind, binding.assigned, part) {
+            (BindingKind::Argument, _, _) => Diagnostic::error(
+                diagnostics::ASSIGN_TO_IMMUTABLE,
+                format!("cannot assign to immutable argument `{}`", name),
+                ass.span,
+            )
+            .with_label("cannot assign to immutable argument")
+            .with_secondary(binding.decl.span, "argument declared without `mut`")
+            .with_note(format!(
+                "consider making the argument mutable: `mut {}`",
+                name
+            )),
+            (BindingKind::Constant, _, _) => Diagnostic::error(
+                diagnostics::ASSIGN_TO_IMMUTABLE,
+                format!("cannot assign to constant `{}`", name),
+                ass.span,
+            )
+            .with_label("cannot assign to a constant")
+            .with_secondary(binding.decl.span, "constant defined here")
+            .with_note(format!(
+                "consider a mutable global variable instead: `static mut {}`",
+                name
+            )),
+            (BindingKind::Static, _, _) => Diagnostic::error(
+                diagnostics::ASSIGN_TO_IMMUTABLE,
+                format!("cannot assign to immutable global variable `{}`", name),
+                ass.span,
+            )
+            .with_label("cannot assign to an immutable global variable")
+            .with_secondary(binding.decl.span, "declared without `mut`")
+            .with_note(format!("consider making it mutable: `static mut {}`", name)),
+            (BindingKind::Initialized, _, Some(part))
+            | (BindingKind::Deferred { .. }, Some(_), Some(part)) => Diagnostic::error(
+                diagnostics::ASSIGN_TO_IMMUTABLE,
+                format!("cannot assign to {} of immutable variable `{}`", part, name),
+                ass.span,
+            )
+            .with_label(format!(
+                "cannot assign to {} of an immutable variable",
+                part
+            ))
+            .with_secondary(binding.decl.span, "declared without `mut`")
+            .with_note(format!(
+                "consider making the variable mutable: `let mut {}`",
+                name
+            )),
+            (BindingKind::Initialized, _, _) => {
+                twice.with_secondary(binding.decl.span, format!("first assignment to `{}`", name))
+            }
+            (BindingKind::Deferred { .. }, Some(first), _) => twice
+                .with_secondary(binding.decl.span, "declared without `mut`")
+                .with_secondary(first, format!("first assignment to `{}`", name)),
+            (BindingKind::Deferred { .. }, None, _) => {
+                binding.assigned = Some(ass.span);
+                return;
+            }
+        };
+        //it is not reported as never assigned as well
+        binding.assigned.get_or_insert(ass.span);
+        self.errors.push(err);
+    }
+
+    ///reports the first assignments to variables declared outside of the loop that are repeated by
+    ///it, as they can run again in the next iteration
+    fn check_repeated(&mut self, loop_body: &BodyAST) {
+        for (id, span) in repeated_assignments(loop_body) {
+            let Some(binding) = self.bindings.get(&id) else {
+                continue;
+            };
+            let BindingKind::Deferred { loops: declared } = binding.kind else {
+                continue;
+            };
+            //later assignments are already reported as the second one
+            if declared > self.loops || binding.assigned != Some(span) {
+                continue;
             }
+            let name = &binding.decl.name;
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::ASSIGN_TO_IMMUTABLE,
+                    format!("cannot assign twice to immutable variable `{}`", name),
+                    span,
+                )
+                .with_label("cannot assign twice to immutable variable")
+                .with_secondary(binding.decl.span, "declared outside of the loop")
+                .with_note("the assignment is repeated by the loop"),
+            );
         }
     }
 
-    fn check_return_stmt(&self, return_expr: &ExprAST) {
-        assert_eq!(
-            self.expected_rt_tp,
-            self.check_and_resolve_expression(return_expr),
-            "invalid return type"
+    ///the binding of var if it names a constant
+    fn constant(&self, var: &VariableAST) -> Option<&Binding> {
+        var.id
+            .and_then(|id| self.bindings.get(&id))
+            .filter(|binding| matches!(binding.kind, BindingKind::Constant))
+    }
+
+    ///reports constants whose type can't be inlined, they are limited to values that fit into a
+    ///register
+    fn check_const_type(&mut self, decl: &DeclarationAST) {
+        let tp = &decl.var_type;
+        let scalar = tp.is_integer()
+            || matches!(
+                tp,
+                TypeAST::Bool | TypeAST::Char | TypeAST::Str | TypeAST::Error
+            )
+            || self.types.enum_of(tp).is_some();
+        if scalar {
+            return;
+        }
+        self.errors.push(
+            Diagnostic::error(
+                diagnostics::INVALID_CONST_TYPE,
+                format!("constants cannot have the type `{}`", tp),
+                decl.span,
+            )
+            .with_label("not an integer, `bool`, `char`, `str` or enum")
+            .with_note(format!(
+                "consider a global variable instead: `static {}: {}`",
+                decl.name, tp
+            )),
         );
     }
 
-    pub fn check_types(&mut self) {
-        for stmt in self.body.stmts.to_vec() {
-            match stmt {
-                StmtAST::Function(func) => {
-                    self.funct_resolver.add_signature(func.fn_signt.clone());
+    ///reports constants and global variables whose name is taken by one defined before them
+    fn check_duplicate_globals(&mut self) {
+        let mut defined: HashMap<&str, Span> = HashMap::new();
+        let mut errors = Vec::new();
+        for stmt in &self.body.stmts {
+            let (what, global) = match stmt {
+                StmtAST::Const(constant) => ("constant", constant),
+                StmtAST::DeclAssign(global) => ("global variable", global),
+                _ => continue,
+            };
+            let decl = &global.decl;
+            match defined.get(decl.name.as_str()) {
+                Some(&previous) => {
+                    errors.push(duplicate_definition(what, &decl.name, decl.span, previous))
+                }
+                None => {
+                    defined.insert(&decl.name, decl.span);
                 }
-                _ => {}
             }
         }
-        for stmt in self.body.stmts.to_vec() {
-            match stmt {
-                StmtAST::Declaration(decl) => self.var_resolver.add_decl(decl.clone()),
-                StmtAST::DeclAssign(mut declassg) => {
-                    if declassg.decl.var_type == TypeAST::Undefined {
-                        declassg.decl.var_type = self.check_and_resolve_expression(&declassg.value);
-                    } else {
-                        assert_eq!(
-                            declassg.decl.var_type,
-                            self.check_and_resolve_expression(&declassg.value),
-                            "invalid type in declare assignment"
-                        );
-                    }
+        self.errors.extend(errors);
+    }
 
-                    self.var_resolver.add_decl(declassg.decl.clone());
-                }
-                StmtAST::Assign(ass) => {
-                    assert_eq!(
-                        self.var_resolver
-                            .resolve_variable(&ass.var)
-                            .expect("variable not found")
-                            .var_type,
-                        self.check_and_resolve_expression(&ass.value)
+    ///reports immutable variables that are declared without a value and never assigned
+    fn check_never_assigned(&mut self) {
+        let mut unassigned: Vec<&Binding> = self
+            .bindings
+            .values()
+            .filter(|binding| {
+                matches!(binding.kind, BindingKind::Deferred { .. }) && binding.assigned.is_none()
+            })
+            .collect();
+        unassigned.sort_by_key(|binding| binding.decl.span.start);
+        let errors: Vec<Diagnostic> = unassigned
+            .into_iter()
+            .map(|binding| {
+                Diagnostic::error(
+                    diagnostics::NEVER_ASSIGNED,
+                    format!(
+                        "variable `{}` is declared but never assigned",
+                        binding.decl.name
+                    ),
+                    binding.decl.span,
+                )
+                .with_label("declared without a value")
+                .with_note("give it a value in the declaration or assign it later")
+            })
+            .collect();
+        self.errors.extend(errors);
+    }
+
+    ///reports an error if found can not be used where expected is required
+    fn expect_type(&mut self, expected: &TypeAST, found: &TypeAST, span: Span) -> bool {
+        if self.unify(expected, found) {
+            return true;
+        }
+        self.errors.push(self.mismatch(expected, found, span));
+        false
+    }
+
+    ///reports types that are not defined, returns the type or TypeAST::Error if it is unknown
+    fn check_type(&mut self, tp: &TypeAST, span: Span) -> TypeAST {
+        match tp {
+            TypeAST::Array(element, len) => match self.check_type(element, span) {
+                TypeAST::Error => TypeAST::Error,
+                element => TypeAST::Array(Box::new(element), *len),
+            },
+            TypeAST::Pointer(pointee) => match self.check_type(pointee, span) {
+                TypeAST::Error => TypeAST::Error,
+                pointee => TypeAST::Pointer(Box::new(pointee)),
+            },
+            TypeAST::Custom(name) if self.types.definition_span(name).is_none() => {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::UNKNOWN_TYPE,
+                        format!("cannot find type `{}` in this scope", name),
+                        span,
                     )
-                }
+                    .with_label("not found in this scope"),
+                );
+                TypeAST::Error
+            }
+            tp => tp.clone(),
+        }
+    }
 
-                StmtAST::Call(cll) => {
-                    self.check_and_resolve_call(&cll);
-                }
+    ///true if a value of type tp contains a value of the struct name
+    fn contains_struct(&self, tp: &TypeAST, name: &str, visited: &mut Vec<String>) -> bool {
+        if let TypeAST::Array(element, _) = tp {
+            return self.contains_struct(element, name, visited);
+        }
+        let Some(def) = self.types.struct_of(tp) else {
+            return false;
+        };
+        if def.name == name {
+            return true;
+        }
+        if visited.contains(&def.name) {
+            return false;
+        }
+        visited.push(def.name.clone());
+        def.fields
+            .iter()
+            .any(|field| self.contains_struct(&field.field_type, name, visited))
+    }
+
+    fn check_struct_def(&mut self, def: &StructAST) {
+        for (i, field) in def.fields.iter().enumerate() {
+            if let Some(previous) = def.fields[..i].iter().find(|f| f.name == field.name) {
+                self.errors.push(duplicate_definition(
+                    "field",
+                    &field.name,
+                    field.span,
+                    previous.span,
+                ));
+            }
+            self.check_type(&field.field_type, field.span);
+        }
+        let recursive = def
+            .fields
+            .iter()
+            .find(|field| self.contains_struct(&field.field_type, &def.name, &mut Vec::new()));
+        if let Some(field) = recursive {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::RECURSIVE_TYPE,
+                    format!("recursive type `{}` has infinite size", def.name),
+                    def.span,
+                )
+                .with_secondary(field.span, "recursive without indirection"),
+            );
+        }
+    }
 
-                StmtAST::Function(func) => {
-                    Self::new(
-                        func.body.clone(),
-                        Some(self.var_resolver.new_scoped()),
-                        Some(self.funct_resolver.new_scoped()),
-                        func.fn_signt.rt_type.clone(),
+    fn check_enum_def(&mut self, def: &EnumAST) {
+        let discriminants = def.discriminants();
+        for (i, (variant, value)) in def.variants.iter().zip(&discriminants).enumerate() {
+            if let Some(previous) = def.variants[..i].iter().find(|v| v.name == variant.name) {
+                self.errors.push(duplicate_definition(
+                    "variant",
+                    &variant.name,
+                    variant.span,
+                    previous.span,
+                ));
+                continue;
+            }
+            if let Some(previous) = discriminants[..i].iter().position(|v| v == value) {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::DUPLICATE_DEFINITION,
+                        format!("discriminant value `{}` assigned more than once", value),
+                        variant.span,
                     )
-                    .check_types();
+                    .with_label(format!("`{}` assigned here", value))
+                    .with_secondary(
+                        def.variants[previous].span,
+                        format!("first use of `{}`", value),
+                    ),
+                );
+            }
+        }
+        let (min, max) = (i32::MIN as i64, u32::MAX as i64);
+        let out_of_range = discriminants
+            .iter()
+            .any(|value| !(min..=max).contains(value))
+            || (discriminants.iter().any(|value| *value < 0)
+                && discriminants.iter().any(|value| *value > i32::MAX as i64));
+        if out_of_range {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::LITERAL_OUT_OF_RANGE,
+                    format!(
+                        "discriminants of `{}` don't fit into an integer type",
+                        def.name
+                    ),
+                    def.span,
+                )
+                .with_note("the discriminants have to fit into `i32` or `u32`"),
+            );
+        }
+    }
+
+    ///the enum and its type, reports unknown enums and variants
+    fn check_enum_variant(&mut self, variant: &EnumVariantAST) -> TypeAST {
+        let tp = TypeAST::Custom(variant.enum_name.clone());
+        if self.check_type(&tp, variant.span) == TypeAST::Error {
+            return TypeAST::Error;
+        }
+        let Some(def) = self.types.enum_of(&tp) else {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::NO_VARIANT,
+                    format!("`{}` is not an enum", variant.enum_name),
+                    variant.span,
+                )
+                .with_label("expected an enum"),
+            );
+            return TypeAST::Error;
+        };
+        if def.discriminant(&variant.variant).is_none() {
+            let err = Diagnostic::error(
+                diagnostics::NO_VARIANT,
+                format!(
+                    "no variant named `{}` in enum `{}`",
+                    variant.variant, def.name
+                ),
+                variant.span,
+            )
+            .with_label("variant not found")
+            .with_secondary(def.span, format!("`{}` defined here", def.name));
+            self.errors.push(err);
+            return TypeAST::Error;
+        }
+        tp
+    }
+
+    ///the value a pattern compares against, None for `_` and patterns with errors
+    fn check_pattern(&mut self, pattern: &PatternAST, tp: &TypeAST) -> Option<i64> {
+        let (found, value) = match pattern {
+            PatternAST::Wildcard(_) => return None,
+            PatternAST::Variant(variant) => {
+                let found = self.check_enum_variant(variant);
+                let value = self
+                    .types
+                    .enum_of(&found)
+                    .and_then(|def| def.discriminant(&variant.variant));
+                (found, value)
+            }
+            //number patterns take the type of the matched value
+            PatternAST::Number(num) => match tp.int_range() {
+                Some(range) if !range.contains(&num.num) => {
+                    self.errors.push(literal_out_of_range(num, tp, range));
+                    return None;
                 }
+                Some(_) => (tp.clone(), Some(num.num)),
+                None => (TypeAST::UntypedInt, Some(num.num)),
+            },
+            PatternAST::Char(ch) => (TypeAST::Char, Some(ch.value as i64)),
+            PatternAST::Bool(bl) => (TypeAST::Bool, Some(bl.value as i64)),
+        };
+        if !tp.accepts(&found) {
+            self.errors
+                .push(mismatched_types(tp, &found, pattern.span()));
+            return None;
+        }
+        value
+    }
 
-                StmtAST::If(if_st) => {
-                    assert_eq!(
-                        self.check_and_resolve_expression(&if_st.condition),
-                        TypeAST::Bool
-                    );
-                    Self::new(
-                        self.body.clone(),
-                        Some(self.var_resolver.new_scoped()),
-                        Some(self.funct_resolver.new_scoped()),
-                        TypeAST::Void,
+    ///checks the patterns and arms, every value of the matched type has to be covered by exactly
+    ///one reachable pattern
+    fn check_match_stmt(&mut self, mut match_st: MatchStmtAST) -> MatchStmtAST {
+        let tp = self.check_expr_as(&mut match_st.scrutinee, &TypeAST::Undefined);
+        let tp = self.known(&tp, match_st.scrutinee.span());
+        let matchable = tp.is_integer()
+            || matches!(tp, TypeAST::Char | TypeAST::Bool | TypeAST::Error)
+            || self.types.enum_of(&tp).is_some();
+        if !matchable {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::UNMATCHABLE_TYPE,
+                    format!("`{}` cannot be matched on", tp),
+                    match_st.scrutinee.span(),
+                )
+                .with_note("only enums, integers, `char` and `bool` can be matched on"),
+            );
+        }
+        //every value of these types is listed, the others need a `_`
+        let all_values: Option<Vec<(i64, String)>> = match &tp {
+            TypeAST::Bool => Some(vec![(0, "false".to_string()), (1, "true".to_string())]),
+            tp => self.types.enum_of(tp).map(|def| {
+                def.variants
+                    .iter()
+                    .zip(def.discriminants())
+                    .map(|(variant, value)| (value, format!("{}::{}", def.name, variant.name)))
+                    .collect()
+            }),
+        };
+        let mut covered: Vec<(i64, Span)> = Vec::new();
+        let mut wildcard: Option<Span> = None;
+        for arm in &match_st.arms {
+            for pattern in &arm.patterns {
+                let value = if matchable && tp != TypeAST::Error {
+                    self.check_pattern(pattern, &tp)
+                } else {
+                    None
+                };
+                let all_covered = all_values.as_ref().is_some_and(|values| {
+                    values
+                        .iter()
+                        .all(|(value, _)| covered.iter().any(|(c, _)| c == value))
+                });
+                //the pattern before that matches the same values
+                let previous = wildcard.or_else(|| {
+                    let value = value?;
+                    covered.iter().find(|(c, _)| *c == value).map(|c| c.1)
+                });
+                let is_wildcard = matches!(pattern, PatternAST::Wildcard(_));
+                if previous.is_some() || (is_wildcard && all_covered) {
+                    let mut err = Diagnostic::error(
+                        diagnostics::UNREACHABLE_PATTERN,
+                        "unreachable pattern",
+                        pattern.span(),
                     )
-                    .check_types();
+                    .with_label("no value reaches this pattern");
+                    err = match previous {
+                        Some(previous) => {
+                            err.with_secondary(previous, "matches the same values before")
+                        }
+                        None => err.with_note("every value is already covered"),
+                    };
+                    self.errors.push(err);
+                }
+                match (value, pattern) {
+                    (Some(value), _) => covered.push((value, pattern.span())),
+                    (None, PatternAST::Wildcard(span)) if wildcard.is_none() => {
+                        wildcard = Some(*span)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if wildcard.is_none() && matchable && tp != TypeAST::Error {
+            let missing: Vec<String> = match &all_values {
+                Some(values) => values
+                    .iter()
+                    .filter(|(value, _)| !covered.iter().any(|(c, _)| c == value))
+                    .map(|(_, name)| format!("`{}`", name))
+                    .collect(),
+                None => vec!["`_`".to_string()],
+            };
+            if !missing.is_empty() {
+                let mut err = Diagnostic::error(
+                    diagnostics::NON_EXHAUSTIVE_MATCH,
+                    format!(
+                        "non-exhaustive patterns: {} not covered",
+                        missing.join(", ")
+                    ),
+                    match_st.scrutinee.span(),
+                )
+                .with_label("patterns not covered");
+                if all_values.is_none() {
+                    err = err.with_note(format!(
+                        "`{}` has too many values to list them, add a `_` arm",
+                        tp
+                    ));
                 }
+                self.errors.push(err);
+            }
+        }
+        let before = self.bindings.clone();
+        let mut branches = Vec::new();
+        match_st.arms = std::mem::take(&mut match_st.arms)
+            .into_iter()
+            .map(|mut arm| {
+                arm.body = self.check_branch(arm.body);
+                branches.push(std::mem::replace(&mut self.bindings, before.clone()));
+                arm
+            })
+            .collect();
+        for branch in branches {
+            self.merge_bindings(branch);
+        }
+        match_st
+    }
+
+    fn check_struct_literal(&mut self, lit: &mut StructLiteralAST) -> TypeAST {
+        //the values are checked even if the struct is unknown
+        let value_tps: Vec<TypeAST> = lit
+            .fields
+            .iter_mut()
+            .map(|init| self.check_and_resolve_expression(&mut init.value))
+            .collect();
+        let tp = TypeAST::Custom(lit.name.clone());
+        if self.check_type(&tp, lit.span) == TypeAST::Error {
+            return TypeAST::Error;
+        }
+        let def = self.types.struct_of(&tp).cloned().expect("checked above");
+        for (i, value_tp) in value_tps.into_iter().enumerate() {
+            let init = &lit.fields[i];
+            if let Some(previous) = lit.fields[..i].iter().find(|f| f.name == init.name) {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::DUPLICATE_DEFINITION,
+                        format!("field `{}` specified more than once", init.name),
+                        init.span,
+                    )
+                    .with_label("used more than once")
+                    .with_secondary(previous.span, "first use of the field"),
+                );
+                continue;
+            }
+            let Some(field) = def.fields.iter().find(|field| field.name == init.name) else {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::NO_FIELD,
+                        format!("struct `{}` has no field named `{}`", def.name, init.name),
+                        init.span,
+                    )
+                    .with_secondary(def.span, format!("`{}` defined here", def.name)),
+                );
+                continue;
+            };
+            let value_tp =
+                self.resolve_untyped(&mut lit.fields[i].value, value_tp, &field.field_type);
+            if !self.unify(&field.field_type, &value_tp) {
+                self.errors.push(
+                    self.mismatch(&field.field_type, &value_tp, lit.fields[i].value.span())
+                        .with_secondary(field.span, "field declared here"),
+                );
+            }
+        }
+        let missing: Vec<String> = def
+            .fields
+            .iter()
+            .filter(|field| !lit.fields.iter().any(|init| init.name == field.name))
+            .map(|field| format!("`{}`", field.name))
+            .collect();
+        if !missing.is_empty() {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::MISSING_FIELDS,
+                    format!(
+                        "missing fields {} in initializer of `{}`",
+                        missing.join(", "),
+                        def.name
+                    ),
+                    lit.span,
+                )
+                .with_label("missing fields"),
+            );
+        }
+        tp
+    }
+
+    ///fields of a struct behind a pointer are accessed through the pointer, like `p.x` for `(*p).x`
+    fn check_field_access(&mut self, access: &mut FieldAccessAST) -> TypeAST {
+        let tp = self.check_and_resolve_expression(&mut access.expr);
+        let tp = match self.known(&tp, access.expr.span()) {
+            TypeAST::Error => return TypeAST::Error,
+            TypeAST::Pointer(pointee) if self.types.struct_of(&pointee).is_some() => *pointee,
+            tp => tp,
+        };
+        let field = self
+            .types
+            .struct_of(&tp)
+            .and_then(|def| def.fields.iter().find(|field| field.name == access.field));
+        match field {
+            Some(field) => field.field_type.clone(),
+            None => {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::NO_FIELD,
+                        format!("no field `{}` on type `{}`", access.field, tp),
+                        access.field_span,
+                    )
+                    .with_label("unknown field"),
+                );
+                TypeAST::Error
+            }
+        }
+    }
+
+    ///the elements have the type of the first one that is not untyped, an empty literal fits every
+    ///array of length 0
+    fn check_array_literal(&mut self, lit: &mut ArrayLiteralAST) -> TypeAST {
+        let tps: Vec<TypeAST> = lit
+            .elements
+            .iter_mut()
+            .map(|element| self.check_and_resolve_expression(element))
+            .collect();
+        let first = tps.iter().position(|tp| !has_untyped(tp)).unwrap_or(0);
+        let element_tp = tps.get(first).cloned().unwrap_or(TypeAST::Error);
+        for (i, tp) in tps.into_iter().enumerate() {
+            //untyped elements stay untyped if all of them are
+            let tp = if has_untyped(&element_tp) {
+                tp
+            } else {
+                self.resolve_untyped(&mut lit.elements[i], tp, &element_tp)
+            };
+            if i != first && !self.unify(&element_tp, &tp) {
+                let label = if first == 0 {
+                    format!("the first element is of type `{}`", element_tp)
+                } else {
+                    format!("this element is of type `{}`", element_tp)
+                };
+                self.errors.push(
+                    self.mismatch(&element_tp, &tp, lit.elements[i].span())
+                        .with_secondary(lit.elements[first].span(), label),
+                );
+            }
+        }
+        TypeAST::Array(Box::new(element_tp), lit.elements.len() as u64)
+    }
+
+    fn check_index(&mut self, index: &mut IndexAST) -> TypeAST {
+        let tp = self.check_expr_as(&mut index.expr, &TypeAST::Undefined);
+        let tp = self.known(&tp, index.expr.span());
+        let index_tp = self.check_expr_as(&mut index.index, &TypeAST::Undefined);
+        let index_tp = self.known(&index_tp, index.index.span());
+        if !index_tp.is_integer() && index_tp != TypeAST::Error {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::MISMATCHED_TYPES,
+                    "mismatched types",
+                    index.index.span(),
+                )
+                .with_label(format!("expected an integer, found `{}`", index_tp)),
+            );
+        }
+        let (element, len) = match tp {
+            TypeAST::Array(element, len) => (*element, len),
+            TypeAST::Error => return TypeAST::Error,
+            tp => {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::CANNOT_INDEX,
+                        format!("cannot index into a value of type `{}`", tp),
+                        index.expr.span(),
+                    )
+                    .with_label("not an array"),
+                );
+                return TypeAST::Error;
+            }
+        };
+        if let Some(value) = const_int(&index.index) {
+            if !(0..len as i64).contains(&value) {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::CONST_INDEX_OUT_OF_BOUNDS,
+                        format!(
+                            "index out of bounds: the length is {} but the index is {}",
+                            len, value
+                        ),
+                        index.span,
+                    )
+                    .with_label("index out of bounds"),
+                );
+            }
+        }
+        element
+    }
 
-                StmtAST::Return(rtstmt) => self.check_return_stmt(&rtstmt.expr),
+    ///`&` needs a place and results in a pointer to it, `*` needs a pointer and results in its
+    ///pointee, `-` needs a signed integer and results in one of the same type
+    fn check_unary(&mut self, unary: &mut UnaryExpressionAST) -> TypeAST {
+        let tp = self.check_and_resolve_expression(&mut unary.expr);
+        if unary.op == Token::Minus {
+            //untyped operands are checked once the context types them
+            if has_untyped(&tp) || matches!(self.resolve(&tp), TypeAST::Infer(_) | TypeAST::Error) {
+                self.expect_integer(&tp);
+                return tp;
+            }
+            let tp = self.resolve(&tp);
+            if !tp.is_signed() {
+                self.errors.push(cannot_negate(&tp, unary.span));
+                return TypeAST::Error;
+            }
+            return tp;
+        }
+        if unary.op == Token::AndInt {
+            if let ExprAST::Variable(var) = &unary.expr {
+                if let Some(binding) = self.constant(var) {
+                    let err = Diagnostic::error(
+                        diagnostics::NOT_ADDRESSABLE,
+                        format!("cannot take the address of constant `{}`", var.name),
+                        var.span,
+                    )
+                    .with_label("constants are inlined and have no address")
+                    .with_secondary(binding.decl.span, "constant defined here")
+                    .with_note(format!(
+                        "consider a global variable instead: `static {}`",
+                        var.name
+                    ));
+                    self.errors.push(err);
+                    return TypeAST::Error;
+                }
+            }
+            if !unary.expr.is_place() {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::NOT_ADDRESSABLE,
+                        "cannot take the address of a temporary value",
+                        unary.expr.span(),
+                    )
+                    .with_label("not a variable, field, element or dereferenced pointer"),
+                );
+                return TypeAST::Error;
+            }
+            return match tp {
+                TypeAST::Error => TypeAST::Error,
+                tp => TypeAST::Pointer(Box::new(tp)),
+            };
+        }
+        match self.known(&tp, unary.expr.span()) {
+            TypeAST::Pointer(pointee) => *pointee,
+            TypeAST::Error => TypeAST::Error,
+            tp => {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::CANNOT_DEREF,
+                        format!("type `{}` cannot be dereferenced", tp),
+                        unary.expr.span(),
+                    )
+                    .with_label("not a pointer"),
+                );
+                TypeAST::Error
             }
         }
     }
+
+    ///integers, bools, chars and enums can be cast to integers, bools and chars
+    fn check_cast(&mut self, cast: &mut CastAST) -> TypeAST {
+        let from = self.check_expr_as(&mut cast.expr, &TypeAST::Undefined);
+        let from = self.known(&from, cast.expr.span());
+        let target = self.check_type(&cast.target, cast.span);
+        let scalar = |tp: &TypeAST| tp.is_integer() || matches!(tp, TypeAST::Bool | TypeAST::Char);
+        if from == TypeAST::Error || target == TypeAST::Error {
+            return target;
+        }
+        if !scalar(&target) || !(scalar(&from) || self.types.enum_of(&from).is_some()) {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::INVALID_CAST,
+                    format!("casting `{}` as `{}` is invalid", from, target),
+                    cast.span,
+                )
+                .with_label("invalid cast")
+                .with_secondary(cast.expr.span(), format!("this is of type `{}`", from))
+                .with_note("only integers, `bool`, `char` and enums can be cast, to integers, `bool` and `char`"),
+            );
+        }
+        target
+    }
+
+    fn resolve_variable(&mut self, var: &mut VariableAST) -> TypeAST {
+        match self.symbols.resolve_variable(var) {
+            Some((id, decl)) => {
+                var.id = Some(id);
+                self.resolve(&decl.var_type)
+            }
+            None => {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::UNDECLARED_VARIABLE,
+                        format!("cannot find variable `{}` in this scope", var.name),
+                        var.span,
+                    )
+                    .with_label("not found in this scope"),
+                );
+                TypeAST::Error
+            }
+        }
+    }
+
+    fn check_and_resolve_call(&mut self, call: &mut CallAST) -> TypeAST {
+        let signt = self
+            .symbols
+            .resolve_function(&call.callee)
+            .map(|(id, signt)| {
+                call.id = Some(id);
+                signt.clone()
+            });
+        //the arguments are checked even if the call itself is invalid, untyped literals take the
+        //type of their parameter
+        let arg_tps: Vec<TypeAST> = call
+            .args
+            .iter_mut()
+            .enumerate()
+            .map(|(i, arg)| {
+                let expected = signt
+                    .as_ref()
+                    .and_then(|signt| signt.args.get(i))
+                    .map_or(TypeAST::Undefined, |arg| arg.var_type.clone());
+                self.check_expr_as(arg, &expected)
+            })
+            .collect();
+
+        let Some(signt) = signt else {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::UNDEFINED_FUNCTION,
+                    format!("cannot find function `{}` in this scope", call.callee),
+                    call.span,
+                )
+                .with_label("not found in this scope"),
+            );
+            return TypeAST::Error;
+        };
+        if signt.args.len() != call.args.len() {
+            self.errors.push(
+                Diagnostic::error(
+                    diagnostics::WRONG_ARGUMENT_COUNT,
+                    format!(
+                        "this function takes {} arguments but {} were supplied",
+                        signt.args.len(),
+                        call.args.len()
+                    ),
+                    call.span,
+                )
+                .with_secondary(signt.span, "function defined here"),
+            );
+        }
+        //compare types between given and declared args
+        for ((signt_arg, call_arg), arg_tp) in signt.args.iter().zip(&call.args).zip(arg_tps) {
+            if !self.unify(&signt_arg.var_type, &arg_tp) {
+                self.errors.push(
+                    self.mismatch(&signt_arg.var_type, &arg_tp, call_arg.span())
+                        .with_secondary(signt_arg.span, "parameter declared here"),
+                );
+            }
+        }
+        if call.rt_value_ignored {
+            TypeAST::Void
+        } else {
+            signt.rt_type
+        }
+    }
+
+    ///checks that tp can be used with an integer operator, returns the type of the result
+    fn check_iteger_bin_expr(&mut self, expr: &BinaryExpressionAST, tp: TypeAST) -> TypeAST {
+        if self.expect_integer(&tp) || matches!(tp, TypeAST::Error | TypeAST::UntypedInt) {
+            tp
+        } else {
+            self.errors
+                .push(unsupported_operator(&expr.op, &tp, expr.span));
+            TypeAST::Error
+        }
+    }
+
+    fn check_and_resolve_binary_expression(&mut self, expr: &mut BinaryExpressionAST) -> TypeAST {
+        let mut lhs_tp = self.check_and_resolve_expression(&mut expr.lhs);
+        let mut rhs_tp = self.check_and_resolve_expression(&mut expr.rhs);
+        //an untyped side takes the integer type of the other side, two untyped sides of
+        //arithmetic stay untyped until the whole expression gets a type
+        let context = |tp: &TypeAST| match tp {
+            tp if tp.is_integer() || matches!(tp, TypeAST::Infer(_)) => tp.clone(),
+            _ => TypeAST::Undefined,
+        };
+        let arithmetic = matches!(
+            expr.op,
+            Token::XorInt
+                | Token::OrInt
+                | Token::AndInt
+                | Token::Plus
+                | Token::Minus
+                | Token::Mult
+                | Token::Divide
+                | Token::LeftShift
+                | Token::RightShift
+        );
+        match (has_untyped(&lhs_tp), has_untyped(&rhs_tp)) {
+            (true, false) => {
+                lhs_tp = self.resolve_untyped(&mut expr.lhs, lhs_tp, &context(&rhs_tp))
+            }
+            (false, true) => {
+                rhs_tp = self.resolve_untyped(&mut expr.rhs, rhs_tp, &context(&lhs_tp))
+            }
+            (true, true) if !arithmetic => {
+                lhs_tp = self.resolve_untyped(&mut expr.lhs, lhs_tp, &TypeAST::Undefined);
+                rhs_tp = self.resolve_untyped(&mut expr.rhs, rhs_tp, &TypeAST::Undefined);
+            }
+            _ => {}
+        }
+        let (lhs_tp, rhs_tp) = (self.resolve(&lhs_tp), self.resolve(&rhs_tp));
+        //pointers move by whole pointees when an integer is added or subtracted
+        match (&expr.op, &lhs_tp, &rhs_tp) {
+            (Token::Plus | Token::Minus, TypeAST::Pointer(_), int) if self.expect_integer(int) => {
+                return lhs_tp
+            }
+            (Token::Plus, int, TypeAST::Pointer(_)) if self.expect_integer(int) => return rhs_tp,
+            _ => {}
+        }
+        if !self.unify(&lhs_tp, &rhs_tp) {
+            self.errors.push(
+                self.mismatch(&lhs_tp, &rhs_tp, expr.rhs.span())
+                    .with_secondary(
+                        expr.lhs.span(),
+                        format!("this is of type `{}`", self.shown(&lhs_tp)),
+                    ),
+            );
+            //the operator is not checked, the result type is unknown
+            return TypeAST::Error;
+        }
+        //if one side is poisoned the result is poisoned as well
+        let tp = if rhs_tp == TypeAST::Error {
+            rhs_tp
+        } else {
+            self.resolve(&lhs_tp)
+        };
+        match &expr.op {
+            Token::XorInt
+            | Token::OrInt
+            | Token::AndInt
+            | Token::Minus
+            | Token::Mult
+            | Token::Divide
+            | Token::LeftShift
+            | Token::RightShift => self.check_iteger_bin_expr(expr, tp),
+            Token::XorBool | Token::OrBool | Token::AndBool | Token::Not => {
+                //bool operators can only be applied to booleans
+                if !self.unify(&TypeAST::Bool, &tp) {
+                    self.errors
+                        .push(unsupported_operator(&expr.op, &tp, expr.span));
+                }
+                TypeAST::Bool
+            }
+            Token::Plus => {
+                if tp == TypeAST::Str {
+                    TypeAST::Str
+                } else {
+                    self.check_iteger_bin_expr(expr, tp)
+                }
+            }
+            Token::Equal | Token::Unequal => {
+                if self.types.struct_of(&tp).is_some() || matches!(tp, TypeAST::Array(..)) {
+                    self.errors
+                        .push(unsupported_operator(&expr.op, &tp, expr.span));
+                }
+                TypeAST::Bool
+            }
+            Token::LessThan | Token::GreaterThan => {
+                if !matches!(tp, TypeAST::Pointer(_)) {
+                    self.check_iteger_bin_expr(expr, tp);
+                }
+                TypeAST::Bool
+            }
+            other => {
+                self.errors
+                    .push(unsupported_operator(other, &tp, expr.span));
+                TypeAST::Error
+            }
+        }
+    }
+
+    ///the type untyped literals in a value of type tp take where expected is required, they take
+    ///the expected integer type or the default one if no integer is expected
+    ///
+    ///without any expectation they get a variable that is bound by later uses
+    fn infer_untyped(&mut self, tp: &TypeAST, expected: &TypeAST) -> TypeAST {
+        match (tp, self.resolve(expected)) {
+            (TypeAST::UntypedInt, expected) if expected.is_integer() => expected,
+            (TypeAST::UntypedInt, TypeAST::Infer(id)) => {
+                self.vars[id].integer = true;
+                TypeAST::Infer(id)
+            }
+            (TypeAST::UntypedInt, TypeAST::Undefined) => self.new_var("", Span::default(), true),
+            (TypeAST::UntypedInt, _) => self.default_int.clone(),
+            (TypeAST::Array(element, len), TypeAST::Array(expected, _)) => {
+                TypeAST::Array(Box::new(self.infer_untyped(element, &expected)), *len)
+            }
+            (TypeAST::Array(element, len), _) => TypeAST::Array(
+                Box::new(self.infer_untyped(element, &TypeAST::Undefined)),
+                *len,
+            ),
+            (tp, _) => tp.clone(),
+        }
+    }
+
+    ///gives the untyped literals in expr their part of tp and reports the ones that do not fit
+    fn coerce(&mut self, expr: &mut ExprAST, tp: &TypeAST) {
+        if let Some(annotated) = annotation(expr).filter(|annotated| has_untyped(annotated)) {
+            *annotated = tp.clone();
+        }
+        match (expr, tp) {
+            (ExprAST::Number(num), tp) if num.tp == TypeAST::UntypedInt => {
+                //literals typed by a variable are checked once it is bound
+                if let Some(range) = tp.int_range().filter(|range| !range.contains(&num.num)) {
+                    self.errors.push(literal_out_of_range(num, tp, range));
+                }
+                num.tp = tp.clone();
+            }
+            (ExprAST::BinaryExpression(bin_expr), tp) => {
+                self.coerce(&mut bin_expr.lhs, tp);
+                self.coerce(&mut bin_expr.rhs, tp);
+            }
+            (ExprAST::Unary(unary), tp) if unary.op == Token::Minus => {
+                self.coerce(&mut unary.expr, tp);
+                if tp.is_integer() && !tp.is_signed() {
+                    self.errors.push(cannot_negate(tp, unary.span));
+                }
+            }
+            (ExprAST::ArrayLiteral(lit), TypeAST::Array(element, _)) => {
+                for value in &mut lit.elements {
+                    self.coerce(value, element);
+                }
+            }
+            (ExprAST::ArrayRepeat(lit), TypeAST::Array(element, _)) => {
+                self.coerce(&mut lit.value, element)
+            }
+            _ => {}
+        }
+    }
+
+    ///types the untyped literals of expr, which is of type tp, for a context that requires
+    ///expected, TypeAST::Undefined if there is no requirement, returns the type of expr afterwards
+    ///
+    ///untyped integers that can not be expected stay untyped so the mismatch names them
+    fn resolve_untyped(&mut self, expr: &mut ExprAST, tp: TypeAST, expected: &TypeAST) -> TypeAST {
+        if !has_untyped(&tp) {
+            return tp;
+        }
+        let resolved = self.infer_untyped(&tp, expected);
+        self.coerce(expr, &resolved);
+        if *expected == TypeAST::Undefined || self.unify(expected, &resolved) {
+            resolved
+        } else {
+            tp
+        }
+    }
+
+    ///checks expr where a value of type expected is required and types its untyped literals
+    fn check_expr_as(&mut self, expr: &mut ExprAST, expected: &TypeAST) -> TypeAST {
+        let tp = self.check_and_resolve_expression(expr);
+        self.resolve_untyped(expr, tp, expected)
+    }
+
+    ///returns the type of the expression, errors are reported and result in TypeAST::Error
+    fn check_and_resolve_expression(&mut self, expr: &mut ExprAST) -> TypeAST {
+        let tp = match expr {
+            //in case of variable resolve variable and return the type
+            ExprAST::Variable(var) => self.resolve_variable(var),
+            //in case of call resolve call and return type
+            ExprAST::Call(call) => self.check_and_resolve_call(call),
+            //the type is picked by the context the literal is used in
+            ExprAST::Number(num_ast) => num_ast.tp.clone(),
+            ExprAST::BoolLiteral(_) => TypeAST::Bool,
+            ExprAST::StringLiteral(_) => TypeAST::Str,
+            ExprAST::CharLiteral(_) => TypeAST::Char,
+            ExprAST::StructLiteral(lit) => self.check_struct_literal(lit),
+            ExprAST::FieldAccess(access) => self.check_field_access(access),
+            ExprAST::EnumVariant(variant) => self.check_enum_variant(variant),
+            ExprAST::ArrayLiteral(lit) => self.check_array_literal(lit),
+            ExprAST::ArrayRepeat(lit) => {
+                let tp = self.check_and_resolve_expression(&mut lit.value);
+                TypeAST::Array(Box::new(tp), lit.len.num as u64)
+            }
+            ExprAST::Index(index) => self.check_index(index),
+            ExprAST::Unary(unary) => self.check_unary(unary),
+            ExprAST::Cast(cast) => self.check_cast(cast),
+            ExprAST::BinaryExpression(bin_expr) => {
+                self.check_and_resolve_binary_expression(bin_expr)
+            }
+        };
+        //a minus in front of a literal is part of it, so `-128` fits into an i8 like in patterns
+        if let ExprAST::Unary(unary) = expr {
+            if let (Token::Minus, ExprAST::Number(num)) = (&unary.op, &unary.expr) {
+                debug_assert_eq!(num.tp, TypeAST::UntypedInt);
+                *expr = ExprAST::Number(NumberAST {
+                    num: -num.num,
+                    tp: num.tp.clone(),
+                    span: unary.span,
+                });
+                return tp;
+            }
+        }
+        if let Some(annotated) = annotation(expr) {
+            *annotated = tp.clone();
+        }
+        tp
+    }
+
+    fn check_return_stmt(&mut self, return_expr: &mut ExprAST) {
+        let expected = self.expected_rt_tp.clone();
+        let tp = self.check_expr_as(return_expr, &expected);
+        self.expect_type(&expected, &tp, return_expr.span());
+    }
+
+    ///picks the routine that writes the value of the printed expression
+    fn check_print_stmt(&mut self, mut print: PrintStmtAST) -> PrintStmtAST {
+        let tp = self.check_expr_as(&mut print.expr, &TypeAST::Undefined);
+        let tp = self.known(&tp, print.expr.span());
+        print.format = match tp {
+            TypeAST::Str => PrintFormat::Str,
+            TypeAST::Char => PrintFormat::Char,
+            TypeAST::Bool => PrintFormat::Bool,
+            tp if tp.is_signed() => PrintFormat::Signed,
+            tp if tp.is_integer() => PrintFormat::Unsigned,
+            //already reported
+            TypeAST::Error => PrintFormat::Unresolved,
+            tp => {
+                self.errors.push(
+                    Diagnostic::error(
+                        diagnostics::UNPRINTABLE_TYPE,
+                        format!("`{}` cannot be printed", tp),
+                        print.expr.span(),
+                    )
+                    .with_label(format!("the type `{}` has no printed form", tp))
+                    .with_note("only `str`, `char`, `bool` and integers can be printed"),
+                );
+                PrintFormat::Unresolved
+            }
+        };
+        print
+    }
+
+    ///checks a nested body in its own scope and returns the checked body
+    fn check_nested(&mut self, body: BodyAST, expected_rt_tp: TypeAST, loops: usize) -> BodyAST {
+        self.symbols.push_scope();
+        let symbols = std::mem::replace(&mut self.symbols, SymbolTable::new());
+        let mut checker = Self::new(body, Some(symbols), expected_rt_tp);
+        checker.loops = loops;
+        checker.top_level = false;
+        checker.default_int = self.default_int.clone();
+        checker.types = std::mem::replace(&mut self.types, TypeTable::new());
+        checker.vars = std::mem::take(&mut self.vars);
+        checker.bindings = std::mem::take(&mut self.bindings);
+        checker.check_stmts();
+        self.symbols = checker.symbols;
+        self.symbols.pop_scope();
+        self.types = checker.types;
+        self.vars = checker.vars;
+        self.bindings = checker.bindings;
+        self.errors.extend(checker.errors);
+        checker.body
+    }
+
+    ///checks a body that is a branch of the current one
+    fn check_branch(&mut self, body: BodyAST) -> BodyAST {
+        let expected_rt_tp = self.expected_rt_tp.clone();
+        self.check_nested(body, expected_rt_tp, self.loops)
+    }
+
+    ///checks the conditions and bodies of the whole else if chain
+    fn check_if_stmt(&mut self, mut if_st: IfStmtAST) -> IfStmtAST {
+        let tp = self.check_expr_as(&mut if_st.condition, &TypeAST::Bool);
+        self.expect_type(&TypeAST::Bool, &tp, if_st.condition.span());
+        let before = self.bindings.clone();
+        if_st.body = self.check_branch(if_st.body);
+        let branch = std::mem::replace(&mut self.bindings, before);
+        if_st.else_branch = match if_st.else_branch {
+            Some(ElseAST::If(else_if)) => Some(ElseAST::If(Box::new(self.check_if_stmt(*else_if)))),
+            Some(ElseAST::Body(body)) => Some(ElseAST::Body(self.check_branch(body))),
+            None => None,
+        };
+        self.merge_bindings(branch);
+        if_st
+    }
+
+    ///checks all statements of the body and keeps going after errors
+    ///returns the checked body and all errors, in the checked body the types of declarations are
+    ///resolved, every expression carries its type and variables, declarations, calls and functions
+    ///carry the id of their symbol. constants and global variables come first, their values are
+    ///evaluated to literals
+    pub fn check_types(mut self) -> (BodyAST, Vec<Diagnostic>) {
+        self.check_duplicate_globals();
+        //constants and global variables are checked first, so every function sees all of them
+        self.body
+            .stmts
+            .sort_by_key(|stmt| !matches!(stmt, StmtAST::Const(_) | StmtAST::DeclAssign(_)));
+        self.check_stmts();
+        self.check_never_assigned();
+        self.finish_inference();
+        let span = self.body.span;
+        let mut body = std::mem::replace(
+            &mut self.body,
+            BodyAST {
+                stmts: Vec::new(),
+                span,
+            },
+        );
+        self.substitute_body(&mut body);
+        //only well typed values can be evaluated and the flow of badly typed functions is not
+        //worth reporting
+        if self.errors.is_empty() {
+            self.errors = eval_globals(&mut body, &self.types);
+        }
+        if self.errors.is_empty() {
+            self.errors = check_flow(&body);
+        }
+        (body, self.errors)
+    }
+
+    ///checks the statements of the body, types of declarations without annotation can still be
+    ///unbound variables afterwards
+    fn check_stmts(&mut self) {
+        for (name, span) in self.symbols.add_functions(&mut self.body) {
+            let (_, previous) = self
+                .symbols
+                .resolve_function(&name)
+                .expect("the first definition is in the innermost scope");
+            self.errors
+                .push(duplicate_definition("function", &name, span, previous.span));
+        }
+        for (name, span) in self.types.add_from_body(&self.body) {
+            let previous = self
+                .types
+                .definition_span(&name)
+                .expect("the first definition is in the table");
+            self.errors
+                .push(duplicate_definition("type", &name, span, previous));
+        }
+        let mut checked = Vec::new();
+        for stmt in std::mem::take(&mut self.body.stmts) {
+            let stmt = match stmt {
+                StmtAST::Declaration(mut decl) => {
+                    decl.var_type = match self.check_type(&decl.var_type, decl.span) {
+                        TypeAST::Undefined => self.new_var(&decl.name, decl.span, false),
+                        tp => tp,
+                    };
+                    let id = self.symbols.add_variable(&mut decl);
+                    self.add_binding(id, &decl, BindingKind::Deferred { loops: self.loops });
+                    StmtAST::Declaration(decl)
+                }
+                StmtAST::DeclAssign(mut declassg) => {
+                    let tp = self.check_and_resolve_expression(&mut declassg.value);
+                    declassg.decl.var_type =
+                        self.check_type(&declassg.decl.var_type, declassg.decl.span);
+                    let tp = self.resolve_untyped(&mut declassg.value, tp, &declassg.decl.var_type);
+                    if declassg.decl.var_type == TypeAST::Undefined {
+                        declassg.decl.var_type = tp;
+                    } else if !self.unify(&declassg.decl.var_type, &tp) {
+                        self.errors.push(
+                            self.mismatch(&declassg.decl.var_type, &tp, declassg.value.span())
+                                .with_secondary(declassg.decl.span, "expected due to this"),
+                        );
+                    }
+
+                    let id = self.symbols.add_variable(&mut declassg.decl);
+                    let kind = if self.top_level {
+                        BindingKind::Static
+                    } else {
+                        BindingKind::Initialized
+                    };
+                    self.add_binding(id, &declassg.decl, kind);
+                    StmtAST::DeclAssign(declassg)
+                }
+                StmtAST::Const(mut constant) => {
+                    let decl = &mut constant.decl;
+                    decl.var_type = self.check_type(&decl.var_type, decl.span);
+                    let tp = self.check_expr_as(&mut constant.value, &decl.var_type);
+                    if !self.unify(&decl.var_type, &tp) {
+                        self.errors.push(
+                            self.mismatch(&decl.var_type, &tp, constant.value.span())
+                                .with_secondary(decl.span, "expected due to this"),
+                        );
+                    }
+                    self.check_const_type(&constant.decl);
+                    let id = self.symbols.add_variable(&mut constant.decl);
+                    self.add_binding(id, &constant.decl, BindingKind::Constant);
+                    StmtAST::Const(constant)
+                }
+                StmtAST::Assign(mut ass) => {
+                    let target_tp = self.check_and_resolve_expression(&mut ass.target);
+                    let tp = self.check_expr_as(&mut ass.value, &target_tp);
+                    if !self.unify(&target_tp, &tp) {
+                        let mut err = self.mismatch(&target_tp, &tp, ass.value.span());
+                        if let ExprAST::Variable(var) = &ass.target {
+                            if let Some((_, decl)) = self.symbols.resolve_variable(var) {
+                                err = err.with_secondary(decl.span, "expected due to this");
+                            }
+                        }
+                        self.errors.push(err);
+                    }
+                    self.check_mutability(&ass);
+                    StmtAST::Assign(ass)
+                }
+
+                StmtAST::Call(mut cll) => {
+                    cll.tp = self.check_and_resolve_call(&mut cll);
+                    StmtAST::Call(cll)
+                }
+
+                StmtAST::Function(mut func) => {
+                    //the arguments are only visible inside of the function, in a scope around
+                    //the one of the body
+                    self.symbols.push_scope();
+                    for arg in &mut func.fn_signt.args {
+                        arg.var_type = self.check_type(&arg.var_type, arg.span);
+                        match self.symbols.add_argument(arg) {
+                            Ok(id) => self.add_binding(id, arg, BindingKind::Argument),
+                            Err(previous) => {
+                                let err = duplicate_definition(
+                                    "argument",
+                                    &arg.name,
+                                    arg.span,
+                                    previous.span,
+                                );
+                                self.errors.push(err);
+                            }
+                        }
+                    }
+                    func.fn_signt.rt_type =
+                        self.check_type(&func.fn_signt.rt_type, func.fn_signt.span);
+                    let rt_tp = func.fn_signt.rt_type.clone();
+                    func.body = self.check_nested(func.body, rt_tp, 0);
+                    self.symbols.pop_scope();
+                    StmtAST::Function(func)
+                }
+
+                StmtAST::Struct(def) => {
+                    self.check_struct_def(&def);
+                    StmtAST::Struct(def)
+                }
+
+                StmtAST::Enum(def) => {
+                    self.check_enum_def(&def);
+                    StmtAST::Enum(def)
+                }
+
+                StmtAST::If(if_st) => StmtAST::If(self.check_if_stmt(if_st)),
+
+                StmtAST::Match(match_st) => StmtAST::Match(self.check_match_stmt(match_st)),
+
+                StmtAST::While(mut while_st) => {
+                    let tp = self.check_expr_as(&mut while_st.condition, &TypeAST::Bool);
+                    self.expect_type(&TypeAST::Bool, &tp, while_st.condition.span());
+                    let expected_rt_tp = self.expected_rt_tp.clone();
+                    while_st.body =
+                        self.check_nested(while_st.body, expected_rt_tp, self.loops + 1);
+                    self.check_repeated(&while_st.body);
+                    StmtAST::While(while_st)
+                }
+
+                StmtAST::Break(span) | StmtAST::Continue(span) if self.loops == 0 => {
+                    let keyword = if matches!(stmt, StmtAST::Break(_)) {
+                        "break"
+                    } else {
+                        "continue"
+                    };
+                    self.errors.push(
+                        Diagnostic::error(
+                            diagnostics::JUMP_OUTSIDE_LOOP,
+                            format!("`{}` outside of a loop", keyword),
+                            span,
+                        )
+                        .with_label(format!("cannot `{}` outside of a loop", keyword)),
+                    );
+                    stmt
+                }
+                StmtAST::Break(_) | StmtAST::Continue(_) => stmt,
+
+                StmtAST::Print(print) => StmtAST::Print(self.check_print_stmt(print)),
+
+                StmtAST::Return(mut rtstmt) => {
+                    self.check_return_stmt(&mut rtstmt.expr);
+                    StmtAST::Return(rtstmt)
+                }
+            };
+            checked.push(stmt);
+        }
+        self.body.stmts = checked;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{lexer::Lexer, parser::Parser};
+
+    fn check(program: &str) -> (BodyAST, Vec<Diagnostic>) {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        Typechecker::new(body, None, TypeAST::Void).check_types()
+    }
+
+    #[test]
+    fn test_collects_all_errors() {
+        let (_, errors) = check(
+            "fn main() -> i8 {
+                let a: bool = 1;
+                let b: i8 = c + 1;
+                let d: i8 = foo(true);
+                return true;
+            }",
+        );
+        let lines: Vec<u32> = errors.iter().map(|err| err.primary.span.line).collect();
+        assert_eq!(lines, vec![2, 3, 4, 5]);
+        assert_eq!(errors[1].code, diagnostics::UNDECLARED_VARIABLE);
+        assert_eq!(errors[2].code, diagnostics::UNDEFINED_FUNCTION);
+    }
+
+    #[test]
+    fn test_no_follow_up_errors() {
+        //`a` is poisoned by the unknown variable, using it must not cause further errors
+        let (_, errors) = check(
+            "fn main() -> i8 {
+                let a = unknown + 1;
+                let b: bool = a;
+                let c: i8 = a * 2;
+                return a;
+            }",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, diagnostics::UNDECLARED_VARIABLE);
+    }
+
+    #[test]
+    fn test_returns_resolved_declarations() {
+        let (body, errors) = check(
+            "fn main() -> i32 {
+                let a = 5;
+                return a;
+            }",
+        );
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        //unconstrained literals take the default type
+        assert_eq!(decl.decl.var_type, TypeAST::I32);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let (body, errors) = check(
+            "fn double(n: u8) -> u8 {
+                return n * 2;
+            }
+            fn main() -> u8 {
+                let a: u8 = 1;
+                if true {
+                    let a = [a, 2];
+                    print(a[0] + 1);
+                }
+                return double(a);
+            }",
+        );
+        assert!(errors.is_empty());
+        let (StmtAST::Function(double), StmtAST::Function(main)) = (&body.stmts[0], &body.stmts[1])
+        else {
+            panic!("expected functions")
+        };
+        let (StmtAST::DeclAssign(outer), StmtAST::If(if_st), StmtAST::Return(rtstmt)) = (
+            &main.body.stmts[0],
+            &main.body.stmts[1],
+            &main.body.stmts[2],
+        ) else {
+            panic!("expected declaration, if and return")
+        };
+        let (StmtAST::DeclAssign(inner), StmtAST::Print(print)) =
+            (&if_st.body.stmts[0], &if_st.body.stmts[1])
+        else {
+            panic!("expected declaration and print")
+        };
+        //the array refers to the outer `a`, the indexing to the inner one
+        let ExprAST::ArrayLiteral(lit) = &inner.value else {
+            panic!("expected array literal")
+        };
+        let ExprAST::Variable(element) = &lit.elements[0] else {
+            panic!("expected variable")
+        };
+        assert_eq!(element.id, outer.decl.id);
+        assert_eq!(inner.value.tp(), TypeAST::Array(Box::new(TypeAST::U8), 2));
+        let ExprAST::BinaryExpression(sum) = &print.expr else {
+            panic!("expected binary expression")
+        };
+        let ExprAST::Index(index) = &sum.lhs else {
+            panic!("expected index")
+        };
+        let ExprAST::Variable(array) = &index.expr else {
+            panic!("expected variable")
+        };
+        assert_eq!(array.id, inner.decl.id);
+        assert_ne!(inner.decl.id, outer.decl.id);
+        assert_eq!(print.expr.tp(), TypeAST::U8);
+        //calls are linked to the signature and typed by its return type
+        let ExprAST::Call(call) = &rtstmt.expr else {
+            panic!("expected call")
+        };
+        assert!(call.id.is_some());
+        assert_eq!(call.id, double.fn_signt.id);
+        assert_eq!(call.tp, TypeAST::U8);
+        let ExprAST::Variable(arg) = &call.args[0] else {
+            panic!("expected variable")
+        };
+        assert_eq!(arg.id, outer.decl.id);
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        let (_, errors) = check(
+            "fn main() -> i8 {
+                if true {
+                    return 1;
+                } else if 2 {
+                    return true;
+                } else {
+                    let a: bool = 3;
+                }
+                return 0;
+            }",
+        );
+        let lines: Vec<u32> = errors.iter().map(|err| err.primary.span.line).collect();
+        assert_eq!(lines, [4, 5, 7]);
+    }
+
+    #[test]
+    fn test_loops() {
+        let (_, errors) = check(
+            "fn main() -> void {
+                while 1 {
+                    if true {
+                        break;
+                    }
+                    fn inner() -> void {
+                        continue;
+                    }
+                }
+                break;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::MISMATCHED_TYPES, 2),
+                (diagnostics::JUMP_OUTSIDE_LOOP, 7),
+                (diagnostics::JUMP_OUTSIDE_LOOP, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_print_formats() {
+        let (body, errors) = check(
+            "fn foo() -> void {}
+            fn main() -> void {
+                let a: u16 = 0;
+                print(a);
+                print(-1);
+                print('x');
+                print(1 == 2);
+                print(\"s\");
+                print(foo());
+                print(b);
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::UNPRINTABLE_TYPE, 9),
+                (diagnostics::UNDECLARED_VARIABLE, 10)
+            ]
+        );
+        let StmtAST::Function(main) = &body.stmts[1] else {
+            panic!("expected function")
+        };
+        let formats: Vec<PrintFormat> = main
+            .body
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                StmtAST::Print(print) => Some(print.format),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            formats,
+            [
+                PrintFormat::Unsigned,
+                PrintFormat::Signed,
+                PrintFormat::Char,
+                PrintFormat::Bool,
+                PrintFormat::Str,
+                PrintFormat::Unresolved,
+                PrintFormat::Unresolved
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structs() {
+        let (_, errors) = check(
+            "struct A { x: i8, b: B }
+            struct B { y: bool, y: u8 }
+            struct C { c: C }
+            struct A {}
+            fn main() -> void {
+                let mut a: A = A { x: 1, x: 2, z: 3 };
+                let n: i8 = a.b.y;
+                a.b.z = true;
+                let mut d: D;
+                print(a == a);
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::DUPLICATE_DEFINITION, 4),
+                (diagnostics::DUPLICATE_DEFINITION, 2),
+                (diagnostics::RECURSIVE_TYPE, 3),
+                (diagnostics::DUPLICATE_DEFINITION, 6),
+                (diagnostics::NO_FIELD, 6),
+                (diagnostics::MISSING_FIELDS, 6),
+                (diagnostics::MISMATCHED_TYPES, 7),
+                (diagnostics::NO_FIELD, 8),
+                (diagnostics::UNKNOWN_TYPE, 9),
+                (diagnostics::UNSUPPORTED_OPERATOR, 10)
+            ]
+        );
+        assert_eq!(
+            errors[5].message,
+            "missing fields `b` in initializer of `A`"
+        );
+    }
+
+    #[test]
+    fn test_enums_and_match() {
+        let (_, errors) = check(
+            "enum Op { Add, Sub = 0, Add, Big = 4294967296 }
+            enum State { Idle, Run = 3, Stop }
+            struct A {}
+            enum A { X }
+            fn main() -> void {
+                let s: State = State::Run;
+                let t: State = State::Jump;
+                let n: u8 = 0;
+                match s {
+                    State::Idle | State::Run => {}
+                    State::Run => {}
+                    2 => {}
+                }
+                match n {
+                    0 | 255 => {}
+                    256 => {}
+                }
+                match true {
+                    true => {}
+                    false => {}
+                    _ => {}
+                }
+                match \"s\" {
+                    _ => {}
+                }
+                match s == s {
+                    _ => {}
+                    false => {}
+                }
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::DUPLICATE_DEFINITION, 4),
+                (diagnostics::DUPLICATE_DEFINITION, 1),
+                (diagnostics::DUPLICATE_DEFINITION, 1),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 1),
+                (diagnostics::NO_VARIANT, 7),
+                (diagnostics::UNREACHABLE_PATTERN, 11),
+                (diagnostics::MISMATCHED_TYPES, 12),
+                (diagnostics::NON_EXHAUSTIVE_MATCH, 9),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 16),
+                (diagnostics::NON_EXHAUSTIVE_MATCH, 14),
+                (diagnostics::UNREACHABLE_PATTERN, 21),
+                (diagnostics::UNMATCHABLE_TYPE, 23),
+                (diagnostics::UNREACHABLE_PATTERN, 28)
+            ]
+        );
+        assert_eq!(
+            errors[7].message,
+            "non-exhaustive patterns: `State::Stop` not covered"
+        );
+        assert_eq!(
+            errors[9].message,
+            "non-exhaustive patterns: `_` not covered"
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        let (_, errors) = check(
+            "fn main() -> void {
+                let mut a: [i8; 4] = [1, 2, 3, 4];
+                let b: [i8; 3] = a;
+                let c: [bool; 2] = [true, 'c'];
+                a[1 + 2] = a[0];
+                a[2 * 2] = 1;
+                a[true] = 1;
+                let n: i8 = b[0][1];
+                let m: [[char; 2]; 2] = [['a'; 2]; 2];
+                let d: char = m[1][0];
+                let e: bool = a == a;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::MISMATCHED_TYPES, 3),
+                (diagnostics::MISMATCHED_TYPES, 4),
+                (diagnostics::CONST_INDEX_OUT_OF_BOUNDS, 6),
+                (diagnostics::MISMATCHED_TYPES, 7),
+                (diagnostics::CANNOT_INDEX, 8),
+                (diagnostics::UNSUPPORTED_OPERATOR, 11)
+            ]
+        );
+        assert_eq!(
+            errors[2].message,
+            "index out of bounds: the length is 4 but the index is 4"
+        );
+    }
+
+    #[test]
+    fn test_pointers() {
+        let (_, errors) = check(
+            "struct P { x: i8 }
+            fn main() -> void {
+                let x: i8 = 0;
+                let mut p: *i8 = &x;
+                let q: *u8 = p;
+                let n: i8 = *x;
+                let r: *i8 = &(x + 1);
+                let d: *i8 = p - p;
+                let b: bool = p < p + 1 && 2 + p == p;
+                let pp: **i8 = &p;
+                *pp = &x;
+                **pp = 'c';
+                let s: P = P { x: **pp };
+                let ps: *P = &s;
+                let f: *i8 = &ps.x;
+                ps.x = *f - 1;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::MISMATCHED_TYPES, 5),
+                (diagnostics::CANNOT_DEREF, 6),
+                (diagnostics::NOT_ADDRESSABLE, 7),
+                (diagnostics::UNSUPPORTED_OPERATOR, 8),
+                (diagnostics::MISMATCHED_TYPES, 12)
+            ]
+        );
+        assert_eq!(errors[0].primary.message, "expected `*u8`, found `*i8`");
+    }
+
+    #[test]
+    fn test_casts() {
+        let (_, errors) = check(
+            "enum E { A = 200 }
+            struct S { x: i8 }
+            fn main() -> void {
+                let a: i8 = -1;
+                let b: u16 = a as u16;
+                let c: char = b as char;
+                let d: bool = c as bool;
+                let e: i32 = E::A as i32 + d as i32;
+                let s: S = S { x: 1 };
+                let f: i8 = s as i8;
+                let g: E = 1 as E;
+                let h: u8 = a as i16;
+                let i: u8 = 1 as Missing;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::INVALID_CAST, 10),
+                (diagnostics::INVALID_CAST, 11),
+                (diagnostics::MISMATCHED_TYPES, 12),
+                (diagnostics::UNKNOWN_TYPE, 13)
+            ]
+        );
+        assert_eq!(errors[0].message, "casting `S` as `i8` is invalid");
+    }
+
+    #[test]
+    fn test_untyped_literals() {
+        let (_, errors) = check(
+            "fn wide(n: u32) -> u16 { return 60000; }
+            struct S { x: u8 }
+            fn main() -> void {
+                let a: u16 = 1000;
+                let b: u8 = 300;
+                let c: u8 = b + 256;
+                let d: i8 = 100 + 100 - 80;
+                let e: u32 = wide(4000000000) as u32;
+                let s: S = S { x: 255 };
+                let f: [u8; 3] = [0, 1, 256];
+                let g: [i16; 2] = [a as i16, 40000];
+                let h: bool = 1;
+                let i = 3000000000;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::LITERAL_OUT_OF_RANGE, 5),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 6),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 10),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 11),
+                (diagnostics::MISMATCHED_TYPES, 12),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 13)
+            ]
+        );
+        assert_eq!(errors[0].message, "literal out of range for `u8`");
+        assert_eq!(
+            errors[4].primary.message,
+            "expected `bool`, found `{integer}`"
+        );
+        assert_eq!(errors[5].message, "literal out of range for `i32`");
+    }
+
+    #[test]
+    fn test_negation() {
+        let (body, errors) = check(
+            "fn main() -> void {
+                let a: i8 = -128;
+                let b: i8 = -129;
+                let c: u8 = -1;
+                let d: i8 = -a;
+                let e: u8 = 5;
+                let f: u8 = -e;
+                let g: u8 = -(1 + 2);
+                let h = 3;
+                let i: u16 = -h + h;
+                let j: bool = -true;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::LITERAL_OUT_OF_RANGE, 3),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 4),
+                (diagnostics::UNSUPPORTED_OPERATOR, 7),
+                (diagnostics::UNSUPPORTED_OPERATOR, 8),
+                (diagnostics::UNSUPPORTED_OPERATOR, 11),
+                (diagnostics::UNSUPPORTED_OPERATOR, 10)
+            ]
+        );
+        assert_eq!(
+            errors[2].message,
+            "unary operator `-` is not supported for type `u8`"
+        );
+        //the minus is folded into the literal
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(a) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        assert!(
+            matches!(&a.value, ExprAST::Number(num) if num.num == -128 && num.tp == TypeAST::I8)
+        );
+    }
+
+    #[test]
+    fn test_default_int() {
+        let (body, errors) = Parser::new(Lexer::new(
+            "fn main() -> void {
+                let a = 7;
+                let b = 200;
+            }"
+            .into(),
+        ))
+        .parse();
+        assert!(errors.is_empty());
+        let (body, errors) = Typechecker::new(body, None, TypeAST::Void)
+            .with_default_int(TypeAST::U8)
+            .check_types();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        assert_eq!(decl.decl.var_type, TypeAST::U8);
+        let ExprAST::Number(num) = &decl.value else {
+            panic!("expected literal")
+        };
+        assert_eq!(num.tp, TypeAST::U8);
+    }
+
+    #[test]
+    fn test_inferred_declarations() {
+        let (body, errors) = check(
+            "fn takes(n: u16) -> void {}
+            fn main() -> void {
+                let a;
+                a = 1000;
+                let b: u16 = a;
+                let mut c;
+                if true { c = true; } else { c = false; }
+                let e = 5;
+                takes(e);
+                let mut f;
+                let mut g;
+                let h: bool = g + 1;
+                let i = 300;
+                let j: u8 = i;
+                let mut k;
+                print(*k);
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::MISMATCHED_TYPES, 12),
+                (diagnostics::TYPE_ANNOTATIONS_NEEDED, 16),
+                (diagnostics::TYPE_ANNOTATIONS_NEEDED, 10),
+                (diagnostics::LITERAL_OUT_OF_RANGE, 13)
+            ]
+        );
+        assert_eq!(
+            errors[0].primary.message,
+            "expected `bool`, found `{integer}`"
+        );
+        let StmtAST::Function(main) = &body.stmts[1] else {
+            panic!("expected function")
+        };
+        let types: Vec<TypeAST> = main
+            .body
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                StmtAST::Declaration(decl) => Some(decl.var_type.clone()),
+                StmtAST::DeclAssign(declassg) => Some(declassg.decl.var_type.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            types[..4],
+            [TypeAST::U16, TypeAST::U16, TypeAST::Bool, TypeAST::U16]
+        );
+    }
+
+    #[test]
+    fn test_mutability() {
+        let (_, errors) = check(
+            "fn inc(n: i8, mut m: i8) -> i8 {
+                n = n + 1;
+                m = m + 1;
+                return m;
+            }
+            struct P { x: i8 }
+            fn main() -> void {
+                let a: i8 = 1;
+                a = 2;
+                let b: i8;
+                b = 1;
+                let c: i8;
+                if true { c = 1; } else { c = 2; }
+                let d: i8;
+                d = 1;
+                d = 2;
+                let e: i8;
+                while false { e = 1; }
+                let f: i8;
+                let p: P = P { x: 1 };
+                p.x = 2;
+                let mut q: P = P { x: 1 };
+                let r: *P = &q;
+                r.x = 3;
+                *r = q;
+                let mut g: i8;
+                g = 1;
+                g = 2;
+                let arr: [i8; 2] = [1, 2];
+                arr[0] = 5;
+            }",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 2),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 9),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 16),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 18),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 21),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 30),
+                (diagnostics::NEVER_ASSIGNED, 19)
+            ]
+        );
+        assert_eq!(errors[0].message, "cannot assign to immutable argument `n`");
+        assert_eq!(
+            errors[1].message,
+            "cannot assign twice to immutable variable `a`"
+        );
+        let secondary: Vec<u32> = errors[2].secondary.iter().map(|l| l.span.line).collect();
+        assert_eq!(secondary, [14, 15]);
+        assert_eq!(
+            errors[4].message,
+            "cannot assign to a field of immutable variable `p`"
+        );
+        assert_eq!(
+            errors[5].message,
+            "cannot assign to an element of immutable variable `arr`"
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        let (_, errors) = check(
+            "fn f() -> i8 {
+                return 1;
+            }
+            fn main() -> void {
+                let a: bool = true;
+                if a {
+                    let a: i8 = 1;
+                    let b: i8 = a;
+                }
+                let c: i8 = 1;
+                let c: bool = true;
+                let d: bool = c;
+                let e: i8 = 1;
+                while d {
+                    let mut e: i8 = 2;
+                    e = 3;
+                    break;
+                }
+                fn f() -> bool {
+                    return true;
+                }
+                let g: bool = f();
+            }
+            fn h(x: i8, x: bool) -> void {}
+            fn h() -> void {}",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::DUPLICATE_DEFINITION, 25),
+                (diagnostics::DUPLICATE_DEFINITION, 24)
+            ]
+        );
+        assert_eq!(
+            errors[0].message,
+            "the function `h` is defined multiple times"
+        );
+        assert_eq!(errors[0].secondary[0].span.line, 24);
+        assert_eq!(
+            errors[1].message,
+            "the argument `x` is defined multiple times"
+        );
+    }
+
+    #[test]
+    fn test_globals() {
+        let (body, errors) = check(
+            "fn main() -> u8 {
+                return MAX - DOUBLE + 1;
+            }
+            const MAX: u8 = 200;
+            const DOUBLE: u8 = MAX * 2;
+            const ABOVE: bool = 1 > 0 && (MAX < DOUBLE) == false;
+            let mut count = MAX as u16 + 1000;",
+        );
+        assert!(errors.is_empty());
+        //the globals are moved in front of the functions and evaluated to literals
+        let values: Vec<&ExprAST> = body.stmts[..4]
+            .iter()
+            .filter_map(|stmt| match stmt {
+                StmtAST::Const(global) | StmtAST::DeclAssign(global) => Some(&global.value),
+                _ => None,
+            })
+            .collect();
+        let [ExprAST::Number(max), ExprAST::Number(double), ExprAST::BoolLiteral(above), ExprAST::Number(count)] =
+            values[..]
+        else {
+            panic!("unexpected values {:?}", values);
+        };
+        //200 * 2 wraps around in a u8
+        assert_eq!((max.num, double.num, above.value), (200, 144, true));
+        assert_eq!((count.num, &count.tp), (1200, &TypeAST::U16));
+
+        let (_, errors) = check(
+            "struct P { x: i8 }
+            const A: i8 = 1;
+            const B: P = P { x: 1 };
+            static C: i8 = 2;
+            static mut C: i8 = 3;
+            fn main() -> void {
+                A = 2;
+                C = 4;
+                let p: *i8 = &A;
+                S = 6;
+            }
+            static S: i8 = 5;",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::DUPLICATE_DEFINITION, 5),
+                (diagnostics::INVALID_CONST_TYPE, 3),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 7),
+                (diagnostics::NOT_ADDRESSABLE, 9),
+                (diagnostics::ASSIGN_TO_IMMUTABLE, 10)
+            ]
+        );
+        assert_eq!(errors[2].message, "cannot assign to constant `A`");
+        assert_eq!(
+            errors[4].message,
+            "cannot assign to immutable global variable `S`"
+        );
+        assert_eq!(
+            errors[4].notes,
+            ["consider making it mutable: `static mut S`"]
+        );
+
+        let (_, errors) = check(
+            "fn one() -> i8 {
+                return 1;
+            }
+            static A: i8 = 1;
+            static B: i8 = A + 1;
+            const C: i8 = one();
+            const D: i8 = 10 / (A - A);
+            const E: i8 = 10 / (3 - 3);",
+        );
+        let codes: Vec<(&str, u32)> = errors
+            .iter()
+            .map(|err| (err.code, err.primary.span.line))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (diagnostics::NOT_CONSTANT, 5),
+                (diagnostics::NOT_CONSTANT, 6),
+                (diagnostics::NOT_CONSTANT, 7),
+                (diagnostics::CONST_EVAL_FAILED, 8)
+            ]
+        );
+    }
 }
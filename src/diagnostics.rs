@@ -0,0 +1,282 @@
+use std::fmt::{Display, Write};
+
+use crate::parser::lexer::Span;
+
+//error codes, E01xx are syntax errors, E02xx are type errors, E03xx are code generation errors
+//and E04xx are errors of the interpreter while running a program, warnings use W instead of E
+pub const UNEXPECTED_TOKEN: &str = "E0101";
+pub const UNKNOWN_TOKEN: &str = "E0102";
+pub const NUMBER_OUT_OF_RANGE: &str = "E0103";
+pub const MISMATCHED_TYPES: &str = "E0201";
+pub const UNDECLARED_VARIABLE: &str = "E0202";
+pub const UNDEFINED_FUNCTION: &str = "E0203";
+pub const WRONG_ARGUMENT_COUNT: &str = "E0204";
+pub const UNSUPPORTED_OPERATOR: &str = "E0205";
+pub const LITERAL_OUT_OF_RANGE: &str = "E0206";
+pub const JUMP_OUTSIDE_LOOP: &str = "E0207";
+pub const UNPRINTABLE_TYPE: &str = "E0208";
+pub const UNKNOWN_TYPE: &str = "E0209";
+pub const NO_FIELD: &str = "E0210";
+pub const MISSING_FIELDS: &str = "E0211";
+pub const DUPLICATE_DEFINITION: &str = "E0212";
+pub const RECURSIVE_TYPE: &str = "E0213";
+pub const NO_VARIANT: &str = "E0214";
+pub const NON_EXHAUSTIVE_MATCH: &str = "E0215";
+pub const UNMATCHABLE_TYPE: &str = "E0216";
+pub const UNREACHABLE_PATTERN: &str = "E0217";
+pub const CANNOT_INDEX: &str = "E0218";
+pub const CONST_INDEX_OUT_OF_BOUNDS: &str = "E0219";
+pub const CANNOT_DEREF: &str = "E0220";
+pub const NOT_ADDRESSABLE: &str = "E0221";
+pub const INVALID_CAST: &str = "E0222";
+pub const TYPE_ANNOTATIONS_NEEDED: &str = "E0223";
+pub const ASSIGN_TO_IMMUTABLE: &str = "E0224";
+pub const NEVER_ASSIGNED: &str = "E0225";
+pub const MISSING_RETURN: &str = "E0226";
+pub const UNASSIGNED_VARIABLE: &str = "E0227";
+pub const INVALID_CONST_TYPE: &str = "E0228";
+pub const NOT_CONSTANT: &str = "E0229";
+pub const CONST_EVAL_FAILED: &str = "E0230";
+pub const UNREACHABLE_STATEMENT: &str = "W0201";
+pub const UNSUPPORTED_BY_BACKEND: &str = "E0301";
+pub const MISSING_MAIN: &str = "E0302";
+pub const OUT_OF_MEMORY: &str = "E0303";
+pub const DIVISION_BY_ZERO: &str = "E0401";
+pub const STACK_OVERFLOW: &str = "E0402";
+pub const INDEX_OUT_OF_BOUNDS: &str = "E0403";
+pub const INVALID_POINTER: &str = "E0404";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+///a span with a message that is printed next to the underlined source
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+///an error or warning found in a program, points at the offending source through its labels
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    ///creates an error pointing at span, the primary label has no message until with_label is used
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            primary: Label {
+                span,
+                message: String::new(),
+            },
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    ///creates a warning pointing at span, warnings do not stop the compilation
+    pub fn warning(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message, span)
+        }
+    }
+
+    ///sets the message printed under the primary span
+    pub fn with_label(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = message.into();
+        self
+    }
+
+    ///adds another location that helps explaining the error
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+///renders diagnostics rustc style, with the offending source lines and carets under the spans
+pub struct Reporter<'a> {
+    file_name: &'a str,
+    source: &'a [u8],
+}
+
+impl<'a> Reporter<'a> {
+    pub fn new(file_name: &'a str, source: &'a [u8]) -> Self {
+        Self { file_name, source }
+    }
+
+    ///returns the text of a 1-based line without the line break
+    fn line_text(&self, line: u32) -> String {
+        let text = self
+            .source
+            .split(|c| *c == b'\n')
+            .nth(line as usize - 1)
+            .unwrap_or_default();
+        String::from_utf8_lossy(text).trim_end().to_string()
+    }
+
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let mut out = String::new();
+        let primary = &diag.primary;
+        let mut labels = vec![(primary, '^')];
+        labels.extend(diag.secondary.iter().map(|label| (label, '-')));
+        labels.sort_by_key(|(label, _)| label.span.start);
+
+        let width = labels
+            .iter()
+            .map(|(label, _)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        writeln!(out, "{}[{}]: {}", diag.severity, diag.code, diag.message).unwrap();
+        writeln!(out, "{:width$}--> {}:{}", "", self.file_name, primary.span).unwrap();
+        writeln!(out, "{:width$} |", "").unwrap();
+
+        let mut last_line = None;
+        for (label, marker) in labels {
+            let line = label.span.line;
+            let text = self.line_text(line);
+            if last_line != Some(line) {
+                //mark skipped lines between two labels
+                if last_line.is_some_and(|last| line > last + 1) {
+                    writeln!(out, "...").unwrap();
+                }
+                writeln!(out, "{:>width$} | {}", line, text).unwrap();
+                last_line = Some(line);
+            }
+            //underline up to the end of the span or the end of the first line of the span
+            let col = label.span.col as usize - 1;
+            let len = (label.span.end - label.span.start)
+                .min(text.len().saturating_sub(col))
+                .max(1);
+            let underline = marker.to_string().repeat(len);
+            let underline = format!("{}{} {}", " ".repeat(col), underline, label.message);
+            writeln!(out, "{:width$} | {}", "", underline.trim_end()).unwrap();
+        }
+
+        if !diag.notes.is_empty() {
+            writeln!(out, "{:width$} |", "").unwrap();
+        }
+        for note in &diag.notes {
+            writeln!(out, "{:width$} = note: {}", "", note).unwrap();
+        }
+        out
+    }
+
+    ///prints all diagnostics to stderr, returns the number of errors among them
+    pub fn emit(&self, diags: &[Diagnostic]) -> usize {
+        for diag in diags {
+            eprintln!("{}", self.render(diag));
+        }
+        let errors = diags
+            .iter()
+            .filter(|diag| diag.severity == Severity::Error)
+            .count();
+        match errors {
+            0 => {}
+            1 => eprintln!("error: aborting due to 1 previous error"),
+            n => eprintln!("error: aborting due to {} previous errors", n),
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_primary_and_secondary() {
+        let program = "fn main() -> i8 {\n    let a: i8 = true;\n    return a;\n}";
+        let reporter = Reporter::new("main.myla", program.as_bytes());
+        let diag = Diagnostic::error(
+            MISMATCHED_TYPES,
+            "mismatched types",
+            Span {
+                start: 34,
+                end: 38,
+                line: 2,
+                col: 17,
+            },
+        )
+        .with_label("expected `i8`, found `bool`")
+        .with_secondary(
+            Span {
+                start: 22,
+                end: 31,
+                line: 2,
+                col: 5,
+            },
+            "expected due to this",
+        )
+        .with_note("booleans can not be used as integers");
+
+        assert_eq!(
+            reporter.render(&diag),
+            "error[E0201]: mismatched types
+ --> main.myla:2:17
+  |
+2 |     let a: i8 = true;
+  |     --------- expected due to this
+  |                 ^^^^ expected `i8`, found `bool`
+  |
+  = note: booleans can not be used as integers
+"
+        );
+    }
+
+    #[test]
+    fn test_render_end_of_file() {
+        let program = "fn main() {";
+        let reporter = Reporter::new("main.myla", program.as_bytes());
+        let diag = Diagnostic::error(
+            UNEXPECTED_TOKEN,
+            "expected statement, found end of file",
+            Span {
+                start: 11,
+                end: 11,
+                line: 1,
+                col: 12,
+            },
+        );
+        assert_eq!(
+            reporter.render(&diag),
+            "error[E0101]: expected statement, found end of file
+ --> main.myla:1:12
+  |
+1 | fn main() {
+  |            ^
+"
+        );
+    }
+}
@@ -0,0 +1,761 @@
+//! emulator for the cpu described by a target, runs assembled images without minecraft
+//!
+//! the image is loaded at address 0 and execution starts at the code start of the target. every
+//! step executes one instruction, the program stops at `HALT` with the return value of main in
+//! r0. words stored to the io_out port of the target are not written to memory, their lowest
+//! byte is appended to the output instead
+
+use crate::codegeneration::isa::{Op, OperandKind};
+use crate::codegeneration::target::Target;
+
+///the flags set by `CMP`
+#[derive(Debug, Default, Clone, Copy)]
+struct Flags {
+    zero: bool,
+    //signed less than
+    negative: bool,
+    //unsigned less than
+    carry: bool,
+}
+
+///an operand read from the instruction stream
+enum Decoded {
+    Reg(u8),
+    Imm(u64),
+    //address computed from the register and the offset
+    Mem(u64),
+}
+
+pub struct Emulator<'a> {
+    target: &'a Target,
+    registers: Vec<u64>,
+    memory: Vec<u8>,
+    pc: u64,
+    flags: Flags,
+    halted: bool,
+    pub output: Vec<u8>,
+    //number of executed instructions
+    pub cycles: u64,
+}
+
+impl<'a> Emulator<'a> {
+    pub fn new(target: &'a Target, image: &[u8]) -> Result<Self, String> {
+        if image.len() as u64 > target.memory_size {
+            return Err(format!(
+                "the image has {} bytes but the memory only {}",
+                image.len(),
+                target.memory_size
+            ));
+        }
+        let mut memory = vec![0; target.memory_size as usize];
+        memory[..image.len()].copy_from_slice(image);
+        Ok(Self {
+            target,
+            registers: vec![0; target.registers as usize],
+            memory,
+            pc: target.code_start,
+            flags: Flags::default(),
+            halted: false,
+            output: Vec::new(),
+            cycles: 0,
+        })
+    }
+
+    fn mask(&self, value: u64) -> u64 {
+        value & ((1 << self.target.word_bits) - 1)
+    }
+
+    ///interprets a word as signed number
+    fn signed(&self, value: u64) -> i64 {
+        let shift = 64 - self.target.word_bits;
+        ((value << shift) as i64) >> shift
+    }
+
+    fn check_addr(&self, addr: u64, len: u64) -> Result<(), String> {
+        if addr + len > self.target.memory_size {
+            return Err(format!("memory access at {:#x} is out of bounds", addr));
+        }
+        Ok(())
+    }
+
+    fn fetch(&mut self, len: u64) -> Result<&[u8], String> {
+        self.check_addr(self.pc, len)?;
+        let start = self.pc as usize;
+        self.pc += len;
+        Ok(&self.memory[start..start + len as usize])
+    }
+
+    fn fetch_word(&mut self) -> Result<u64, String> {
+        let target = self.target;
+        let word = self.fetch(target.word_bytes() as u64)?;
+        Ok(target.decode_word(word))
+    }
+
+    fn load(&self, addr: u64) -> Result<u64, String> {
+        let len = self.target.word_bytes() as u64;
+        self.check_addr(addr, len)?;
+        let start = addr as usize;
+        Ok(self
+            .target
+            .decode_word(&self.memory[start..start + len as usize]))
+    }
+
+    fn store(&mut self, addr: u64, value: u64) -> Result<(), String> {
+        if addr == self.target.io_out {
+            self.output.push(value as u8);
+            return Ok(());
+        }
+        let bytes = self.target.encode_word(value as i64);
+        self.check_addr(addr, bytes.len() as u64)?;
+        let start = addr as usize;
+        self.memory[start..start + bytes.len()].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn push(&mut self, value: u64) -> Result<(), String> {
+        let sp = self.target.stack_pointer() as usize;
+        let addr = self.registers[sp].wrapping_sub(self.target.word_bytes() as u64);
+        self.registers[sp] = self.mask(addr);
+        self.store(self.registers[sp], value)
+    }
+
+    fn pop(&mut self) -> Result<u64, String> {
+        let sp = self.target.stack_pointer() as usize;
+        let value = self.load(self.registers[sp])?;
+        self.registers[sp] = self.mask(self.registers[sp] + self.target.word_bytes() as u64);
+        Ok(value)
+    }
+
+    fn decode_operand(&mut self, kind: OperandKind) -> Result<Decoded, String> {
+        match kind {
+            OperandKind::Reg => {
+                let reg = self.fetch(1)?[0];
+                if reg >= self.target.registers {
+                    return Err(format!("invalid register r{}", reg));
+                }
+                Ok(Decoded::Reg(reg))
+            }
+            OperandKind::Imm => Ok(Decoded::Imm(self.fetch_word()?)),
+            OperandKind::Mem => {
+                let reg = match self.decode_operand(OperandKind::Reg)? {
+                    Decoded::Reg(reg) => reg,
+                    _ => unreachable!(),
+                };
+                let offset = self.fetch_word()?;
+                Ok(Decoded::Mem(
+                    self.mask(self.registers[reg as usize].wrapping_add(offset)),
+                ))
+            }
+        }
+    }
+
+    ///executes one instruction
+    pub fn step(&mut self) -> Result<(), String> {
+        let opcode = self.fetch(1)?[0];
+        let op = self
+            .target
+            .op_by_opcode(opcode)
+            .ok_or_else(|| format!("unknown opcode {:#04x}", opcode))?;
+        let mut operands = Vec::new();
+        for kind in op.operand_kinds() {
+            operands.push(self.decode_operand(*kind)?);
+        }
+        self.cycles += 1;
+
+        let reg = |operand: &Decoded| match operand {
+            Decoded::Reg(reg) => *reg as usize,
+            _ => unreachable!("decoded from the operand kinds"),
+        };
+        let value = |operand: &Decoded| match operand {
+            Decoded::Imm(value) | Decoded::Mem(value) => *value,
+            Decoded::Reg(_) => unreachable!("decoded from the operand kinds"),
+        };
+        let bits = self.target.word_bits as u64;
+        match op {
+            Op::Ldi => self.registers[reg(&operands[0])] = value(&operands[1]),
+            Op::Mov => self.registers[reg(&operands[0])] = self.registers[reg(&operands[1])],
+            Op::Add
+            | Op::Sub
+            | Op::Mul
+            | Op::Div
+            | Op::Divs
+            | Op::And
+            | Op::Or
+            | Op::Xor
+            | Op::Shl
+            | Op::Shr
+            | Op::Sar => {
+                let a = self.registers[reg(&operands[1])];
+                let b = self.registers[reg(&operands[2])];
+                if matches!(op, Op::Div | Op::Divs) && b == 0 {
+                    return Err("division by zero".to_string());
+                }
+                let result = match op {
+                    Op::Add => a.wrapping_add(b),
+                    Op::Sub => a.wrapping_sub(b),
+                    Op::Mul => a.wrapping_mul(b),
+                    Op::Div => a / b,
+                    Op::Divs => self.signed(a).wrapping_div(self.signed(b)) as u64,
+                    Op::And => a & b,
+                    Op::Or => a | b,
+                    Op::Xor => a ^ b,
+                    //shifting by the width of a word or more shifts everything out
+                    Op::Shl if b >= bits => 0,
+                    Op::Shl => a << b,
+                    Op::Shr if b >= bits => 0,
+                    Op::Shr => a >> b,
+                    _ => (self.signed(a) >> b.min(bits - 1)) as u64,
+                };
+                self.registers[reg(&operands[0])] = self.mask(result);
+            }
+            Op::Cmp => {
+                let a = self.registers[reg(&operands[0])];
+                let b = self.registers[reg(&operands[1])];
+                self.flags = Flags {
+                    zero: a == b,
+                    negative: self.signed(a) < self.signed(b),
+                    carry: a < b,
+                };
+            }
+            Op::Jmp | Op::Jeq | Op::Jne | Op::Jlt | Op::Jb => {
+                let taken = match op {
+                    Op::Jmp => true,
+                    Op::Jeq => self.flags.zero,
+                    Op::Jne => !self.flags.zero,
+                    Op::Jlt => self.flags.negative,
+                    _ => self.flags.carry,
+                };
+                if taken {
+                    self.pc = value(&operands[0]);
+                }
+            }
+            Op::Ldw => {
+                self.registers[reg(&operands[0])] = self.load(value(&operands[1]))?;
+            }
+            Op::Stw => self.store(value(&operands[0]), self.registers[reg(&operands[1])])?,
+            Op::Push => self.push(self.registers[reg(&operands[0])])?,
+            Op::Pop => self.registers[reg(&operands[0])] = self.pop()?,
+            Op::Call => {
+                self.push(self.pc)?;
+                self.pc = value(&operands[0]);
+            }
+            Op::Ret => self.pc = self.pop()?,
+            Op::Halt => self.halted = true,
+        }
+        Ok(())
+    }
+
+    ///runs until the program halts, returns the exit code from r0
+    pub fn run(&mut self, max_cycles: u64) -> Result<i64, String> {
+        while !self.halted {
+            if self.cycles >= max_cycles {
+                return Err(format!(
+                    "the program did not halt within {} instructions",
+                    max_cycles
+                ));
+            }
+            let pc = self.pc;
+            self.step()
+                .map_err(|err| format!("at {:#06x}: {}", pc, err))?;
+        }
+        Ok(self.signed(self.registers[0]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegeneration::{assembler, codegen::Codegen, typechecks::Typechecker};
+    use crate::interpreter::Interpreter;
+    use crate::parser::{ast::TypeAST, lexer::Lexer, parser::Parser};
+
+    const MAX_CYCLES: u64 = 10000;
+
+    fn run(asm: &str, target: &Target) -> (Result<i64, String>, Vec<u8>) {
+        let image = assembler::assemble(asm, target).unwrap().image;
+        let mut emulator = Emulator::new(target, &image).unwrap();
+        let result = emulator.run(MAX_CYCLES);
+        (result, emulator.output)
+    }
+
+    fn compile(program: &str, target: &Target) -> String {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        let (checked, errors) = Typechecker::new(body, None, TypeAST::Void).check_types();
+        assert!(errors.is_empty());
+        let (asm, errors) = Codegen::new(target).generate(&checked);
+        assert!(errors.is_empty());
+        asm
+    }
+
+    ///runs the program in the interpreter and on the bundled targets, the exit code and output
+    ///have to be the same everywhere, returns them
+    fn assert_matches_interpreter(program: &str) -> (i64, Vec<u8>) {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        //the default i32 does not fit into redstone16
+        let (checked, errors) = Typechecker::new(body, None, TypeAST::Void)
+            .with_default_int(TypeAST::I16)
+            .check_types();
+        assert!(errors.is_empty());
+        let mut interpreter = Interpreter::new();
+        let expected = interpreter.run(&checked).unwrap().exit_code();
+        for target in [
+            Target::default(),
+            Target::load("./targets/redstone16.target").unwrap(),
+        ] {
+            let (asm, errors) = Codegen::new(&target).generate(&checked);
+            assert!(errors.is_empty());
+            let (result, output) = run(&asm, &target);
+            assert_eq!(result, Ok(expected));
+            assert_eq!(output, interpreter.output);
+        }
+        (expected, interpreter.output)
+    }
+
+    #[test]
+    fn test_compiled_program() {
+        let program = "fn sub(a: i8, b: i8) -> i8 {
+                return a - b;
+            }
+            fn main() -> i8 {
+                let a: i8 = sub(3, 5);
+                if a < 0 {
+                    return sub(a, 127);
+                }
+                return 1;
+            }";
+        //-129 wraps around to 127 in an i8
+        for target in [
+            Target::default(),
+            Target::load("./targets/redstone16.target").unwrap(),
+        ] {
+            let (result, _) = run(&compile(program, &target), &target);
+            assert_eq!(result, Ok(127));
+        }
+    }
+
+    #[test]
+    fn test_nested_function_named_ret() {
+        let program = "fn main() -> u8 {
+                fn ret() -> u8 {
+                    return 1;
+                }
+                return ret();
+            }";
+        let target = Target::default();
+        let (result, _) = run(&compile(program, &target), &target);
+        assert_eq!(result, Ok(1));
+    }
+
+    #[test]
+    fn test_shadowed_functions_match_interpreter() {
+        let program = "fn main() -> u8 {
+                let mut total: u8 = 0;
+                if true {
+                    fn f() -> u8 {
+                        return 1;
+                    }
+                    total = total + f();
+                }
+                if true {
+                    fn f() -> u8 {
+                        return 10;
+                    }
+                    total = total + f();
+                    if true {
+                        fn f() -> u8 {
+                            return 100;
+                        }
+                        total = total + f();
+                    }
+                    total = total + f();
+                }
+                return total;
+            }";
+        assert_eq!(assert_matches_interpreter(program).0, 121);
+    }
+
+    #[test]
+    fn test_captured_variables_match_interpreter() {
+        let program = "struct Pair { a: i8, b: i8 }
+            fn main() -> i8 {
+                let x: i8 = 7;
+                let mut count: i8 = 0;
+                let mut pair: Pair = Pair { a: 1, b: 2 };
+                let mut small: u8 = 3;
+                fn bump() -> void {
+                    count = count + 1;
+                    pair.b = pair.b + x;
+                    fn twice() -> void {
+                        inc(&small);
+                        count = count + 10;
+                    }
+                    twice();
+                }
+                fn inc(p: *u8) -> void {
+                    *p = *p + 1;
+                }
+                fn down(n: i8) -> i8 {
+                    if n == 0 {
+                        return x;
+                    }
+                    return down(n - 1) + 1;
+                }
+                bump();
+                bump();
+                let copy: Pair = pair;
+                print(count);
+                print(' ');
+                print(copy.b);
+                print(' ');
+                print(small);
+                return down(3);
+            }";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 10);
+        assert_eq!(output, b"22 16 5");
+    }
+
+    #[test]
+    fn test_control_flow_matches_interpreter() {
+        let program = "fn main() -> i8 {
+                let mut i: i8 = 0;
+                let mut sum: i8 = 0;
+                while true {
+                    i = i + 1;
+                    if i == 5 {
+                        continue;
+                    } else if i > 8 {
+                        break;
+                    } else {
+                        sum = sum + i;
+                    }
+                }
+                return sum;
+            }";
+        assert_eq!(assert_matches_interpreter(program).0, 31);
+    }
+
+    #[test]
+    fn test_print_matches_interpreter() {
+        let program = "fn main() -> void {
+                let min: i8 = -128;
+                let big: u16 = 0;
+                let c: char = 'x';
+                print(min);
+                print(' ');
+                print(big);
+                print(c);
+                print(100 / 3);
+                print(\"\\tok\\n\");
+                print(min < 0);
+                print(false);
+            }";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 0);
+        assert_eq!(output, b"-128 0x33\tok\ntruefalse");
+    }
+
+    #[test]
+    fn test_structs_match_interpreter() {
+        let program = "struct Inner { c: char, n: i8, on: bool }
+            struct Outer { flag: bool, inner: Inner, wide: i8, name: str }
+            fn main() -> i8 {
+                let mut o: Outer = Outer {
+                    name: \"s\",
+                    inner: Inner { n: -5, c: 'a', on: true },
+                    wide: -100,
+                    flag: true,
+                };
+                let mut copy: Outer = o;
+                o.inner.n = o.inner.n - 1;
+                o.inner.c = 'b';
+                copy.flag = false;
+                let i: Inner = o.inner;
+                print(o.flag);
+                print(copy.flag);
+                print(i.c);
+                print(i.on);
+                print(copy.inner.c);
+                print(o.inner.n);
+                print(copy.inner.n);
+                print(o.name);
+                return o.wide + i.n;
+            }";
+        //aligned fields on mcpu32, packed fields on redstone16
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, -106);
+        assert_eq!(output, b"truefalsebtruea-6-5s");
+    }
+
+    #[test]
+    fn test_match_matches_interpreter() {
+        let program = "enum Op { Nop, Load, Store, Add, Jump, Halt = -1 }
+            enum Dir { Left = -2, Right = 2 }
+            struct Instr { op: Op, dir: Dir }
+            fn name(op: Op) -> char {
+                match op {
+                    Op::Load => { return 'l'; }
+                    Op::Store | Op::Add => { return 's'; }
+                    Op::Jump => { return 'j'; }
+                    _ => { return '?'; }
+                }
+            }
+            fn main() -> i8 {
+                let mut i: Instr = Instr { op: Op::Nop, dir: Dir::Left };
+                print(name(i.op));
+                i.op = Op::Store;
+                print(name(i.op));
+                print(name(Op::Jump));
+                print(name(Op::Halt));
+                match i.dir {
+                    Dir::Right => { print('r'); }
+                    Dir::Left => { print('L'); }
+                }
+                let mut n: i8 = 0;
+                while true {
+                    n = n + 1;
+                    match n {
+                        3 => { break; }
+                        -1 | 1 => { continue; }
+                        _ => { print(n == 2); }
+                    }
+                }
+                return n;
+            }";
+        //the first match uses a jump table, the others compare
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 3);
+        assert_eq!(output, b"?sj?Ltrue");
+    }
+
+    #[test]
+    fn test_arrays_match_interpreter() {
+        let program = "struct Cell { on: bool, c: char }
+            fn main() -> i8 {
+                let n: u8 = 0;
+                let mut squares: [i8; 5] = [0, 1, 4, 9, 16];
+                let wide: [u8; 3] = [n; 3];
+                let mut screen: [[Cell; 3]; 2] = [[Cell { on: false, c: '.' }; 3]; 2];
+                screen[1][2] = Cell { on: true, c: '#' };
+                screen[0][1].c = 'o';
+                let mut i: i8 = 0;
+                while i < 2 {
+                    let mut j: i8 = 0;
+                    while j < 3 {
+                        print(screen[i][j].c);
+                        j = j + 1;
+                    }
+                    i = i + 1;
+                }
+                squares[squares[2] - 1] = -7;
+                print(wide[2]);
+                print(screen[1][2].on);
+                return squares[3] + squares[4];
+            }";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 9);
+        assert_eq!(output, b".o...#0true");
+    }
+
+    #[test]
+    fn test_pointers_match_interpreter() {
+        let program = "struct Node { value: i8, tag: char, next: *Node }
+            fn bump(n: *i8) -> void {
+                *n = *n + 1;
+            }
+            fn sum(first: *Node) -> i8 {
+                let mut sum: i8 = 0;
+                let mut node: *Node = first;
+                let mut i: i8 = 0;
+                while i < 3 {
+                    sum = sum + node.value;
+                    print(node.tag);
+                    node = node.next;
+                    i = i + 1;
+                }
+                return sum;
+            }
+            fn main() -> i8 {
+                let mut x: i8 = -3;
+                bump(&x);
+                bump(&x);
+                print(x);
+                let mut a: Node;
+                let b: Node = Node { value: 20, tag: 'b', next: &a };
+                let c: Node = Node { value: 30, tag: 'c', next: &b };
+                a = Node { value: 10, tag: 'a', next: &c };
+                let total: i8 = sum(&a);
+                let mut values: [i8; 4] = [1, 2, 3, 4];
+                let mut p: *i8 = &values[0];
+                p = p + 2;
+                *p = -7;
+                let q: *i8 = p - 1;
+                bump(q);
+                print(values[1]);
+                print(values[2]);
+                print(p > q);
+                print(&values[3] == 1 + p);
+                return total + *q;
+            }";
+        //x is a byte in the middle of its word on the big endian redstone16
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 63);
+        assert_eq!(output, b"-1acb3-7truetrue");
+    }
+
+    #[test]
+    fn test_casts_match_interpreter() {
+        let program = "enum Color { Red = 200 }
+            fn main() -> i8 {
+                let m: i8 = -1;
+                let big: u16 = m as u16;
+                let wide: i16 = m as i16;
+                print(big);
+                print(wide);
+                print(wide as u16 == big);
+                print(m as u8);
+                print(big as u8 as i8);
+                let n: u16 = 100 as u16 * 3 as u16;
+                print(n as u8);
+                print((n - 100 as u16) as i8);
+                print(n as bool);
+                print((n - n) as bool);
+                let seventy: u8 = 70 as u8;
+                print((seventy - 5 as u8) as char);
+                print('z' as i8);
+                print(true as u8 + 1 as u8);
+                print(Color::Red as i8);
+                print(Color::Red as u16);
+                return m as i16 as i8;
+            }";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, -1);
+        assert_eq!(output, b"65535-1true255-144-56truefalseA1222-56200");
+    }
+
+    #[test]
+    fn test_untyped_literals_match_interpreter() {
+        let program = "fn scale(n: u16, by: u8) -> u16 {
+                return n * by as u16 + 1;
+            }
+            fn main() -> i8 {
+                let x: u16 = 1000;
+                let y: u8 = 5;
+                print(x + 24);
+                print(y * 51);
+                print(scale(x, 60));
+                let big: i16 = 30000;
+                print(big + 2767);
+                let mask: u16 = 65535;
+                print(mask - x);
+                let w: u8 = 200;
+                print(w + 100);
+                let arr: [u16; 2] = [500, 40000];
+                print(arr[1] - arr[0]);
+                let d = 300 * 100;
+                print(d);
+                print(x > 999);
+                return 100 + 27;
+            }";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 127);
+        assert_eq!(output, b"1024255600013276764535443950030000true");
+    }
+
+    #[test]
+    fn test_negation_matches_interpreter() {
+        let program = "const LOW: i16 = -(300 + 4);
+            fn main() -> i16 {
+                let min: i8 = -128;
+                let x: i16 = 70;
+                print(-min);
+                print(' ');
+                print(-x * 2);
+                print(' ');
+                print(5 - -x);
+                return -LOW + -(x / 7);
+            }";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, 294);
+        assert_eq!(output, b"-128 -140 75");
+    }
+
+    #[test]
+    fn test_globals_match_interpreter() {
+        let program = "struct Point { x: i8, y: i16 }
+            enum Color { Red, Green = 7 }
+            const LIMIT: u8 = 2 * 3 + 1;
+            const SHIFTED: i16 = (LIMIT as i16) << 4;
+            const GREETING: str = \"hi \";
+            const FAVORITE: Color = Color::Green;
+            static mut COUNTER: i16 = SHIFTED - 100;
+            static ORIGIN: Point = Point { y: -300, x: LIMIT as i8 };
+            let mut table = [LIMIT; 3];
+            let mut flag: bool = LIMIT > 5;
+            fn bump(n: *i16) -> void {
+                *n = *n + 1;
+            }
+            fn main() -> i16 {
+                print(GREETING);
+                COUNTER = COUNTER + 1;
+                bump(&COUNTER);
+                table[1] = table[1] + LIMIT;
+                print(table[1]);
+                print(flag);
+                flag = flag == false;
+                print(flag);
+                print(FAVORITE as u8);
+                return COUNTER + ORIGIN.y + ORIGIN.x as i16 + late();
+            }
+            fn late() -> i16 {
+                return LATE;
+            }
+            static LATE: i16 = 5;";
+        let (exit, output) = assert_matches_interpreter(program);
+        assert_eq!(exit, -274);
+        assert_eq!(output, b"hi 14truefalse7");
+    }
+
+    #[test]
+    fn test_io_out_and_flags() {
+        let target = Target::default();
+        let (result, output) = run(
+            "    LDI r1, 0xfffc
+                LDI r2, 72
+                STW [r1], r2
+                LDI r2, -1
+                LDI r3, 1
+                CMP r2, r3
+                JB wrong
+                JLT right
+            wrong:
+                HALT
+            right:
+                LDI r2, 105
+                STW [r1], r2
+                LDI r0, -7
+                HALT",
+            &target,
+        );
+        //-1 is smaller than 1 signed but not unsigned
+        assert_eq!(result, Ok(-7));
+        assert_eq!(output, b"Hi");
+    }
+
+    #[test]
+    fn test_faults() {
+        let target = Target::default();
+        let (result, _) = run("loop:\n JMP loop", &target);
+        assert_eq!(
+            result,
+            Err(format!(
+                "the program did not halt within {} instructions",
+                MAX_CYCLES
+            ))
+        );
+        let (result, _) = run(" LDI r1, 0\n DIV r0, r0, r1", &target);
+        assert_eq!(result, Err("at 0x0006: division by zero".to_string()));
+    }
+}
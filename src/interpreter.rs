@@ -0,0 +1,873 @@
+//! tree-walking interpreter for typechecked programs, the reference for the code generator
+//!
+//! integers are kept as i64 together with their type and wrap around to the width of the type
+//! after every operation, exactly like the registers of the cpu do after normalization
+//!
+//! printed values are collected in the output, formatted like the print routines of the code
+//! generator do
+//!
+//! values of enums are integers of the representation of the enum, holding the discriminant
+
+use std::collections::HashMap;
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::parser::{
+    ast::{
+        BinaryExpressionAST, BodyAST, CallAST, ElseAST, EnumAST, ExprAST, FunctionAST, IfStmtAST,
+        MatchStmtAST, PatternAST, PrintStmtAST, StmtAST, StructAST, StructLiteralAST, TypeAST,
+    },
+    lexer::{Span, Token},
+};
+
+//calls that can be nested before the program is stopped
+const MAX_CALL_DEPTH: usize = 1024;
+//every call of the program recurses in the interpreter, so it runs on a thread with a large stack
+const STACK_SIZE: usize = 64 << 20;
+
+///where a pointer points to, a variable of a running function and the fields and elements inside
+///of it
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Location {
+    frame: usize,
+    //the call that created the frame, pointers into frames of returned calls are dangling
+    call: usize,
+    scope: usize,
+    var: usize,
+    //positions of the fields and indices of the elements
+    path: Vec<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Value {
+    Int(i64, TypeAST),
+    Bool(bool),
+    Str(String),
+    Char(u8),
+    //the fields in the order of the definition
+    Struct(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    //None is the null pointer of pointers that were never assigned
+    Pointer(Option<Location>),
+    Void,
+}
+
+impl Value {
+    ///the exit code of a program that returned this value from main
+    pub fn exit_code(&self) -> i64 {
+        match self {
+            Value::Int(value, _) => *value,
+            Value::Bool(value) => *value as i64,
+            Value::Char(value) => *value as i64,
+            Value::Str(_)
+            | Value::Struct(_)
+            | Value::Array(_)
+            | Value::Pointer(_)
+            | Value::Void => 0,
+        }
+    }
+}
+
+///gives an integer the type of the variable, parameter or return value it is stored in
+fn convert(value: Value, tp: &TypeAST) -> Value {
+    match value {
+        Value::Int(value, _) if tp.is_integer() => Value::Int(tp.wrap(value), tp.clone()),
+        value => value,
+    }
+}
+
+///converts the value of a cast, integers are truncated or sign extended like in `TypeAST::wrap`
+///and every value other than 0 is true
+fn cast(value: Value, target: &TypeAST) -> Value {
+    let n = match value {
+        Value::Int(n, _) => n,
+        Value::Bool(b) => b as i64,
+        Value::Char(c) => c as i64,
+        _ => unreachable!("the typechecker only allows casting scalars"),
+    };
+    match target {
+        TypeAST::Bool => Value::Bool(n != 0),
+        TypeAST::Char => Value::Char(n as u8),
+        tp => Value::Int(tp.wrap(n), tp.clone()),
+    }
+}
+
+///result of executing a statement
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+///a function with the functions visible where it is defined
+struct FnDef<'a> {
+    func: &'a FunctionAST,
+    functions: Vec<(String, String)>,
+}
+
+///the variables of a running function
+struct Frame {
+    label: String,
+    //inner scopes are at the end
+    scopes: Vec<Vec<(String, Value)>>,
+    //visible functions with their labels, inner definitions are at the end
+    functions: Vec<(String, String)>,
+    //frame of the enclosing function, used to access its variables
+    parent: Option<usize>,
+    //counts the calls, identifies the frame
+    call: usize,
+}
+
+pub struct Interpreter<'a> {
+    defs: HashMap<String, FnDef<'a>>,
+    structs: HashMap<String, &'a StructAST>,
+    enums: HashMap<String, &'a EnumAST>,
+    frames: Vec<Frame>,
+    calls: usize,
+    pub output: Vec<u8>,
+}
+
+fn runtime_error(code: &'static str, message: impl Into<String>, span: Span) -> Diagnostic {
+    Diagnostic::error(code, message, span)
+}
+
+///the location a pointer points to, errors for the null pointer
+fn deref(pointer: Value, span: Span) -> Result<Location, Diagnostic> {
+    match pointer {
+        Value::Pointer(Some(location)) => Ok(location),
+        Value::Pointer(None) => Err(runtime_error(
+            diagnostics::INVALID_POINTER,
+            "dereferenced a null pointer",
+            span,
+        )
+        .with_note("the pointer was declared but never assigned")),
+        _ => unreachable!("the typechecker only allows dereferencing pointers"),
+    }
+}
+
+fn outside_of_array(span: Span) -> Diagnostic {
+    runtime_error(
+        diagnostics::INVALID_POINTER,
+        "pointer arithmetic outside of an array",
+        span,
+    )
+    .with_note("only pointers to elements of arrays can be moved")
+}
+
+///the element at index i, errors if it is out of bounds
+fn element(elements: &mut [Value], i: i64, span: Span) -> Result<&mut Value, Diagnostic> {
+    let len = elements.len();
+    usize::try_from(i)
+        .ok()
+        .and_then(|i| elements.get_mut(i))
+        .ok_or_else(|| {
+            runtime_error(
+                diagnostics::INDEX_OUT_OF_BOUNDS,
+                format!(
+                    "index out of bounds: the length is {} but the index is {}",
+                    len, i
+                ),
+                span,
+            )
+        })
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new() -> Self {
+        Self {
+            defs: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            frames: Vec::new(),
+            calls: 0,
+            output: Vec::new(),
+        }
+    }
+
+    ///value of a declared variable that was not assigned yet
+    fn zero(&self, tp: &TypeAST) -> Value {
+        match tp {
+            TypeAST::Bool => Value::Bool(false),
+            TypeAST::Str => Value::Str(String::new()),
+            TypeAST::Char => Value::Char(0),
+            TypeAST::Array(element, len) => Value::Array(vec![self.zero(element); *len as usize]),
+            TypeAST::Pointer(_) => Value::Pointer(None),
+            TypeAST::Custom(name) if self.enums.contains_key(name) => {
+                Value::Int(0, self.enums[name].repr())
+            }
+            TypeAST::Custom(name) => Value::Struct(
+                self.structs[name]
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.clone(), self.zero(&field.field_type)))
+                    .collect(),
+            ),
+            tp if tp.is_integer() => Value::Int(0, tp.clone()),
+            _ => Value::Void,
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no running function")
+    }
+
+    ///makes the functions defined directly in body visible, labels are prefixed like in codegen
+    fn add_functions(&mut self, body: &'a BodyAST, prefix: &str) {
+        let funcs: Vec<&FunctionAST> = body
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                StmtAST::Function(func) => Some(func),
+                _ => None,
+            })
+            .collect();
+        if funcs.is_empty() {
+            return;
+        }
+        //nested functions can be shadowed, so their labels contain their id
+        let labels: Vec<String> = funcs
+            .iter()
+            .map(|func| {
+                let name = &func.fn_signt.name;
+                if prefix.is_empty() {
+                    return name.clone();
+                }
+                let id = func
+                    .fn_signt
+                    .id
+                    .expect("the typechecker gave every function an id");
+                format!("{}{}_{}", prefix, name, id.0)
+            })
+            .collect();
+        let frame = self.frame();
+        for (func, label) in funcs.iter().zip(&labels) {
+            frame
+                .functions
+                .push((func.fn_signt.name.clone(), label.clone()));
+        }
+        let functions = frame.functions.clone();
+        for (func, label) in funcs.into_iter().zip(labels) {
+            self.defs.insert(
+                label,
+                FnDef {
+                    func,
+                    functions: functions.clone(),
+                },
+            );
+        }
+    }
+
+    ///finds the variable in the current function or in the functions it is nested in
+    fn variable(&mut self, name: &str) -> &mut Value {
+        let (frame, scope, var) = self.find_variable(name);
+        &mut self.frames[frame].scopes[scope][var].1
+    }
+
+    ///the frame, scope and position of a variable
+    fn find_variable(&self, name: &str) -> (usize, usize, usize) {
+        let mut frame = self.frames.len() - 1;
+        loop {
+            let found =
+                self.frames[frame]
+                    .scopes
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .find_map(|(i, scope)| {
+                        let var = scope.iter().rposition(|(var, _)| var == name);
+                        var.map(|var| (i, var))
+                    });
+            if let Some((scope, var)) = found {
+                return (frame, scope, var);
+            }
+            frame = self.frames[frame]
+                .parent
+                .expect("the typechecker resolved every variable");
+        }
+    }
+
+    ///the location of a place, the indices are evaluated from the innermost one outwards
+    fn location(&mut self, place: &ExprAST) -> Result<Location, Diagnostic> {
+        match place {
+            ExprAST::Variable(var) => {
+                let (frame, scope, var) = self.find_variable(&var.name);
+                Ok(Location {
+                    frame,
+                    call: self.frames[frame].call,
+                    scope,
+                    var,
+                    path: Vec::new(),
+                })
+            }
+            ExprAST::FieldAccess(access) => {
+                let mut location = self.location(&access.expr)?;
+                //fields of a struct behind a pointer are accessed through the pointer
+                let value = self.pointee(&location, access.span)?;
+                if let Value::Pointer(_) = value {
+                    location = deref(value.clone(), access.span)?;
+                }
+                let Value::Struct(fields) = self.pointee(&location, access.span)? else {
+                    unreachable!("the typechecker only allows fields of structs")
+                };
+                let field = fields
+                    .iter()
+                    .position(|(name, _)| *name == access.field)
+                    .expect("the typechecker resolved every field");
+                location.path.push(field as i64);
+                Ok(location)
+            }
+            ExprAST::Index(index) => {
+                let mut location = self.location(&index.expr)?;
+                let i = self.eval(&index.index)?.exit_code();
+                location.path.push(i);
+                Ok(location)
+            }
+            ExprAST::Unary(unary) => deref(self.eval(&unary.expr)?, unary.span),
+            _ => unreachable!("the typechecker only allows places"),
+        }
+    }
+
+    ///the value at a location, errors if the function of the variable returned or an element is
+    ///out of bounds
+    fn pointee(&mut self, location: &Location, span: Span) -> Result<&mut Value, Diagnostic> {
+        let mut value = self
+            .frames
+            .get_mut(location.frame)
+            .filter(|frame| frame.call == location.call)
+            .and_then(|frame| frame.scopes.get_mut(location.scope))
+            .and_then(|scope| scope.get_mut(location.var))
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                runtime_error(
+                    diagnostics::INVALID_POINTER,
+                    "dereferenced a dangling pointer",
+                    span,
+                )
+                .with_note("the variable it points to went out of scope")
+            })?;
+        for &i in &location.path {
+            value = match value {
+                Value::Struct(fields) => &mut fields[i as usize].1,
+                Value::Array(elements) => element(elements, i, span)?,
+                _ => unreachable!("locations only lead into structs and arrays"),
+            };
+        }
+        Ok(value)
+    }
+
+    ///moves the pointer by n elements of the array it points into
+    fn offset(&mut self, pointer: Value, n: i64, span: Span) -> Result<Value, Diagnostic> {
+        let mut location = deref(pointer, span)?;
+        if n == 0 {
+            return Ok(Value::Pointer(Some(location)));
+        }
+        let Some(i) = location.path.pop() else {
+            return Err(outside_of_array(span));
+        };
+        if !matches!(self.pointee(&location, span)?, Value::Array(_)) {
+            return Err(outside_of_array(span));
+        }
+        location.path.push(i + n);
+        Ok(Value::Pointer(Some(location)))
+    }
+
+    fn struct_literal(&mut self, lit: &StructLiteralAST) -> Result<Value, Diagnostic> {
+        //the values are evaluated in the order they are written
+        let mut values = Vec::new();
+        for init in &lit.fields {
+            values.push((init.name.as_str(), self.eval(&init.value)?));
+        }
+        let def = self.structs[&lit.name];
+        let fields = def
+            .fields
+            .iter()
+            .map(|field| {
+                let i = values
+                    .iter()
+                    .position(|(name, _)| *name == field.name)
+                    .expect("the typechecker checked that every field is initialized");
+                let value = values.swap_remove(i).1;
+                (field.name.clone(), convert(value, &field.field_type))
+            })
+            .collect();
+        Ok(Value::Struct(fields))
+    }
+
+    fn call(&mut self, call: &CallAST) -> Result<Value, Diagnostic> {
+        let mut args = Vec::new();
+        for arg in &call.args {
+            args.push(self.eval(arg)?);
+        }
+        let label = self
+            .frame()
+            .functions
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == call.callee)
+            .map(|(_, label)| label.clone())
+            .expect("the typechecker resolved every call");
+        self.call_label(&label, args, call.span)
+    }
+
+    fn call_label(
+        &mut self,
+        label: &str,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, Diagnostic> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(runtime_error(
+                diagnostics::STACK_OVERFLOW,
+                format!("more than {} nested calls", MAX_CALL_DEPTH),
+                span,
+            ));
+        }
+        let def = &self.defs[label];
+        let func = def.func;
+        //the enclosing function is the closest frame on the chain of the caller that runs it
+        let parent_label = label.rsplit_once('.').map_or("", |(parent, _)| parent);
+        let mut parent = self.frames.len().checked_sub(1);
+        while let Some(frame) = parent {
+            if self.frames[frame].label == parent_label {
+                break;
+            }
+            parent = self.frames[frame].parent;
+        }
+
+        let scope = func
+            .fn_signt
+            .args
+            .iter()
+            .zip(args)
+            .map(|(arg, value)| (arg.name.clone(), convert(value, &arg.var_type)))
+            .collect();
+        self.calls += 1;
+        self.frames.push(Frame {
+            label: label.to_string(),
+            scopes: vec![scope],
+            functions: def.functions.clone(),
+            parent,
+            call: self.calls,
+        });
+        let flow = self.exec_body(&func.body);
+        self.frames.pop();
+        match flow? {
+            Flow::Return(value) => Ok(convert(value, &func.fn_signt.rt_type)),
+            //break and continue can't leave a function
+            Flow::Next | Flow::Break | Flow::Continue => Ok(Value::Void),
+        }
+    }
+
+    fn eval_binary(&mut self, expr: &BinaryExpressionAST) -> Result<Value, Diagnostic> {
+        let lhs = self.eval(&expr.lhs)?;
+        let rhs = self.eval(&expr.rhs)?;
+        let value = match (&expr.op, lhs, rhs) {
+            (Token::Equal, lhs, rhs) => Value::Bool(lhs == rhs),
+            (Token::Unequal, lhs, rhs) => Value::Bool(lhs != rhs),
+            (Token::Plus, Value::Str(lhs), Value::Str(rhs)) => Value::Str(lhs + &rhs),
+            (Token::AndBool, Value::Bool(lhs), Value::Bool(rhs)) => Value::Bool(lhs && rhs),
+            (Token::OrBool, Value::Bool(lhs), Value::Bool(rhs)) => Value::Bool(lhs || rhs),
+            (Token::XorBool, Value::Bool(lhs), Value::Bool(rhs)) => Value::Bool(lhs ^ rhs),
+            (Token::LessThan, Value::Int(lhs, _), Value::Int(rhs, _)) => Value::Bool(lhs < rhs),
+            (Token::GreaterThan, Value::Int(lhs, _), Value::Int(rhs, _)) => Value::Bool(lhs > rhs),
+            (Token::LessThan, Value::Pointer(lhs), Value::Pointer(rhs)) => Value::Bool(lhs < rhs),
+            (Token::GreaterThan, Value::Pointer(lhs), Value::Pointer(rhs)) => {
+                Value::Bool(lhs > rhs)
+            }
+            (Token::Plus, pointer @ Value::Pointer(_), Value::Int(n, _))
+            | (Token::Plus, Value::Int(n, _), pointer @ Value::Pointer(_)) => {
+                self.offset(pointer, n, expr.span)?
+            }
+            (Token::Minus, pointer @ Value::Pointer(_), Value::Int(n, _)) => {
+                self.offset(pointer, -n, expr.span)?
+            }
+            (op, Value::Int(lhs, tp), Value::Int(rhs, _)) => {
+                let bits = tp.int_bits().unwrap_or(64) as i64;
+                let result = match op {
+                    Token::Plus => lhs.wrapping_add(rhs),
+                    Token::Minus => lhs.wrapping_sub(rhs),
+                    Token::Mult => lhs.wrapping_mul(rhs),
+                    Token::Divide if rhs == 0 => {
+                        return Err(runtime_error(
+                            diagnostics::DIVISION_BY_ZERO,
+                            "attempt to divide by zero",
+                            expr.span,
+                        ))
+                    }
+                    Token::Divide => lhs.wrapping_div(rhs),
+                    Token::AndInt => lhs & rhs,
+                    Token::OrInt => lhs | rhs,
+                    Token::XorInt => lhs ^ rhs,
+                    //the shift amount wraps around at the width of the type
+                    Token::LeftShift => lhs << (rhs & (bits - 1)),
+                    Token::RightShift => lhs >> (rhs & (bits - 1)),
+                    other => return Err(unsupported(other, expr.span)),
+                };
+                Value::Int(tp.wrap(result), tp)
+            }
+            (other, _, _) => return Err(unsupported(other, expr.span)),
+        };
+        Ok(value)
+    }
+
+    fn eval(&mut self, expr: &ExprAST) -> Result<Value, Diagnostic> {
+        let value = match expr {
+            ExprAST::Variable(var) => self.variable(&var.name).clone(),
+            ExprAST::Call(call) => self.call(call)?,
+            ExprAST::BinaryExpression(bin_expr) => self.eval_binary(bin_expr)?,
+            ExprAST::Number(num) => Value::Int(num.num, num.tp.clone()),
+            ExprAST::StringLiteral(lit) => Value::Str(lit.str.clone()),
+            ExprAST::BoolLiteral(bl) => Value::Bool(bl.value),
+            ExprAST::CharLiteral(ch) => Value::Char(ch.value),
+            ExprAST::StructLiteral(lit) => self.struct_literal(lit)?,
+            ExprAST::FieldAccess(access) => match self.eval(&access.expr)? {
+                //fields of a struct behind a pointer are read through the pointer
+                pointer @ Value::Pointer(_) => {
+                    let location = deref(pointer, access.span)?;
+                    let Value::Struct(fields) = self.pointee(&location, access.span)? else {
+                        unreachable!("the typechecker only allows fields of structs")
+                    };
+                    fields
+                        .iter()
+                        .find(|(name, _)| *name == access.field)
+                        .map(|(_, value)| value.clone())
+                        .expect("the typechecker resolved every field")
+                }
+                Value::Struct(fields) => fields
+                    .into_iter()
+                    .find(|(name, _)| *name == access.field)
+                    .map(|(_, value)| value)
+                    .expect("the typechecker resolved every field"),
+                _ => unreachable!("the typechecker only allows fields of structs"),
+            },
+            ExprAST::ArrayLiteral(lit) => {
+                let mut elements = Vec::new();
+                for element in &lit.elements {
+                    elements.push(self.eval(element)?);
+                }
+                Value::Array(elements)
+            }
+            ExprAST::ArrayRepeat(lit) => {
+                let value = self.eval(&lit.value)?;
+                Value::Array(vec![value; lit.len.num as usize])
+            }
+            ExprAST::Index(index) => {
+                let Value::Array(mut elements) = self.eval(&index.expr)? else {
+                    unreachable!("the typechecker only allows indexing arrays")
+                };
+                let i = self.eval(&index.index)?.exit_code();
+                element(&mut elements, i, index.span)?.clone()
+            }
+            ExprAST::Cast(expr) => cast(self.eval(&expr.expr)?, &expr.target),
+            ExprAST::Unary(unary) if unary.op == Token::AndInt => {
+                Value::Pointer(Some(self.location(&unary.expr)?))
+            }
+            ExprAST::Unary(unary) if unary.op == Token::Minus => {
+                let Value::Int(n, tp) = self.eval(&unary.expr)? else {
+                    unreachable!("the typechecker only allows negating integers")
+                };
+                Value::Int(tp.wrap(n.wrapping_neg()), tp)
+            }
+            ExprAST::Unary(unary) => {
+                let location = deref(self.eval(&unary.expr)?, unary.span)?;
+                self.pointee(&location, unary.span)?.clone()
+            }
+            ExprAST::EnumVariant(variant) => {
+                let def = self.enums[&variant.enum_name];
+                let value = def
+                    .discriminant(&variant.variant)
+                    .expect("the typechecker resolved every variant");
+                Value::Int(value, def.repr())
+            }
+        };
+        Ok(value)
+    }
+
+    ///true if the matched value, given as its integer value, is matched by the pattern
+    fn matches(&self, pattern: &PatternAST, value: i64) -> bool {
+        match pattern {
+            PatternAST::Variant(variant) => {
+                self.enums[&variant.enum_name].discriminant(&variant.variant) == Some(value)
+            }
+            PatternAST::Number(num) => num.num == value,
+            PatternAST::Char(ch) => ch.value as i64 == value,
+            PatternAST::Bool(bl) => bl.value as i64 == value,
+            PatternAST::Wildcard(_) => true,
+        }
+    }
+
+    ///runs the first arm with a pattern that matches the value
+    fn exec_match(&mut self, match_st: &'a MatchStmtAST) -> Result<Flow, Diagnostic> {
+        let value = match self.eval(&match_st.scrutinee)? {
+            Value::Int(value, _) => value,
+            Value::Char(value) => value as i64,
+            Value::Bool(value) => value as i64,
+            _ => unreachable!("the typechecker only allows matching on scalars"),
+        };
+        let arm = match_st.arms.iter().find(|arm| {
+            arm.patterns
+                .iter()
+                .any(|pattern| self.matches(pattern, value))
+        });
+        match arm {
+            Some(arm) => self.exec_body(&arm.body),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    fn print(&mut self, print: &PrintStmtAST) -> Result<(), Diagnostic> {
+        let text = match self.eval(&print.expr)? {
+            //unsigned values are kept wrapped to their width, so they are never negative
+            Value::Int(value, _) => value.to_string(),
+            Value::Bool(value) => value.to_string(),
+            Value::Str(value) => value,
+            Value::Char(value) => {
+                self.output.push(value);
+                return Ok(());
+            }
+            //the typechecker rejects printing anything else
+            Value::Struct(_) | Value::Array(_) | Value::Pointer(_) | Value::Void => String::new(),
+        };
+        self.output.extend(text.bytes());
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        let frame = self.frame();
+        frame
+            .scopes
+            .last_mut()
+            .expect("functions have a scope")
+            .push((name.to_string(), value));
+    }
+
+    fn exec_body(&mut self, body: &'a BodyAST) -> Result<Flow, Diagnostic> {
+        let prefix = format!("{}.", self.frame().label);
+        let fn_scope = self.frame().functions.len();
+        self.frame().scopes.push(Vec::new());
+        self.add_functions(body, &prefix);
+        let flow = self.exec_stmts(body);
+        let frame = self.frame();
+        frame.scopes.pop();
+        frame.functions.truncate(fn_scope);
+        flow
+    }
+
+    ///runs the first branch of the else if chain whose condition is true
+    fn exec_if(&mut self, if_st: &'a IfStmtAST) -> Result<Flow, Diagnostic> {
+        if self.eval(&if_st.condition)? == Value::Bool(true) {
+            return self.exec_body(&if_st.body);
+        }
+        match &if_st.else_branch {
+            Some(ElseAST::If(else_if)) => self.exec_if(else_if),
+            Some(ElseAST::Body(body)) => self.exec_body(body),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    fn exec_stmts(&mut self, body: &'a BodyAST) -> Result<Flow, Diagnostic> {
+        for stmt in &body.stmts {
+            match stmt {
+                StmtAST::Declaration(decl) => {
+                    let value = self.zero(&decl.var_type);
+                    self.declare(&decl.name, value);
+                }
+                StmtAST::DeclAssign(declassg) => {
+                    let value = self.eval(&declassg.value)?;
+                    let value = convert(value, &declassg.decl.var_type);
+                    self.declare(&declassg.decl.name, value);
+                }
+                StmtAST::Assign(assign) => {
+                    let value = self.eval(&assign.value)?;
+                    let location = self.location(&assign.target)?;
+                    let var = self.pointee(&location, assign.target.span())?;
+                    *var = match var {
+                        Value::Int(_, tp) => convert(value, &tp.clone()),
+                        _ => value,
+                    };
+                }
+                StmtAST::Call(call) => {
+                    self.call(call)?;
+                }
+                //made visible when the body was entered
+                StmtAST::Function(_) => {}
+                //collected before main is called
+                StmtAST::Struct(_) | StmtAST::Enum(_) | StmtAST::Const(_) => {}
+                StmtAST::Return(rt) => return Ok(Flow::Return(self.eval(&rt.expr)?)),
+                StmtAST::If(if_st) => match self.exec_if(if_st)? {
+                    Flow::Next => {}
+                    flow => return Ok(flow),
+                },
+                StmtAST::Match(match_st) => match self.exec_match(match_st)? {
+                    Flow::Next => {}
+                    flow => return Ok(flow),
+                },
+                StmtAST::While(while_st) => {
+                    while self.eval(&while_st.condition)? == Value::Bool(true) {
+                        match self.exec_body(&while_st.body)? {
+                            Flow::Next | Flow::Continue => {}
+                            Flow::Break => break,
+                            flow @ Flow::Return(_) => return Ok(flow),
+                        }
+                    }
+                }
+                StmtAST::Print(print) => self.print(print)?,
+                StmtAST::Break(_) => return Ok(Flow::Break),
+                StmtAST::Continue(_) => return Ok(Flow::Continue),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    ///runs main and returns its return value
+    pub fn run(&mut self, program: &'a BodyAST) -> Result<Value, Diagnostic> {
+        std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || self.run_main(program))
+                .expect("can't start the interpreter")
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    fn run_main(&mut self, program: &'a BodyAST) -> Result<Value, Diagnostic> {
+        //the program itself is the outermost frame, it holds the top level functions, constants and
+        //global variables
+        self.frames.push(Frame {
+            label: String::new(),
+            scopes: vec![Vec::new()],
+            functions: Vec::new(),
+            parent: None,
+            call: self.calls,
+        });
+        self.add_functions(program, "");
+        for stmt in &program.stmts {
+            match stmt {
+                StmtAST::Struct(def) => {
+                    self.structs.insert(def.name.clone(), def);
+                }
+                StmtAST::Enum(def) => {
+                    self.enums.insert(def.name.clone(), def);
+                }
+                _ => {}
+            }
+        }
+        //the typechecker evaluated their values and placed them before the functions
+        for stmt in &program.stmts {
+            if let StmtAST::Const(global) | StmtAST::DeclAssign(global) = stmt {
+                let value = self.eval(&global.value)?;
+                let value = convert(value, &global.decl.var_type);
+                self.declare(&global.decl.name, value);
+            }
+        }
+        if !self.defs.contains_key("main") {
+            return Err(Diagnostic::error(
+                diagnostics::MISSING_MAIN,
+                "`main` function not found",
+                program.span,
+            ));
+        }
+        self.call_label("main", Vec::new(), program.span)
+    }
+}
+
+fn unsupported(op: &Token, span: Span) -> Diagnostic {
+    runtime_error(
+        diagnostics::UNSUPPORTED_OPERATOR,
+        format!("the operator `{}` can't be evaluated", op),
+        span,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegeneration::typechecks::Typechecker;
+    use crate::parser::{lexer::Lexer, parser::Parser};
+
+    fn run(program: &str) -> Result<Value, Diagnostic> {
+        let (body, errors) = Parser::new(Lexer::new(program.into())).parse();
+        assert!(errors.is_empty());
+        let (checked, errors) = Typechecker::new(body, None, TypeAST::Void).check_types();
+        assert!(errors.is_empty());
+        Interpreter::new().run(&checked)
+    }
+
+    #[test]
+    fn test_wrapping() {
+        assert_eq!(TypeAST::I8.wrap(200), -56);
+        assert_eq!(TypeAST::U16.wrap(-1), 0xffff);
+        assert_eq!(TypeAST::U32.wrap(1 << 32), 0);
+        let result = run("fn sub(a: i8, b: i8) -> i8 {
+                return a - b;
+            }
+            fn main() -> i8 {
+                let a: i8 = 100;
+                return a + a + sub(-100, 100);
+            }");
+        //100 + 100 wraps to -56, -100 - 100 wraps to 56
+        assert_eq!(result, Ok(Value::Int(0, TypeAST::I8)));
+    }
+
+    #[test]
+    fn test_calls_and_nesting() {
+        let result = run("fn main() -> str {
+                let a: i8 = 3;
+                fn count(n: i8) -> i8 {
+                    if n == 0 {
+                        return a;
+                    }
+                    return count(n - 1) + 1;
+                }
+                if count(2) > 4 {
+                    return \"big\" + \"!\";
+                }
+                return \"small\";
+            }");
+        assert_eq!(result, Ok(Value::Str("big!".to_string())));
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let result = run("fn main() -> i8 {
+                let a: i8 = 0;
+                return 1 / a;
+            }");
+        assert_eq!(result.unwrap_err().code, diagnostics::DIVISION_BY_ZERO);
+        let result = run("fn main() -> i8 {
+                return main();
+            }");
+        assert_eq!(result.unwrap_err().code, diagnostics::STACK_OVERFLOW);
+        let result = run("fn main() -> i8 {
+                let mut a: [i8; 2] = [0; 2];
+                let i: i8 = 2;
+                a[i] = 1;
+                return a[0];
+            }");
+        assert_eq!(result.unwrap_err().code, diagnostics::INDEX_OUT_OF_BOUNDS);
+        let result = run("fn main() -> i8 {
+                let mut p: *i8;
+                let q: **i8 = &p;
+                return **q;
+            }");
+        assert_eq!(result.unwrap_err().message, "dereferenced a null pointer");
+        let result = run("fn local() -> *i8 {
+                let x: i8 = 1;
+                return &x;
+            }
+            fn main() -> i8 {
+                return *local();
+            }");
+        assert_eq!(
+            result.unwrap_err().message,
+            "dereferenced a dangling pointer"
+        );
+        let result = run("fn main() -> i8 {
+                let x: i8 = 1;
+                let p: *i8 = &x + 1;
+                return 0;
+            }");
+        assert_eq!(result.unwrap_err().code, diagnostics::INVALID_POINTER);
+    }
+}
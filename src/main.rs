@@ -1,28 +1,283 @@
+//diagnostics are returned by value through the whole front end
+#![allow(clippy::result_large_err)]
+
 mod codegeneration;
+mod diagnostics;
+mod emulator;
+mod interpreter;
 mod parser;
+mod schematic;
 
-use crate::parser::ast::TypeAST;
+use crate::diagnostics::Reporter;
+use crate::parser::ast::{BodyAST, TypeAST};
 use crate::parser::lexer::Lexer;
+use codegeneration::assembler::{self, Assembly};
+use codegeneration::codegen::Codegen;
+use codegeneration::target::Target;
 use codegeneration::typechecks::Typechecker;
+use emulator::Emulator;
+use interpreter::Interpreter;
 use parser::parser::Parser;
+use schematic::{RomLayout, STRUCTURE_BLOCK_LIMIT};
 
 use std::env;
 use std::fs;
+use std::io::Write;
+use std::process;
 
-fn main() {
-    //get path from env
-    let path = env::args().nth(1).expect("no path provided");
-    //read program file
-    let program = fs::read(path).expect("can't read program");
+const USAGE: &str = "usage: MyLang [check|run|asm|build|assemble|emulate] <program> [-o <output>] \
+[--target <cpu.target>] [--format bin|hex|listing|structure|schematic] \
+[--rom-layout <rom.layout>] [--max-cycles <n>] [--default-int u8|u16|u32|i8|i16|i32]";
+
+//instructions the emulator executes before it gives up on a program
+const DEFAULT_MAX_CYCLES: u64 = 10_000_000;
+
+struct Options {
+    mode: String,
+    path: String,
+    output: Option<String>,
+    //target description file, the bundled mcpu32 is used without one
+    target: Option<String>,
+    //output format of build and assemble
+    format: String,
+    max_cycles: u64,
+    //rom layout of the structure and schematic formats, the bundled one is used without one
+    rom_layout: Option<String>,
+    //type of integer literals that are not constrained by their context
+    default_int: TypeAST,
+}
+
+///the integer type called name, exits with the usage if there is none
+fn parse_int_type(name: Option<String>) -> TypeAST {
+    match name.as_deref() {
+        Some("u8") => TypeAST::U8,
+        Some("u16") => TypeAST::U16,
+        Some("u32") => TypeAST::U32,
+        Some("i8") => TypeAST::I8,
+        Some("i16") => TypeAST::I16,
+        Some("i32") => TypeAST::I32,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_args() -> Options {
+    let mut args = env::args().skip(1);
+    let mut positional = Vec::new();
+    let mut output = None;
+    let mut target = None;
+    let mut format = "bin".to_string();
+    let mut max_cycles = DEFAULT_MAX_CYCLES;
+    let mut rom_layout = None;
+    let mut default_int = TypeAST::I32;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = Some(args.next().expect(USAGE)),
+            "--target" => target = Some(args.next().expect(USAGE)),
+            "--format" => format = args.next().expect(USAGE),
+            "--rom-layout" => rom_layout = Some(args.next().expect(USAGE)),
+            "--max-cycles" => max_cycles = args.next().and_then(|n| n.parse().ok()).expect(USAGE),
+            "--default-int" => default_int = parse_int_type(args.next()),
+            _ => positional.push(arg),
+        }
+    }
+    //only the path means check
+    let (mode, path) = match positional.as_slice() {
+        [path] => ("check".to_string(), path.clone()),
+        [mode, path] => (mode.clone(), path.clone()),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    Options {
+        mode,
+        path,
+        output,
+        target,
+        format,
+        max_cycles,
+        rom_layout,
+        default_int,
+    }
+}
+
+///writes to the output file or to stdout if there is none
+fn write_output(output: &Option<String>, content: &[u8]) {
+    match output {
+        Some(path) => fs::write(path, content).expect("can't write output"),
+        None => std::io::stdout()
+            .write_all(content)
+            .expect("can't write output"),
+    }
+}
+
+///assembles the program, exits after printing the errors if there are any
+fn assemble(asm: &str, target: &Target) -> Assembly {
+    assembler::assemble(asm, target).unwrap_or_else(|errors| {
+        for err in errors {
+            eprintln!("error: {}", err);
+        }
+        process::exit(1);
+    })
+}
+
+///assembles the source and writes it in the requested format
+fn assemble_output(asm: &str, target: &Target, options: &Options) {
+    let assembly = assemble(asm, target);
+    let content = match options.format.as_str() {
+        "bin" => assembly.image,
+        "hex" => assembly.hex_dump().into_bytes(),
+        "listing" => assembly.listing().into_bytes(),
+        "structure" | "schematic" => {
+            let layout = match &options.rom_layout {
+                Some(path) => RomLayout::load(path),
+                None => Ok(RomLayout::default()),
+            };
+            let rom = layout.and_then(|layout| match options.format.as_str() {
+                "structure" => {
+                    warn_structure_size(layout.size(&assembly.image)?);
+                    layout.structure(&assembly.image)
+                }
+                _ => layout.schematic(&assembly.image),
+            });
+            rom.unwrap_or_else(|err| {
+                eprintln!("error: invalid rom layout: {}", err);
+                process::exit(1);
+            })
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    write_output(&options.output, &content);
+}
+
+///structure blocks refuse larger structures, but `/place template` still loads them
+fn warn_structure_size(size: [i32; 3]) {
+    if size.iter().any(|len| *len > STRUCTURE_BLOCK_LIMIT) {
+        eprintln!(
+            "warning: the rom is {}x{}x{} blocks, a structure block loads at most {} blocks along \
+            each axis, load it with `/place template` instead",
+            size[0], size[1], size[2], STRUCTURE_BLOCK_LIMIT
+        );
+    }
+}
+
+fn load_target(path: &Option<String>) -> Target {
+    let Some(path) = path else {
+        return Target::default();
+    };
+    Target::load(path).unwrap_or_else(|err| {
+        eprintln!("error: invalid target {}: {}", path, err);
+        process::exit(1);
+    })
+}
+
+///parses and typechecks the program, exits after printing the errors if there are any
+fn check(program: &[u8], reporter: &Reporter, options: &Options, print_ast: bool) -> BodyAST {
     //build the lexer
-    let lexer = Lexer::new(program);
+    let lexer = Lexer::new(program.to_vec());
     //build parser
     let mut pars = Parser::new(lexer);
     //run the parser
-    let parsed = pars.parse();
-    println!("{:?}", parsed);
+    let (parsed, errors) = pars.parse();
+    if !errors.is_empty() {
+        reporter.emit(&errors);
+        process::exit(1);
+    }
+    if print_ast {
+        println!("{:?}", parsed);
+    }
     //run typechecks
-    Typechecker::new(parsed, None, None, TypeAST::Void).check_types();
+    let (checked, errors) = Typechecker::new(parsed, None, TypeAST::Void)
+        .with_default_int(options.default_int.clone())
+        .check_types();
+    //warnings are printed but do not stop the compilation
+    if reporter.emit(&errors) > 0 {
+        process::exit(1);
+    }
+    checked
+}
+
+///generates the assembly for the program, exits after printing the errors if there are any
+fn compile(program: &[u8], reporter: &Reporter, options: &Options, target: &Target) -> String {
+    let checked = check(program, reporter, options, false);
+    let (asm, errors) = Codegen::new(target).generate(&checked);
+    if !errors.is_empty() {
+        reporter.emit(&errors);
+        process::exit(1);
+    }
+    asm
+}
+
+fn main() {
+    let options = parse_args();
+    //read program file
+    let program = fs::read(&options.path).expect("can't read program");
+    let reporter = Reporter::new(&options.path, &program);
+    match options.mode.as_str() {
+        "check" => {
+            check(&program, &reporter, &options, true);
+        }
+        //interprets the program, exits with the return value of main
+        "run" => {
+            let checked = check(&program, &reporter, &options, false);
+            let mut interpreter = Interpreter::new();
+            let result = interpreter.run(&checked);
+            write_output(&options.output, &interpreter.output);
+            match result {
+                Ok(value) => process::exit(value.exit_code() as i32),
+                Err(err) => {
+                    reporter.emit(&[err]);
+                    process::exit(101);
+                }
+            }
+        }
+        "asm" | "build" => {
+            let target = load_target(&options.target);
+            let asm = compile(&program, &reporter, &options, &target);
+            if options.mode == "build" {
+                assemble_output(&asm, &target, &options);
+            } else {
+                write_output(&options.output, asm.as_bytes());
+            }
+        }
+        //the program is already assembly
+        "assemble" => {
+            let target = load_target(&options.target);
+            assemble_output(&String::from_utf8_lossy(&program), &target, &options);
+        }
+        //runs a program or an assembled image, exits with the return value of main
+        "emulate" => {
+            let target = load_target(&options.target);
+            let image = if options.path.ends_with(".myla") {
+                let asm = compile(&program, &reporter, &options, &target);
+                assemble(&asm, &target).image
+            } else {
+                program.clone()
+            };
+            let result = Emulator::new(&target, &image).and_then(|mut emulator| {
+                let result = emulator.run(options.max_cycles);
+                write_output(&options.output, &emulator.output);
+                result
+            });
+            match result {
+                Ok(code) => process::exit(code as i32),
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    process::exit(101);
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -38,10 +293,12 @@ mod test {
         //build parser
         let mut pars = Parser::new(lexer);
         //run the parser
-        let parsed = pars.parse();
+        let (parsed, errors) = pars.parse();
+        assert!(errors.is_empty());
         println!("{:?}", parsed);
         //run typechecks
-        Typechecker::new(parsed, None, None, TypeAST::Void).check_types();
+        let (_, errors) = Typechecker::new(parsed, None, TypeAST::Void).check_types();
+        assert!(errors.is_empty());
     }
     #[test]
     fn test_complexe_assignment() {
@@ -53,9 +310,11 @@ mod test {
         //build parser
         let mut pars = Parser::new(lexer);
         //run the parser
-        let parsed = pars.parse();
+        let (parsed, errors) = pars.parse();
+        assert!(errors.is_empty());
         println!("{:?}", parsed);
-        //run typechecks
-        Typechecker::new(parsed, None, None, TypeAST::Void).check_types();
+        //run typechecks, the i8 variables are cast to the u8 arguments of add
+        let (_, errors) = Typechecker::new(parsed, None, TypeAST::Void).check_types();
+        assert!(errors.is_empty());
     }
 }
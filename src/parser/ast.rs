@@ -1,6 +1,12 @@
 use std::fmt::Display;
+use std::ops::RangeInclusive;
 
-use crate::parser::lexer::Token;
+use crate::parser::lexer::{Span, Token};
+
+///identifies a variable or function of the program, given out by the typechecker. ids are never
+///reused, so later passes can look symbols up by them instead of by their name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(pub usize);
 
 //{
 //  Body
@@ -8,6 +14,7 @@ use crate::parser::lexer::Token;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct BodyAST {
     pub stmts: Vec<StmtAST>,
+    pub span: Span,
 }
 
 /// code that has, returns or is a value
@@ -21,6 +28,70 @@ pub enum ExprAST {
     Number(NumberAST),
     StringLiteral(StringLiteralAST),
     BoolLiteral(BoolAST),
+    CharLiteral(CharAST),
+    StructLiteral(StructLiteralAST),
+    FieldAccess(Box<FieldAccessAST>),
+    EnumVariant(EnumVariantAST),
+    ArrayLiteral(ArrayLiteralAST),
+    ArrayRepeat(Box<ArrayRepeatAST>),
+    Index(Box<IndexAST>),
+    //&a or *p
+    Unary(Box<UnaryExpressionAST>),
+    Cast(Box<CastAST>),
+}
+
+impl ExprAST {
+    pub fn span(&self) -> Span {
+        match self {
+            ExprAST::Variable(var) => var.span,
+            ExprAST::Call(call) => call.span,
+            ExprAST::BinaryExpression(bin_expr) => bin_expr.span,
+            ExprAST::Number(num) => num.span,
+            ExprAST::StringLiteral(lit) => lit.span,
+            ExprAST::BoolLiteral(bl) => bl.span,
+            ExprAST::CharLiteral(ch) => ch.span,
+            ExprAST::StructLiteral(lit) => lit.span,
+            ExprAST::FieldAccess(access) => access.span,
+            ExprAST::EnumVariant(variant) => variant.span,
+            ExprAST::ArrayLiteral(lit) => lit.span,
+            ExprAST::ArrayRepeat(lit) => lit.span,
+            ExprAST::Index(index) => index.span,
+            ExprAST::Unary(unary) => unary.span,
+            ExprAST::Cast(cast) => cast.span,
+        }
+    }
+
+    ///the type the typechecker gave the expression, TypeAST::Undefined before it ran
+    pub fn tp(&self) -> TypeAST {
+        match self {
+            ExprAST::Variable(var) => var.tp.clone(),
+            ExprAST::Call(call) => call.tp.clone(),
+            ExprAST::BinaryExpression(bin_expr) => bin_expr.tp.clone(),
+            ExprAST::Number(num) => num.tp.clone(),
+            ExprAST::StringLiteral(_) => TypeAST::Str,
+            ExprAST::BoolLiteral(_) => TypeAST::Bool,
+            ExprAST::CharLiteral(_) => TypeAST::Char,
+            ExprAST::StructLiteral(lit) => TypeAST::Custom(lit.name.clone()),
+            ExprAST::FieldAccess(access) => access.tp.clone(),
+            ExprAST::EnumVariant(variant) => TypeAST::Custom(variant.enum_name.clone()),
+            ExprAST::ArrayLiteral(lit) => lit.tp.clone(),
+            ExprAST::ArrayRepeat(lit) => lit.tp.clone(),
+            ExprAST::Index(index) => index.tp.clone(),
+            ExprAST::Unary(unary) => unary.tp.clone(),
+            ExprAST::Cast(cast) => cast.target.clone(),
+        }
+    }
+
+    ///true if the expression names a location in memory, whose address can be taken
+    pub fn is_place(&self) -> bool {
+        match self {
+            ExprAST::Variable(_) => true,
+            ExprAST::Unary(unary) => unary.op == Token::Mult,
+            ExprAST::FieldAccess(access) => access.expr.is_place(),
+            ExprAST::Index(index) => index.expr.is_place(),
+            _ => false,
+        }
+    }
 }
 
 /// code that only moves a value
@@ -31,15 +102,125 @@ pub enum StmtAST {
     Call(CallAST),
     Declaration(DeclarationAST),
     DeclAssign(DeclAssignAST),
+    //const NAME: Type = value;, only at the top level
+    Const(DeclAssignAST),
     //function definition
     Function(FunctionAST),
+    //struct definition, only at the top level
+    Struct(StructAST),
+    //enum definition, only at the top level
+    Enum(EnumAST),
     Return(Box<ReturnStmtAST>),
     If(IfStmtAST),
+    While(WhileStmtAST),
+    Match(MatchStmtAST),
+    //the span of the keyword
+    Break(Span),
+    Continue(Span),
+    Print(PrintStmtAST),
+}
+
+impl StmtAST {
+    pub fn span(&self) -> Span {
+        match self {
+            StmtAST::Assign(ass) => ass.span,
+            StmtAST::Call(call) => call.span,
+            StmtAST::Declaration(decl) => decl.span,
+            StmtAST::DeclAssign(declassg) | StmtAST::Const(declassg) => declassg.span,
+            StmtAST::Function(func) => func.span,
+            StmtAST::Struct(struct_def) => struct_def.span,
+            StmtAST::Enum(enum_def) => enum_def.span,
+            StmtAST::Return(rtstmt) => rtstmt.span,
+            StmtAST::If(if_st) => if_st.span,
+            StmtAST::While(while_st) => while_st.span,
+            StmtAST::Match(match_st) => match_st.span,
+            StmtAST::Break(span) | StmtAST::Continue(span) => *span,
+            StmtAST::Print(print) => print.span,
+        }
+    }
 }
 
 impl Display for Token {
+    ///writes the token the way it appears in the source
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        let tk = match self {
+            Token::Declaration => "let",
+            Token::Definition => "fn",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::Return => "return",
+            Token::Struct => "struct",
+            Token::Enum => "enum",
+            Token::Match => "match",
+            Token::While => "while",
+            Token::Print => "print",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::Mut => "mut",
+            Token::Const => "const",
+            Token::Static => "static",
+            Token::As => "as",
+            Token::True => "true",
+            Token::False => "false",
+            Token::AndBool => "&&",
+            Token::AndInt => "&",
+            Token::AndIntAssign => "&=",
+            Token::Divide => "/",
+            Token::DivideAssign => "/=",
+            Token::Equal => "==",
+            Token::Assign => "=",
+            Token::GreaterThan => ">",
+            Token::LeftShift => "<<",
+            Token::LessThan => "<",
+            Token::Minus => "-",
+            Token::MinusAssign => "-=",
+            Token::Mult => "*",
+            Token::MultAssign => "*=",
+            Token::Not => "!",
+            Token::OrBool => "||",
+            Token::OrInt => "|",
+            Token::OrIntAssign => "|=",
+            Token::Plus => "+",
+            Token::PlusAssign => "+=",
+            Token::Unequal => "!=",
+            Token::RightShift => ">>",
+            Token::XorBool => "^^",
+            Token::XorInt => "^",
+            Token::XorIntAssign => "^=",
+            Token::Arrow => "->",
+            Token::Comma => ",",
+            Token::Dot => ".",
+            Token::Comment(cmt) => return write!(f, "//{}", cmt),
+            Token::Colon => ":",
+            Token::DoubleColon => "::",
+            Token::FatArrow => "=>",
+            Token::Identifier(ident) => ident,
+            Token::LeftBrace => "{",
+            Token::LeftBracket => "[",
+            Token::LeftParen => "(",
+            Token::Number(num) => return write!(f, "{}", num),
+            Token::StringLiteral(lit) => return write!(f, "\"{}\"", lit),
+            Token::CharLiteral(ch) => return write!(f, "'{}'", (*ch as char).escape_default()),
+            Token::SemiColon => ";",
+            Token::RightBrace => "}",
+            Token::RightBracket => "]",
+            Token::RightParen => ")",
+            Token::Underscore => "_",
+            Token::U8 => "u8",
+            Token::U16 => "u16",
+            Token::U32 => "u32",
+            Token::I8 => "i8",
+            Token::I16 => "i16",
+            Token::I32 => "i32",
+            Token::Str => "str",
+            Token::Char => "char",
+            Token::Void => "void",
+            Token::Bool => "bool",
+            Token::EOF => "end of file",
+            Token::Unknown => "unknown token",
+            Token::NumberOutOfRange => "number",
+        };
+        write!(f, "{}", tk)
     }
 }
 
@@ -55,14 +236,84 @@ pub enum TypeAST {
     Char,
     Void,
     Bool,
-    //Custom(String), //custom types are not yet supported
+    //a struct or enum, resolved by name
+    Custom(String),
+    //[T; N]
+    Array(Box<TypeAST>, u64),
+    //*T
+    Pointer(Box<TypeAST>),
     Undefined,
+    //type of an expression that already caused an error, it is compatible with every type so
+    //one error does not cause a chain of follow up errors
+    Error,
     //for compilation only, allows for varying interger sizes depending on expected type
+    UntypedInt,
+    //type of a declaration without annotation until it is inferred, only used by the typechecker
+    Infer(usize),
+}
+
+impl TypeAST {
+    ///true if a value of type other can be used where self is expected
+    pub fn accepts(&self, other: &TypeAST) -> bool {
+        match (self, other) {
+            (TypeAST::Array(tp, len), TypeAST::Array(other, other_len)) => {
+                len == other_len && tp.accepts(other)
+            }
+            (TypeAST::Pointer(tp), TypeAST::Pointer(other)) => tp.accepts(other),
+            _ => self == other || *self == TypeAST::Error || *other == TypeAST::Error,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            TypeAST::U8 | TypeAST::U16 | TypeAST::U32 | TypeAST::I8 | TypeAST::I16 | TypeAST::I32
+        )
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, TypeAST::I8 | TypeAST::I16 | TypeAST::I32)
+    }
+
+    ///number of bits of integer types
+    pub fn int_bits(&self) -> Option<u32> {
+        match self {
+            TypeAST::U8 | TypeAST::I8 => Some(8),
+            TypeAST::U16 | TypeAST::I16 => Some(16),
+            TypeAST::U32 | TypeAST::I32 => Some(32),
+            _ => None,
+        }
+    }
+
+    ///values an integer type can hold
+    pub fn int_range(&self) -> Option<RangeInclusive<i64>> {
+        let bits = self.int_bits()?;
+        if self.is_signed() {
+            Some(-(1 << (bits - 1))..=(1 << (bits - 1)) - 1)
+        } else {
+            Some(0..=(1 << bits) - 1)
+        }
+    }
+
+    ///wraps the value around to the width of an integer type, other values are kept
+    pub fn wrap(&self, value: i64) -> i64 {
+        let Some(bits) = self.int_bits() else {
+            return value;
+        };
+        let shift = 64 - bits;
+        if self.is_signed() {
+            (value << shift) >> shift
+        } else {
+            ((value as u64) << shift >> shift) as i64
+        }
+    }
 }
 
 impl Display for TypeAST {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let tp = match self {
+            TypeAST::Array(tp, len) => return write!(f, "[{}; {}]", tp, len),
+            TypeAST::Pointer(tp) => return write!(f, "*{}", tp),
             TypeAST::U8 => "u8",
             TypeAST::U16 => "u16",
             TypeAST::U32 => "u32",
@@ -73,8 +324,11 @@ impl Display for TypeAST {
             TypeAST::Char => "char",
             TypeAST::Void => "void",
             TypeAST::Bool => "bool",
-            //Custom(String), //custom types are not yet supported
+            TypeAST::Custom(name) => name.as_str(),
             TypeAST::Undefined => "undefined",
+            TypeAST::Error => "{error}",
+            TypeAST::UntypedInt => "{integer}",
+            TypeAST::Infer(_) => "_",
         };
         write!(f, "{}", tp)
     }
@@ -83,43 +337,305 @@ impl Display for TypeAST {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct BoolAST {
     pub value: bool,
+    pub span: Span,
+}
+
+//struct Point {
+//  x: i8,
+//  y: i8,
+//}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StructAST {
+    pub name: String,
+    pub fields: Vec<FieldAST>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldAST {
+    pub name: String,
+    pub field_type: TypeAST,
+    pub span: Span,
+}
+
+//enum Op {
+//  Add,
+//  Halt = 255,
+//}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EnumAST {
+    pub name: String,
+    pub variants: Vec<VariantAST>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VariantAST {
+    pub name: String,
+    //the explicit discriminant
+    pub value: Option<NumberAST>,
+    pub span: Span,
+}
+
+impl EnumAST {
+    ///discriminants of the variants in the order they are defined, a variant without an explicit
+    ///value takes the one after the previous variant, the first one 0
+    pub fn discriminants(&self) -> Vec<i64> {
+        let mut next = 0;
+        self.variants
+            .iter()
+            .map(|variant| {
+                let value = variant.value.as_ref().map_or(next, |num| num.num);
+                next = value.wrapping_add(1);
+                value
+            })
+            .collect()
+    }
+
+    pub fn discriminant(&self, variant: &str) -> Option<i64> {
+        let i = self.variants.iter().position(|v| v.name == variant)?;
+        Some(self.discriminants()[i])
+    }
+
+    ///the smallest integer type that holds every discriminant, values of the enum are stored
+    ///like values of this type
+    pub fn repr(&self) -> TypeAST {
+        let values = self.discriminants();
+        let min = values.iter().copied().min().unwrap_or(0);
+        let max = values.iter().copied().max().unwrap_or(0);
+        [
+            TypeAST::U8,
+            TypeAST::I8,
+            TypeAST::U16,
+            TypeAST::I16,
+            TypeAST::U32,
+            TypeAST::I32,
+        ]
+        .into_iter()
+        .find(|tp| {
+            let range = tp.int_range().expect("integer type");
+            range.contains(&min) && range.contains(&max)
+        })
+        //the typechecker reports discriminants that don't fit into any of them
+        .unwrap_or(TypeAST::I32)
+    }
+}
+
+//Op::Add
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EnumVariantAST {
+    pub enum_name: String,
+    pub variant: String,
+    pub span: Span,
+}
+
+//Point { x: 1, y: 2 }
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StructLiteralAST {
+    pub name: String,
+    //in the order they are written, not the order of the definition
+    pub fields: Vec<FieldInitAST>,
+    pub span: Span,
+}
+
+//x: 1
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldInitAST {
+    pub name: String,
+    pub value: ExprAST,
+    pub span: Span,
+}
+
+//a.b
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldAccessAST {
+    pub expr: ExprAST,
+    pub field: String,
+    pub field_span: Span,
+    ///set by the typechecker
+    pub tp: TypeAST,
+    pub span: Span,
+}
+
+//[1, 2, 3]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ArrayLiteralAST {
+    pub elements: Vec<ExprAST>,
+    ///set by the typechecker
+    pub tp: TypeAST,
+    pub span: Span,
+}
+
+//[0; 64]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ArrayRepeatAST {
+    pub value: ExprAST,
+    pub len: NumberAST,
+    ///set by the typechecker
+    pub tp: TypeAST,
+    pub span: Span,
+}
+
+//a[i]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndexAST {
+    pub expr: ExprAST,
+    pub index: ExprAST,
+    ///set by the typechecker
+    pub tp: TypeAST,
+    pub span: Span,
+}
+
+//a as u8
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CastAST {
+    pub expr: ExprAST,
+    pub target: TypeAST,
+    pub span: Span,
+}
+
+//&a takes the address of a place, *p dereferences a pointer, -a negates a signed integer
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UnaryExpressionAST {
+    //Token::AndInt, Token::Mult or Token::Minus
+    pub op: Token,
+    pub expr: ExprAST,
+    ///set by the typechecker
+    pub tp: TypeAST,
+    pub span: Span,
+}
+
+//'a'
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CharAST {
+    pub value: u8,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct IfStmtAST {
     pub condition: ExprAST,
     pub body: BodyAST,
+    pub else_branch: Option<ElseAST>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ElseAST {
+    //else if, the next link of the chain
+    If(Box<IfStmtAST>),
+    Body(BodyAST),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WhileStmtAST {
+    pub condition: ExprAST,
+    pub body: BodyAST,
+    pub span: Span,
+}
+
+//match a {
+//  Op::Add | Op::Sub => { ... }
+//  _ => { ... }
+//}
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MatchStmtAST {
+    pub scrutinee: ExprAST,
+    pub arms: Vec<MatchArmAST>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MatchArmAST {
+    //alternatives separated by `|`
+    pub patterns: Vec<PatternAST>,
+    pub body: BodyAST,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PatternAST {
+    Variant(EnumVariantAST),
+    Number(NumberAST),
+    Char(CharAST),
+    Bool(BoolAST),
+    //`_`, matches everything
+    Wildcard(Span),
+}
+
+impl PatternAST {
+    pub fn span(&self) -> Span {
+        match self {
+            PatternAST::Variant(variant) => variant.span,
+            PatternAST::Number(num) => num.span,
+            PatternAST::Char(ch) => ch.span,
+            PatternAST::Bool(bl) => bl.span,
+            PatternAST::Wildcard(span) => *span,
+        }
+    }
+}
+
+//print(a);
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrintStmtAST {
+    pub expr: ExprAST,
+    //how the value is written, decided by the typechecker
+    pub format: PrintFormat,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PrintFormat {
+    //not yet typechecked
+    Unresolved,
+    Str,
+    Char,
+    Bool,
+    Unsigned,
+    Signed,
 }
 
 /// a hardcoded integer value
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct NumberAST {
     pub num: i64,
+    ///untyped until the typechecker picks the type from the context the literal is used in
+    pub tp: TypeAST,
+    pub span: Span,
 }
 
-/// used in expressions, will be resolved by code gen
+/// used in expressions, resolved to its declaration by the typechecker
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct VariableAST {
     pub name: String,
+    pub span: Span,
+    ///the declaration, set by the typechecker
+    pub id: Option<SymbolId>,
+    ///set by the typechecker
+    pub tp: TypeAST,
 }
 
-//a = b
+//a = b, a.b = c, a[i] = d or *p = e
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct AssignStmtAST {
-    pub var: VariableAST,
+    //a variable, a dereferenced pointer or a field or element of one
+    pub target: ExprAST,
     pub value: ExprAST,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DeclAssignAST {
     pub decl: DeclarationAST,
     pub value: ExprAST,
+    pub span: Span,
 }
 
 //"some string"
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StringLiteralAST {
     pub str: String,
+    pub span: Span,
 }
 
 //fn foo(a: u8, b: u8) -> u8 {
@@ -129,6 +645,7 @@ pub struct StringLiteralAST {
 pub struct FunctionAST {
     pub fn_signt: FnSignatureAST,
     pub body: BodyAST,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -136,6 +653,9 @@ pub struct FnSignatureAST {
     pub name: String,
     pub args: Vec<DeclarationAST>,
     pub rt_type: TypeAST,
+    pub span: Span,
+    ///set by the typechecker, None for functions whose name was taken
+    pub id: Option<SymbolId>,
 }
 
 //let (mut) var: Type;
@@ -144,6 +664,9 @@ pub struct DeclarationAST {
     pub name: String,
     pub var_type: TypeAST,
     pub is_mut: bool,
+    pub span: Span,
+    ///set by the typechecker
+    pub id: Option<SymbolId>,
 }
 
 //foo(8 , 2);
@@ -152,6 +675,11 @@ pub struct CallAST {
     pub callee: String,
     pub args: Vec<ExprAST>,
     pub rt_value_ignored: bool,
+    pub span: Span,
+    ///the called function, set by the typechecker
+    pub id: Option<SymbolId>,
+    ///set by the typechecker
+    pub tp: TypeAST,
 }
 
 //a + b
@@ -160,10 +688,14 @@ pub struct BinaryExpressionAST {
     pub rhs: ExprAST,
     pub lhs: ExprAST,
     pub op: Token,
+    pub span: Span,
+    ///set by the typechecker
+    pub tp: TypeAST,
 }
 
 //return a
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ReturnStmtAST {
     pub expr: ExprAST,
+    pub span: Span,
 }
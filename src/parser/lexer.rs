@@ -1,19 +1,27 @@
-use std::usize;
+use std::fmt::Display;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Token {
     //keywords
     Declaration,
     Definition,
     If,
+    Else,
     Return,
 
-    //Struct,
-    //Enum,
+    Struct,
+    Enum,
+    Match,
     While,
     Print, //write to default io_out
     Break,
+    Continue,
     Mut,
+    Const,
+    Static,
+    //`x as u8`
+    As,
 
     // booleans
     True,
@@ -52,17 +60,24 @@ pub enum Token {
     Comma,
     Comment(String),
     Colon,
-    //Dot,
+    //`::` between an enum and its variant
+    DoubleColon,
+    Dot,
+    //`=>` between a pattern and its arm
+    FatArrow,
     Identifier(String),
     LeftBrace,
     LeftBracket,
     LeftParen,
     Number(i64),
     StringLiteral(String),
+    CharLiteral(u8),
     SemiColon,
     RightBrace,
     RightBracket,
     RightParen,
+    //the wildcard pattern
+    Underscore,
 
     //primitives
     U8,
@@ -75,13 +90,55 @@ pub enum Token {
     Char,
     Void,
     Bool,
-    //Pointer,
 
     //End of File
     EOF,
 
     //unknown
     Unknown,
+    //a number that does not fit into an i64
+    NumberOutOfRange,
+}
+
+///location of a token or ast node in the source, `start` and `end` are byte offsets into the
+///program, `line` and `col` point at the first character and are 1-based
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: u32,
+    pub col: u32,
+}
+
+impl Span {
+    ///returns a span covering everything from the start of self to the end of other
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end.max(self.end),
+            line: self.line,
+            col: self.col,
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+///a token together with the location it was read from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+impl PartialEq<Token> for SpannedToken {
+    fn eq(&self, other: &Token) -> bool {
+        self.token == *other
+    }
 }
 
 pub struct Lexer {
@@ -89,37 +146,73 @@ pub struct Lexer {
     pos: usize,
     end: usize,
     line: u32,
+    //byte offset of the first character of the current line
+    line_start: usize,
+}
+
+///the character written as `\\c` in string and char literals
+fn unescape(c: u8) -> Option<u8> {
+    match c {
+        b'n' => Some(b'\n'),
+        b't' => Some(b'\t'),
+        b'0' => Some(0),
+        b'\\' | b'\'' | b'"' => Some(c),
+        _ => None,
+    }
 }
 
 impl Lexer {
     pub fn new(program: Vec<u8>) -> Self {
         Self {
-            end: program.len() - 1,
+            end: program.len().saturating_sub(1),
             program,
             pos: 0,
             line: 1,
+            line_start: 0,
         }
     }
 
-    pub fn current_line(&self) -> u32 {
-        self.line
+    ///keeps line and column tracking in sync, has to be called for every consumed character
+    fn track_newline(&mut self, pos: usize) {
+        if self.program[pos] == b'\n' {
+            self.line += 1;
+            self.line_start = pos + 1;
+        }
     }
 
-    pub fn get_next_token(&mut self) -> Token {
-        if self.pos > self.end {
-            return Token::EOF;
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.program.len() && self.program[self.pos].is_ascii_whitespace() {
+            self.track_newline(self.pos);
+            self.pos += 1;
         }
+    }
 
-        while self.program[self.pos].is_ascii_whitespace() {
-            if self.program[self.pos] == b'\n' {
-                self.line += 1;
-            }
-            if self.pos == self.end {
-                return Token::EOF;
-            }
-            self.pos += 1
+    ///lexes the next token and returns it together with its location
+    pub fn get_next_token(&mut self) -> SpannedToken {
+        self.skip_whitespace();
+        let start = self.pos;
+        let line = self.line;
+        let col = (self.pos - self.line_start) as u32 + 1;
+
+        let token = if self.pos >= self.program.len() {
+            Token::EOF
+        } else {
+            self.lex_token()
+        };
+
+        SpannedToken {
+            token,
+            span: Span {
+                start,
+                end: self.pos,
+                line,
+                col,
+            },
         }
+    }
 
+    ///lexes the token starting at the current position, the position must not be whitespace
+    fn lex_token(&mut self) -> Token {
         let token = match self.program[self.pos] {
             b'+' => {
                 if self.pos == self.end {
@@ -163,11 +256,9 @@ impl Lexer {
 
                     let mut comment = String::new();
 
-                    while self.pos < self.end {
+                    //the newline is left for skip_whitespace so the line count stays correct
+                    while self.pos < self.end && self.program[self.pos + 1] != b'\n' {
                         self.pos += 1;
-                        if self.program[self.pos] == b'\n' {
-                            break;
-                        }
                         comment.push(self.program[self.pos] as char);
                     }
                     Token::Comment(comment)
@@ -223,13 +314,25 @@ impl Lexer {
                 } else if self.program[self.pos + 1] == b'=' {
                     self.pos += 1;
                     Token::Equal
+                } else if self.program[self.pos + 1] == b'>' {
+                    self.pos += 1;
+                    Token::FatArrow
                 } else {
                     Token::Assign
                 }
             }
             b',' => Token::Comma,
+            b'.' => Token::Dot,
             b';' => Token::SemiColon,
-            b':' => Token::Colon,
+            b':' => {
+                if self.pos < self.end && self.program[self.pos + 1] == b':' {
+                    self.pos += 1;
+                    Token::DoubleColon
+                } else {
+                    Token::Colon
+                }
+            }
+            b'_' => Token::Underscore,
             b'[' => Token::LeftBracket,
             b']' => Token::RightBracket,
             b'(' => Token::LeftParen,
@@ -268,14 +371,49 @@ impl Lexer {
             }
             b'"' => {
                 let mut literal = String::new();
+                let mut valid = true;
                 while self.pos < self.end {
                     self.pos += 1;
-                    if self.program[self.pos] == b'"' {
-                        break;
+                    match self.program[self.pos] {
+                        b'"' => break,
+                        b'\\' if self.pos < self.end => {
+                            self.pos += 1;
+                            match unescape(self.program[self.pos]) {
+                                Some(c) => literal.push(c as char),
+                                None => valid = false,
+                            }
+                        }
+                        c => {
+                            self.track_newline(self.pos);
+                            literal.push(c as char);
+                        }
+                    }
+                }
+                if valid {
+                    Token::StringLiteral(literal)
+                } else {
+                    Token::Unknown
+                }
+            }
+            b'\'' => {
+                //'c' or an escaped character like '\n'
+                let value = if self.pos + 2 <= self.end && self.program[self.pos + 1] == b'\\' {
+                    self.pos += 2;
+                    unescape(self.program[self.pos])
+                } else if self.pos < self.end {
+                    self.pos += 1;
+                    Some(self.program[self.pos])
+                } else {
+                    None
+                };
+                match value {
+                    Some(c) if self.pos < self.end && self.program[self.pos + 1] == b'\'' => {
+                        //eat the closing '
+                        self.pos += 1;
+                        Token::CharLiteral(c)
                     }
-                    literal.push(self.program[self.pos] as char);
+                    _ => Token::Unknown,
                 }
-                Token::StringLiteral(literal)
             }
             //b'%' => Token::Modulo,
 
@@ -301,13 +439,17 @@ impl Lexer {
                         "fn" => Token::Definition,
                         "while" => Token::While,
                         "if" => Token::If,
-                        //"struct" => Token::Struct,
-                        //"enum" => Token::Enum,
+                        "else" => Token::Else,
+                        "struct" => Token::Struct,
+                        "enum" => Token::Enum,
+                        "match" => Token::Match,
                         "return" => Token::Return,
                         "true" => Token::True,
                         "false" => Token::False,
                         "print" => Token::Print,
                         "break" => Token::Break,
+                        "continue" => Token::Continue,
+                        "as" => Token::As,
                         "u8" => Token::U8,
                         "u16" => Token::U16,
                         "u32" => Token::U32,
@@ -318,6 +460,8 @@ impl Lexer {
                         "void" => Token::Void,
                         "let" => Token::Declaration,
                         "mut" => Token::Mut,
+                        "const" => Token::Const,
+                        "static" => Token::Static,
                         "char" => Token::Char,
                         "bool" => Token::Bool,
 
@@ -335,7 +479,10 @@ impl Lexer {
                             break;
                         }
                     }
-                    Token::Number(num_string.parse::<i64>().expect("invalid number"))
+                    match num_string.parse::<i64>() {
+                        Ok(num) => Token::Number(num),
+                        Err(_) => Token::NumberOutOfRange,
+                    }
                 } else {
                     Token::Unknown
                 }
@@ -348,7 +495,7 @@ impl Lexer {
 
 #[cfg(test)]
 mod test {
-    use crate::parser::lexer::Token;
+    use crate::parser::lexer::{Span, Token};
 
     use super::Lexer;
 
@@ -429,6 +576,9 @@ return     0
             ^
             ==
             !=
+            =>
+            ::
+            _
         ";
         let mut lexer = Lexer::new(program.to_string().into_bytes());
         assert_eq!(lexer.get_next_token(), Token::Plus);
@@ -447,6 +597,9 @@ return     0
         assert_eq!(lexer.get_next_token(), Token::XorInt);
         assert_eq!(lexer.get_next_token(), Token::Equal);
         assert_eq!(lexer.get_next_token(), Token::Unequal);
+        assert_eq!(lexer.get_next_token(), Token::FatArrow);
+        assert_eq!(lexer.get_next_token(), Token::DoubleColon);
+        assert_eq!(lexer.get_next_token(), Token::Underscore);
     }
 
     #[test]
@@ -497,4 +650,78 @@ return     0
         assert_eq!(lexer.get_next_token(), Token::RightBrace);
         assert_eq!(lexer.get_next_token(), Token::EOF);
     }
+
+    #[test]
+    fn test_escapes() {
+        let program = r#"'a' '\'' '\0' "x\"\n" '\q' 'ab'"#;
+        let mut lexer = Lexer::new(program.to_string().into_bytes());
+        assert_eq!(lexer.get_next_token(), Token::CharLiteral(b'a'));
+        assert_eq!(lexer.get_next_token(), Token::CharLiteral(b'\''));
+        assert_eq!(lexer.get_next_token(), Token::CharLiteral(0));
+        assert_eq!(
+            lexer.get_next_token(),
+            Token::StringLiteral("x\"\n".to_string())
+        );
+        assert_eq!(lexer.get_next_token(), Token::Unknown);
+    }
+
+    #[test]
+    fn test_number_out_of_range() {
+        let program = "9223372036854775807 99999999999999999999;";
+        let mut lexer = Lexer::new(program.to_string().into_bytes());
+        assert_eq!(lexer.get_next_token(), Token::Number(i64::MAX));
+        assert_eq!(lexer.get_next_token(), Token::NumberOutOfRange);
+        assert_eq!(lexer.get_next_token(), Token::SemiColon);
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let program = "fn main() {
+    // comment
+    return 10;
+}";
+        let mut lexer = Lexer::new(program.to_string().into_bytes());
+        assert_eq!(
+            lexer.get_next_token().span,
+            Span {
+                start: 0,
+                end: 2,
+                line: 1,
+                col: 1
+            }
+        );
+        for _ in 0..4 {
+            lexer.get_next_token();
+        }
+        assert_eq!(
+            lexer.get_next_token().span,
+            Span {
+                start: 16,
+                end: 26,
+                line: 2,
+                col: 5
+            }
+        );
+        assert_eq!(lexer.get_next_token().span.line, 3);
+        let num = lexer.get_next_token();
+        assert_eq!(num, Token::Number(10));
+        assert_eq!(
+            num.span,
+            Span {
+                start: 38,
+                end: 40,
+                line: 3,
+                col: 12
+            }
+        );
+        lexer.get_next_token();
+        lexer.get_next_token();
+        assert_eq!(lexer.get_next_token().span.line, 4);
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let mut lexer = Lexer::new(Vec::new());
+        assert_eq!(lexer.get_next_token(), Token::EOF);
+    }
 }
@@ -1,3 +1,4 @@
 pub mod ast;
 pub mod lexer;
+#[allow(clippy::module_inception)]
 pub mod parser;
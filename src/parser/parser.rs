@@ -1,34 +1,138 @@
+use crate::diagnostics::{self, Diagnostic};
 use crate::parser::{
     ast::{
-        AssignStmtAST, BinaryExpressionAST, BodyAST, BoolAST, CallAST, DeclAssignAST,
-        DeclarationAST, ExprAST, FunctionAST, IfStmtAST, NumberAST, ReturnStmtAST, StmtAST,
-        StringLiteralAST, TypeAST, VariableAST,
+        ArrayLiteralAST, ArrayRepeatAST, AssignStmtAST, BinaryExpressionAST, BodyAST, BoolAST,
+        CallAST, CastAST, CharAST, DeclAssignAST, DeclarationAST, ElseAST, EnumAST, EnumVariantAST,
+        ExprAST, FieldAST, FieldAccessAST, FieldInitAST, FunctionAST, IfStmtAST, IndexAST,
+        MatchArmAST, MatchStmtAST, NumberAST, PatternAST, PrintFormat, PrintStmtAST, ReturnStmtAST,
+        StmtAST, StringLiteralAST, StructAST, StructLiteralAST, TypeAST, UnaryExpressionAST,
+        VariableAST, VariantAST, WhileStmtAST,
     },
-    lexer::{Lexer, Token},
+    lexer::{Lexer, Span, Token},
 };
 
 use super::ast::FnSignatureAST;
 
+type ParseResult<T> = Result<T, Diagnostic>;
+
 pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
+    cur_span: Span,
+    //span of the last token that was eaten, used as the end of ast nodes
+    prev_span: Span,
+    //syntax errors that were recovered from
+    errors: Vec<Diagnostic>,
+    //set while parsing the condition of an if or while, where `a {` starts the body
+    no_struct_literal: bool,
 }
 
 impl Parser {
     pub fn new(mut lexer: Lexer) -> Self {
+        let first = lexer.get_next_token();
         Self {
-            cur_token: lexer.get_next_token(),
+            cur_token: first.token,
+            cur_span: first.span,
+            prev_span: first.span,
             lexer,
+            errors: Vec::new(),
+            no_struct_literal: false,
         }
     }
 
     ///advances the lexer to the next token, stores the new token in current token and returns a
     ///clone of the new token
     pub fn get_next_token(&mut self) -> Token {
-        self.cur_token = self.lexer.get_next_token();
+        let next = self.lexer.get_next_token();
+        self.prev_span = self.cur_span;
+        self.cur_token = next.token;
+        self.cur_span = next.span;
         self.cur_token.clone()
     }
 
+    ///builds the error for a current token that does not fit, expected is used in the message
+    fn unexpected_token(&self, expected: &str) -> Diagnostic {
+        if self.cur_token == Token::Unknown {
+            return Diagnostic::error(
+                diagnostics::UNKNOWN_TOKEN,
+                "unknown start of token",
+                self.cur_span,
+            )
+            .with_label(format!("expected {}", expected));
+        }
+        if self.cur_token == Token::NumberOutOfRange {
+            return Diagnostic::error(
+                diagnostics::NUMBER_OUT_OF_RANGE,
+                "integer literal out of range",
+                self.cur_span,
+            )
+            .with_label(format!("the largest integer literal is {}", i64::MAX));
+        }
+        Diagnostic::error(
+            diagnostics::UNEXPECTED_TOKEN,
+            format!("expected {}, found `{}`", expected, self.cur_token),
+            self.cur_span,
+        )
+        .with_label(format!("expected {}", expected))
+    }
+
+    ///eats the current token if it is the expected one, errors otherwise
+    fn expect_token(&mut self, expected: Token) -> ParseResult<()> {
+        if self.cur_token != expected {
+            return Err(self.unexpected_token(&format!("`{}`", expected)));
+        }
+        self.get_next_token();
+        Ok(())
+    }
+
+    ///stores a syntax error, errors at the same location as the previous one are follow up errors
+    ///of the recovery and are dropped
+    fn report(&mut self, err: Diagnostic) {
+        if self.errors.last().map(|last| last.primary.span) != Some(err.primary.span) {
+            self.errors.push(err);
+        }
+    }
+
+    ///skips tokens after a syntax error in a body until parsing can continue, this is after the
+    ///next ';', before the '}' that closes the body or before the next 'fn', 'let', 'if',
+    ///'while', 'match', 'print' or 'return', bodies that are opened while skipping are skipped as
+    ///a whole
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.cur_token {
+                Token::EOF => return,
+                Token::SemiColon if depth == 0 => {
+                    self.get_next_token();
+                    return;
+                }
+                Token::RightBrace
+                | Token::Definition
+                | Token::Declaration
+                | Token::If
+                | Token::While
+                | Token::Match
+                | Token::Print
+                | Token::Return
+                    if depth == 0 =>
+                {
+                    return
+                }
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        //eat the '}' of the skipped body
+                        self.get_next_token();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.get_next_token();
+        }
+    }
+
     ///returns the precedence for the current token, returns -1 if the token is not an operator,     
     fn operator_precedence(&self) -> i8 {
         match &self.cur_token {
@@ -62,22 +166,109 @@ impl Parser {
 
     ///parses num and eats its token
     fn parse_number(&mut self, num: i64) -> NumberAST {
-        let n = NumberAST { num };
+        let n = NumberAST {
+            num,
+            tp: TypeAST::UntypedInt,
+            span: self.cur_span,
+        };
         self.get_next_token();
         n
     }
 
+    ///parses a number with an optional leading '-', used where no expression is allowed
+    fn parse_signed_number(&mut self) -> ParseResult<NumberAST> {
+        let start = self.cur_span;
+        let negative = self.cur_token == Token::Minus;
+        if negative {
+            //eat '-'
+            self.get_next_token();
+        }
+        let Token::Number(num) = self.cur_token else {
+            return Err(self.unexpected_token("number"));
+        };
+        //eat the number
+        self.get_next_token();
+        Ok(NumberAST {
+            num: if negative { -num } else { num },
+            tp: TypeAST::UntypedInt,
+            span: start.to(self.prev_span),
+        })
+    }
+
+    ///parses the length of an array type or repeat literal and eats its token
+    fn parse_array_len(&mut self) -> ParseResult<NumberAST> {
+        match self.cur_token {
+            Token::Number(num) => Ok(self.parse_number(num)),
+            _ => Err(self.unexpected_token("array length")),
+        }
+    }
+
+    ///parses `[a, b, c]` or `[value; len]`, the current token has to be the '['
+    fn parse_array_literal(&mut self) -> ParseResult<ExprAST> {
+        let start = self.cur_span;
+        //eat '['
+        self.get_next_token();
+        let mut elements = Vec::new();
+        while self.cur_token != Token::RightBracket {
+            elements.push(self.parse_delimited_expression()?);
+            match self.cur_token {
+                Token::SemiColon if elements.len() == 1 => {
+                    //eat ';'
+                    self.get_next_token();
+                    let len = self.parse_array_len()?;
+                    self.expect_token(Token::RightBracket)?;
+                    return Ok(ExprAST::ArrayRepeat(Box::new(ArrayRepeatAST {
+                        value: elements.remove(0),
+                        len,
+                        span: start.to(self.prev_span),
+                        tp: TypeAST::Undefined,
+                    })));
+                }
+                //eat ','
+                Token::Comma => self.get_next_token(),
+                Token::RightBracket => break,
+                _ => return Err(self.unexpected_token("`,` or `]`")),
+            };
+        }
+        //eat ']'
+        self.get_next_token();
+        Ok(ExprAST::ArrayLiteral(ArrayLiteralAST {
+            elements,
+            span: start.to(self.prev_span),
+            tp: TypeAST::Undefined,
+        }))
+    }
+
     ///parses string literal and eats its token
     fn parse_string_literal(&mut self, lit: String) -> StringLiteralAST {
-        let l = StringLiteralAST { str: lit };
+        let l = StringLiteralAST {
+            str: lit,
+            span: self.cur_span,
+        };
         self.get_next_token();
         l
     }
 
     ///parses the Current token to a type and eats the current token
-    ///only support for primitives so far
-    fn parse_type(&mut self) -> TypeAST {
+    ///only support for primitives, named types and arrays so far
+    fn parse_type(&mut self) -> ParseResult<TypeAST> {
         let t = match &self.cur_token {
+            Token::LeftBracket => {
+                //eat '['
+                self.get_next_token();
+                let element = self.parse_type()?;
+                self.expect_token(Token::SemiColon)?;
+                let len = self.parse_array_len()?;
+                if self.cur_token != Token::RightBracket {
+                    return Err(self.unexpected_token("`]`"));
+                }
+                TypeAST::Array(Box::new(element), len.num as u64)
+            }
+            Token::Mult => {
+                //eat '*'
+                self.get_next_token();
+                return Ok(TypeAST::Pointer(Box::new(self.parse_type()?)));
+            }
             Token::U8 => TypeAST::U8,
             Token::U16 => TypeAST::U16,
             Token::U32 => TypeAST::U32,
@@ -88,101 +279,261 @@ impl Parser {
             Token::Char => TypeAST::Char,
             Token::Bool => TypeAST::Bool,
             Token::Void => TypeAST::Void,
-            //Token::Identifier(name) => TypeAST::Custom(name.to_string()),
-            other => panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected Type",
-                self.lexer.current_line(),
-                other
-            ),
+            Token::Identifier(name) => TypeAST::Custom(name.to_string()),
+            _other => return Err(self.unexpected_token("type")),
         };
         self.get_next_token();
-        t
+        Ok(t)
     }
 
     ///constructs a function call, where the return value is not ignored
-    fn parse_call_expr(&mut self, name: String) -> CallAST {
+    ///the current token has to be the '('
+    fn parse_call_expr(&mut self, name: String, start: Span) -> ParseResult<CallAST> {
         let mut args = Vec::new();
-        while self.cur_token != Token::RightParen {
-            //eat '(' or ','
-            self.get_next_token();
-            args.push(self.parse_expression());
+        //eat '('
+        self.get_next_token();
+        if self.cur_token != Token::RightParen {
+            loop {
+                args.push(self.parse_delimited_expression()?);
+                match self.cur_token {
+                    //eat ','
+                    Token::Comma => self.get_next_token(),
+                    Token::RightParen => break,
+                    _ => return Err(self.unexpected_token("`,` or `)`")),
+                };
+            }
         }
         //eat ')'
         self.get_next_token();
-        CallAST {
+        Ok(CallAST {
             callee: name,
             args,
             rt_value_ignored: false,
-        }
+            span: start.to(self.prev_span),
+            id: None,
+            tp: TypeAST::Undefined,
+        })
     }
 
     ///parses the right side of an assignment
-    fn parse_assign(&mut self, name: String) -> AssignStmtAST {
+    fn parse_assign(&mut self, target: ExprAST) -> ParseResult<AssignStmtAST> {
         //eat '='
         self.get_next_token();
-        let value = self.parse_expression();
+        let value = self.parse_expression()?;
         if self.cur_token != Token::SemiColon {
-            panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected ';'",
-                self.lexer.current_line(),
-                self.cur_token
-            )
+            return Err(self.unexpected_token("`;`"));
         }
-        AssignStmtAST {
-            var: VariableAST { name },
+        Ok(AssignStmtAST {
+            span: target.span().to(self.cur_span),
+            target,
             value,
+        })
+    }
+
+    ///parses the fields of a struct literal, the current token has to be the '{'
+    fn parse_struct_literal(&mut self, name: String, start: Span) -> ParseResult<StructLiteralAST> {
+        //eat '{'
+        self.get_next_token();
+        let mut fields = Vec::new();
+        while self.cur_token != Token::RightBrace {
+            let Token::Identifier(field) = self.cur_token.clone() else {
+                return Err(self.unexpected_token("field name"));
+            };
+            let field_start = self.cur_span;
+            //eat the field name
+            self.get_next_token();
+            self.expect_token(Token::Colon)?;
+            let value = self.parse_delimited_expression()?;
+            fields.push(FieldInitAST {
+                name: field,
+                span: field_start.to(value.span()),
+                value,
+            });
+            match self.cur_token {
+                //eat ','
+                Token::Comma => self.get_next_token(),
+                Token::RightBrace => break,
+                _ => return Err(self.unexpected_token("`,` or `}`")),
+            };
         }
+        //eat '}'
+        self.get_next_token();
+        Ok(StructLiteralAST {
+            name,
+            fields,
+            span: start.to(self.prev_span),
+        })
+    }
+
+    ///parses the variant after the name of the enum, the current token has to be the '::'
+    fn parse_enum_variant(
+        &mut self,
+        enum_name: String,
+        start: Span,
+    ) -> ParseResult<EnumVariantAST> {
+        //eat '::'
+        let Token::Identifier(variant) = self.get_next_token() else {
+            return Err(self.unexpected_token("variant name"));
+        };
+        //eat the variant
+        self.get_next_token();
+        Ok(EnumVariantAST {
+            enum_name,
+            variant,
+            span: start.to(self.prev_span),
+        })
     }
 
-    ///for call expressions and variables inside expressions
-    fn parse_identifier(&mut self, ident: String) -> ExprAST {
+    ///for call expressions, struct literals, enum variants and variables inside expressions
+    fn parse_identifier(&mut self, ident: String) -> ParseResult<ExprAST> {
+        let start = self.cur_span;
         //eats the identifier
-        if self.get_next_token() != Token::LeftParen {
+        match self.get_next_token() {
+            Token::LeftParen => Ok(ExprAST::Call(self.parse_call_expr(ident, start)?)),
+            Token::DoubleColon => Ok(ExprAST::EnumVariant(self.parse_enum_variant(ident, start)?)),
+            Token::LeftBrace if !self.no_struct_literal => Ok(ExprAST::StructLiteral(
+                self.parse_struct_literal(ident, start)?,
+            )),
             //its a variable
-            return ExprAST::Variable(VariableAST { name: ident });
+            _ => Ok(ExprAST::Variable(VariableAST {
+                name: ident,
+                span: start,
+                id: None,
+                tp: TypeAST::Undefined,
+            })),
+        }
+    }
+
+    ///parses the `.field` accesses and `[index]` indexing that follow an expression
+    fn parse_postfix(&mut self, mut expr: ExprAST) -> ParseResult<ExprAST> {
+        loop {
+            expr = match self.cur_token {
+                Token::LeftBracket => {
+                    //eat '['
+                    self.get_next_token();
+                    let index = self.parse_delimited_expression()?;
+                    self.expect_token(Token::RightBracket)?;
+                    ExprAST::Index(Box::new(IndexAST {
+                        span: expr.span().to(self.prev_span),
+                        expr,
+                        index,
+                        tp: TypeAST::Undefined,
+                    }))
+                }
+                Token::Dot => {
+                    //eat '.'
+                    self.get_next_token();
+                    let Token::Identifier(field) = self.cur_token.clone() else {
+                        return Err(self.unexpected_token("field name"));
+                    };
+                    let field_span = self.cur_span;
+                    //eat the field name
+                    self.get_next_token();
+                    ExprAST::FieldAccess(Box::new(FieldAccessAST {
+                        span: expr.span().to(field_span),
+                        expr,
+                        field,
+                        field_span,
+                        tp: TypeAST::Undefined,
+                    }))
+                }
+                _ => return Ok(expr),
+            };
         }
-        ExprAST::Call(self.parse_call_expr(ident))
     }
 
     ///this is called when an identifier is found outside of expressions
     ///it is either a call with ignored return value or an assignment
-    fn parse_ident_stmt(&mut self, ident: String) -> StmtAST {
+    fn parse_ident_stmt(&mut self, ident: String) -> ParseResult<StmtAST> {
+        let start = self.cur_span;
         //eats the identifier
         let stmt = match self.get_next_token() {
             //...
             //foo(2,6)
             //...
             Token::LeftParen => {
-                let mut call = self.parse_call_expr(ident);
+                let mut call = self.parse_call_expr(ident, start)?;
                 call.rt_value_ignored = true;
                 StmtAST::Call(call)
             }
-            Token::Assign => StmtAST::Assign(Box::new(self.parse_assign(ident))),
-            other => panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected '(' or '='",
-                self.lexer.current_line(),
-                other
-            ),
+            Token::Assign | Token::Dot | Token::LeftBracket => {
+                let var = ExprAST::Variable(VariableAST {
+                    name: ident,
+                    span: start,
+                    id: None,
+                    tp: TypeAST::Undefined,
+                });
+                let target = self.parse_postfix(var)?;
+                if self.cur_token != Token::Assign {
+                    return Err(self.unexpected_token("`=`"));
+                }
+                StmtAST::Assign(Box::new(self.parse_assign(target)?))
+            }
+            _other => return Err(self.unexpected_token("`(`, `.`, `[` or `=`")),
         };
         //eat the semi colon
+        self.expect_token(Token::SemiColon)?;
+        Ok(stmt)
+    }
+
+    ///an assignment through a pointer, `*p = v;`
+    fn parse_deref_stmt(&mut self) -> ParseResult<StmtAST> {
+        let target = self.parse_unary_expr()?;
+        if self.cur_token != Token::Assign {
+            return Err(self.unexpected_token("`=`"));
+        }
+        let assign = self.parse_assign(target)?;
+        //eat the semi colon
+        self.expect_token(Token::SemiColon)?;
+        Ok(StmtAST::Assign(Box::new(assign)))
+    }
+
+    ///parses `&place`, `*pointer` or `-int`, the operand binds tighter than any binary operator
+    fn parse_unary_expr(&mut self) -> ParseResult<ExprAST> {
+        let start = self.cur_span;
+        let op = self.cur_token.clone();
+        //eat the operator
         self.get_next_token();
-        stmt
+        let expr = self.parse_postfix_expression()?;
+        Ok(ExprAST::Unary(Box::new(UnaryExpressionAST {
+            op,
+            span: start.to(expr.span()),
+            expr,
+            tp: TypeAST::Undefined,
+        })))
     }
 
     ///returns the parsed expression from within the parens
-    fn parse_paren_expr(&mut self) -> ExprAST {
+    fn parse_paren_expr(&mut self) -> ParseResult<ExprAST> {
         assert_eq!(self.cur_token, Token::LeftParen);
         // eat '('
         self.get_next_token();
         //parse whatever is in the parens
-        let expr = self.parse_expression();
+        let expr = self.parse_delimited_expression()?;
         //eat ')'
-        self.get_next_token();
+        self.expect_token(Token::RightParen)?;
+        Ok(expr)
+    }
+
+    ///parses an expression between delimiters, where struct literals are allowed again
+    fn parse_delimited_expression(&mut self) -> ParseResult<ExprAST> {
+        let outer = std::mem::replace(&mut self.no_struct_literal, false);
+        let expr = self.parse_expression();
+        self.no_struct_literal = outer;
         expr
     }
 
-    fn parse_declaration(&mut self) -> StmtAST {
+    ///parses the condition of an if or while
+    fn parse_condition(&mut self) -> ParseResult<ExprAST> {
+        self.no_struct_literal = true;
+        let condition = self.parse_expression();
+        self.no_struct_literal = false;
+        condition
+    }
+
+    fn parse_declaration(&mut self) -> ParseResult<StmtAST> {
         assert_eq!(self.cur_token, Token::Declaration);
+        let start = self.cur_span;
         //eat "let"
         self.get_next_token();
         let is_mut = if self.cur_token == Token::Mut {
@@ -194,62 +545,110 @@ impl Parser {
         };
         let name = match &self.cur_token {
             Token::Identifier(ident) => ident.to_string(),
-            other => panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected identifier",
-                self.lexer.current_line(),
-                other
-            ),
+            _other => return Err(self.unexpected_token("identifier")),
         };
         //eat identifier
         let var_type = if self.get_next_token() == Token::Colon {
             //eat the ':'
             self.get_next_token();
-            self.parse_type()
+            self.parse_type()?
         } else {
             TypeAST::Undefined
         };
+        let decl_span = start.to(self.prev_span);
         //eat the type
         if self.cur_token == Token::SemiColon {
             //eat the ';'
             self.get_next_token();
-            StmtAST::Declaration(DeclarationAST {
+            Ok(StmtAST::Declaration(DeclarationAST {
                 name,
                 var_type,
                 is_mut,
-            })
+                span: decl_span,
+                id: None,
+            }))
         } else if self.cur_token == Token::Assign {
             //eat the '='
             self.get_next_token();
-            let val = self.parse_expression();
-
-            if self.cur_token != Token::SemiColon {
-                panic!(
-                    "Error in line: {:?}, unexpected token: {:?}, expected ';'",
-                    self.lexer.current_line(),
-                    self.cur_token
-                );
-            }
+            let val = self.parse_expression()?;
+
             //eat the ';'
-            self.get_next_token();
+            self.expect_token(Token::SemiColon)?;
             //return Declaration
-            StmtAST::DeclAssign(DeclAssignAST {
+            Ok(StmtAST::DeclAssign(DeclAssignAST {
                 decl: DeclarationAST {
                     name,
                     var_type,
                     is_mut,
+                    span: decl_span,
+                    id: None,
                 },
                 value: val,
-            })
+                span: start.to(self.prev_span),
+            }))
         } else {
-            panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected ';' or '='",
-                self.lexer.current_line(),
-                self.cur_token
-            );
+            Err(self.unexpected_token("`;` or `=`"))
         }
     }
 
-    fn parse_argument(&mut self) -> (DeclarationAST, bool) {
+    ///parses `const NAME: Type = value;`, `static (mut) NAME: Type = value;` or
+    ///`let (mut) NAME(: Type) = value;` at the top level, only the type of a `let` can be left out
+    fn parse_global(&mut self) -> ParseResult<StmtAST> {
+        let start = self.cur_span;
+        let keyword = self.cur_token.clone();
+        //eat the keyword
+        self.get_next_token();
+        let is_mut = keyword != Token::Const && self.cur_token == Token::Mut;
+        if is_mut {
+            //eat the 'mut'
+            self.get_next_token();
+        }
+        let name = match &self.cur_token {
+            Token::Identifier(ident) => ident.to_string(),
+            _other => return Err(self.unexpected_token("identifier")),
+        };
+        //eat identifier
+        let var_type = if self.get_next_token() == Token::Colon {
+            //eat the ':'
+            self.get_next_token();
+            self.parse_type()?
+        } else if keyword == Token::Declaration {
+            TypeAST::Undefined
+        } else {
+            return Err(self
+                .unexpected_token("`:`")
+                .with_note(format!("the type of a `{}` can not be inferred", keyword)));
+        };
+        let decl_span = start.to(self.prev_span);
+        if self.cur_token != Token::Assign {
+            return Err(self
+                .unexpected_token("`=`")
+                .with_note("constants and global variables need a value"));
+        }
+        //eat the '='
+        self.get_next_token();
+        let value = self.parse_expression()?;
+        self.expect_token(Token::SemiColon)?;
+        let global = DeclAssignAST {
+            decl: DeclarationAST {
+                name,
+                var_type,
+                is_mut,
+                span: decl_span,
+                id: None,
+            },
+            value,
+            span: start.to(self.prev_span),
+        };
+        if keyword == Token::Const {
+            Ok(StmtAST::Const(global))
+        } else {
+            Ok(StmtAST::DeclAssign(global))
+        }
+    }
+
+    fn parse_argument(&mut self) -> ParseResult<(DeclarationAST, bool)> {
+        let start = self.cur_span;
         let is_mut = if self.cur_token == Token::Mut {
             //eat 'mut' if exists
             self.get_next_token();
@@ -260,198 +659,453 @@ impl Parser {
 
         if let Token::Identifier(arg_name) = self.cur_token.clone() {
             //eat name
-            if self.get_next_token() != Token::Colon {
-                panic!(
-                    "Error in line: {:?}, unexpected token: {:?}, expected ':'",
-                    self.lexer.current_line(),
-                    self.cur_token
-                );
-            }
             self.get_next_token();
-            let arg_tp = self.parse_type();
+            self.expect_token(Token::Colon)?;
+            let arg_tp = self.parse_type()?;
+            let span = start.to(self.prev_span);
             let is_last = if self.cur_token == Token::Comma {
                 self.get_next_token();
                 false
             } else if self.cur_token == Token::RightParen {
                 true
             } else {
-                panic!(
-                    "Error in line: {:?}, unexpected token: {:?}, expected ',' or ')'",
-                    self.lexer.current_line(),
-                    self.cur_token
-                )
+                return Err(self.unexpected_token("`,` or `)`"));
             };
-            (
+            Ok((
                 DeclarationAST {
                     name: arg_name,
                     var_type: arg_tp,
                     is_mut,
+                    span,
+                    id: None,
                 },
                 is_last,
-            )
+            ))
         } else {
-            panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected identifier",
-                self.lexer.current_line(),
-                self.cur_token
-            )
+            Err(self.unexpected_token("identifier"))
         }
     }
 
-    fn parse_function_def(&mut self) -> FunctionAST {
+    fn parse_function_def(&mut self) -> ParseResult<FunctionAST> {
+        let start = self.cur_span;
         //eat 'fn'
-        if let Token::Identifier(name) = self.get_next_token() {
-            //eat function name
-            if let Token::LeftParen = self.get_next_token() {
-                //eat '('
-                let mut args = Vec::new();
-                //only look for arguments if there are any
-                if self.get_next_token() != Token::RightParen {
-                    let mut is_last = false;
-                    while !is_last {
-                        let (arg, last) = self.parse_argument();
-                        is_last = last;
-                        args.push(arg);
-                    }
-                }
-                //eat ')'
-                let rt_type = if self.get_next_token() == Token::Arrow {
-                    //eat '->'
-                    self.get_next_token();
-                    //parses and eats the type
-                    self.parse_type()
-                } else {
-                    TypeAST::Void
-                };
+        let name = match self.get_next_token() {
+            Token::Identifier(name) => name,
+            _other => return Err(self.unexpected_token("identifier")),
+        };
+        //eat function name
+        self.get_next_token();
+        self.expect_token(Token::LeftParen)?;
+        let mut args = Vec::new();
+        //only look for arguments if there are any
+        if self.cur_token != Token::RightParen {
+            let mut is_last = false;
+            while !is_last {
+                let (arg, last) = self.parse_argument()?;
+                is_last = last;
+                args.push(arg);
+            }
+        }
+        //eat ')'
+        let rt_type = if self.get_next_token() == Token::Arrow {
+            //eat '->'
+            self.get_next_token();
+            //parses and eats the type
+            self.parse_type()?
+        } else {
+            TypeAST::Void
+        };
+        let signt_span = start.to(self.prev_span);
+
+        //eat '{'
+        self.expect_token(Token::LeftBrace)?;
+        let body = self.parse_body();
+        Ok(FunctionAST {
+            fn_signt: FnSignatureAST {
+                name,
+                args,
+                rt_type,
+                span: signt_span,
+                id: None,
+            },
+            body,
+            span: start.to(self.prev_span),
+        })
+    }
 
-                //check for '{'
-                if self.cur_token != Token::LeftBrace {
-                    panic!(
-                        "Error in line: {:?}, unexpected token: {:?}, expected '{{'",
-                        self.lexer.current_line(),
-                        self.cur_token
-                    )
+    fn parse_struct_def(&mut self) -> ParseResult<StructAST> {
+        let start = self.cur_span;
+        //eat 'struct'
+        let name = match self.get_next_token() {
+            Token::Identifier(name) => name,
+            _other => return Err(self.unexpected_token("identifier")),
+        };
+        //eat the name
+        self.get_next_token();
+        self.expect_token(Token::LeftBrace)?;
+        let mut fields = Vec::new();
+        loop {
+            let field = match &self.cur_token {
+                Token::Comment(_) => {
+                    self.get_next_token();
+                    continue;
                 }
+                Token::RightBrace => break,
+                Token::Identifier(field) => field.to_string(),
+                _other => return Err(self.unexpected_token("field name")),
+            };
+            let field_start = self.cur_span;
+            //eat the field name
+            self.get_next_token();
+            self.expect_token(Token::Colon)?;
+            let field_type = self.parse_type()?;
+            fields.push(FieldAST {
+                name: field,
+                field_type,
+                span: field_start.to(self.prev_span),
+            });
+            match self.cur_token {
+                //eat ','
+                Token::Comma => self.get_next_token(),
+                Token::RightBrace => break,
+                _ => return Err(self.unexpected_token("`,` or `}`")),
+            };
+        }
+        //eat '}'
+        self.get_next_token();
+        Ok(StructAST {
+            name,
+            fields,
+            span: start.to(self.prev_span),
+        })
+    }
 
-                //eat '{'
-                self.get_next_token();
-                let body = self.parse_body();
-                FunctionAST {
-                    fn_signt: FnSignatureAST {
-                        name,
-                        args,
-                        rt_type,
-                    },
-                    body,
+    fn parse_enum_def(&mut self) -> ParseResult<EnumAST> {
+        let start = self.cur_span;
+        //eat 'enum'
+        let name = match self.get_next_token() {
+            Token::Identifier(name) => name,
+            _other => return Err(self.unexpected_token("identifier")),
+        };
+        //eat the name
+        self.get_next_token();
+        self.expect_token(Token::LeftBrace)?;
+        let mut variants = Vec::new();
+        loop {
+            let variant = match &self.cur_token {
+                Token::Comment(_) => {
+                    self.get_next_token();
+                    continue;
                 }
+                Token::RightBrace => break,
+                Token::Identifier(variant) => variant.to_string(),
+                _other => return Err(self.unexpected_token("variant name")),
+            };
+            let variant_start = self.cur_span;
+            //eat the variant name
+            let value = if self.get_next_token() == Token::Assign {
+                //eat '='
+                self.get_next_token();
+                Some(self.parse_signed_number()?)
             } else {
-                panic!(
-                    "Error in line: {:?}, unexpected token: {:?}, expected '()'",
-                    self.lexer.current_line(),
-                    self.cur_token
-                );
-            }
-        } else {
-            panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected identifier",
-                self.lexer.current_line(),
-                self.cur_token
-            );
+                None
+            };
+            variants.push(VariantAST {
+                name: variant,
+                value,
+                span: variant_start.to(self.prev_span),
+            });
+            match self.cur_token {
+                //eat ','
+                Token::Comma => self.get_next_token(),
+                Token::RightBrace => break,
+                _ => return Err(self.unexpected_token("`,`, `=` or `}`")),
+            };
         }
+        //eat '}'
+        self.get_next_token();
+        Ok(EnumAST {
+            name,
+            variants,
+            span: start.to(self.prev_span),
+        })
     }
 
-    fn parse_return_stmt(&mut self) -> ReturnStmtAST {
+    fn parse_return_stmt(&mut self) -> ParseResult<ReturnStmtAST> {
+        let start = self.cur_span;
         //eat 'return'
         self.get_next_token();
+        let expr = self.parse_expression()?;
         let rtstmt = ReturnStmtAST {
-            expr: self.parse_expression(),
+            expr,
+            span: start.to(self.cur_span),
         };
-        if self.cur_token == Token::SemiColon {
-            //eat ';'
+        //eat ';'
+        self.expect_token(Token::SemiColon)?;
+        Ok(rtstmt)
+    }
+
+    fn parse_if_stmnt(&mut self) -> ParseResult<IfStmtAST> {
+        let start = self.cur_span;
+        //eat 'if'
+        self.get_next_token();
+        let condition = self.parse_condition()?;
+        //eat '{'
+        self.expect_token(Token::LeftBrace)?;
+        let body = self.parse_body();
+        let else_branch = if self.cur_token == Token::Else {
+            //eat 'else'
             self.get_next_token();
+            if self.cur_token == Token::If {
+                Some(ElseAST::If(Box::new(self.parse_if_stmnt()?)))
+            } else {
+                //eat '{'
+                self.expect_token(Token::LeftBrace)?;
+                Some(ElseAST::Body(self.parse_body()))
+            }
         } else {
-            panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected ';'",
-                self.lexer.current_line(),
-                self.cur_token
-            )
-        }
-        rtstmt
+            None
+        };
+        Ok(IfStmtAST {
+            condition,
+            body,
+            else_branch,
+            span: start.to(self.prev_span),
+        })
     }
 
-    fn parse_if_stmnt(&mut self) -> IfStmtAST {
+    fn parse_while_stmt(&mut self) -> ParseResult<WhileStmtAST> {
+        let start = self.cur_span;
+        //eat 'while'
         self.get_next_token();
-        let condition = self.parse_expression();
-        assert_eq!(self.get_next_token(), Token::LeftBrace);
+        let condition = self.parse_condition()?;
+        //eat '{'
+        self.expect_token(Token::LeftBrace)?;
         let body = self.parse_body();
-        IfStmtAST { condition, body }
+        Ok(WhileStmtAST {
+            condition,
+            body,
+            span: start.to(self.prev_span),
+        })
+    }
+
+    fn parse_pattern(&mut self) -> ParseResult<PatternAST> {
+        let pattern = match &self.cur_token {
+            Token::Identifier(name) => {
+                let (name, start) = (name.to_string(), self.cur_span);
+                //eat the name of the enum
+                if self.get_next_token() != Token::DoubleColon {
+                    return Err(self.unexpected_token("`::`"));
+                }
+                return Ok(PatternAST::Variant(self.parse_enum_variant(name, start)?));
+            }
+            Token::Number(_) | Token::Minus => {
+                return Ok(PatternAST::Number(self.parse_signed_number()?))
+            }
+            Token::CharLiteral(ch) => PatternAST::Char(CharAST {
+                value: *ch,
+                span: self.cur_span,
+            }),
+            Token::True | Token::False => return Ok(PatternAST::Bool(self.parse_bool_expr())),
+            Token::Underscore => PatternAST::Wildcard(self.cur_span),
+            _other => return Err(self.unexpected_token("pattern")),
+        };
+        //eat the char or '_'
+        self.get_next_token();
+        Ok(pattern)
+    }
+
+    fn parse_match_stmt(&mut self) -> ParseResult<MatchStmtAST> {
+        let start = self.cur_span;
+        //eat 'match'
+        self.get_next_token();
+        let scrutinee = self.parse_condition()?;
+        self.expect_token(Token::LeftBrace)?;
+        let mut arms = Vec::new();
+        loop {
+            match self.cur_token {
+                Token::Comment(_) => {
+                    self.get_next_token();
+                    continue;
+                }
+                Token::RightBrace => break,
+                _ => {}
+            }
+            let arm_start = self.cur_span;
+            let mut patterns = vec![self.parse_pattern()?];
+            while self.cur_token == Token::OrInt {
+                //eat '|'
+                self.get_next_token();
+                patterns.push(self.parse_pattern()?);
+            }
+            if self.cur_token != Token::FatArrow {
+                return Err(self.unexpected_token("`=>` or `|`"));
+            }
+            //eat '=>'
+            self.get_next_token();
+            self.expect_token(Token::LeftBrace)?;
+            let body = self.parse_body();
+            arms.push(MatchArmAST {
+                patterns,
+                body,
+                span: arm_start.to(self.prev_span),
+            });
+            if self.cur_token == Token::Comma {
+                //eat ','
+                self.get_next_token();
+            }
+        }
+        //eat '}'
+        self.get_next_token();
+        Ok(MatchStmtAST {
+            scrutinee,
+            arms,
+            span: start.to(self.prev_span),
+        })
+    }
+
+    ///parses `break;` or `continue;`, returns the span of the keyword
+    fn parse_loop_jump(&mut self) -> ParseResult<Span> {
+        let span = self.cur_span;
+        //eat 'break' or 'continue'
+        self.get_next_token();
+        //eat ';'
+        self.expect_token(Token::SemiColon)?;
+        Ok(span)
+    }
+
+    fn parse_print_stmt(&mut self) -> ParseResult<PrintStmtAST> {
+        let start = self.cur_span;
+        //eat 'print'
+        self.get_next_token();
+        if self.cur_token != Token::LeftParen {
+            return Err(self.unexpected_token("`(`"));
+        }
+        let expr = self.parse_paren_expr()?;
+        let span = start.to(self.prev_span);
+        //eat ';'
+        self.expect_token(Token::SemiColon)?;
+        Ok(PrintStmtAST {
+            expr,
+            format: PrintFormat::Unresolved,
+            span,
+        })
     }
 
     fn parse_bool_expr(&mut self) -> BoolAST {
         let bl = BoolAST {
             value: self.cur_token == Token::True,
+            span: self.cur_span,
         };
         self.get_next_token();
         bl
     }
 
+    ///parses the statements up to and including the closing '}', the '{' must already be eaten
+    ///statements with syntax errors are reported and left out of the body
     fn parse_body(&mut self) -> BodyAST {
+        let start = self.prev_span;
         let mut stmts = Vec::new();
         loop {
-            match &self.cur_token {
-                Token::Declaration => stmts.push(self.parse_declaration()),
-                Token::Definition => stmts.push(StmtAST::Function(self.parse_function_def())),
-                Token::Return => stmts.push(StmtAST::Return(Box::new(self.parse_return_stmt()))),
-                Token::Identifier(ident) => stmts.push(self.parse_ident_stmt(ident.to_string())),
+            let stmt = match &self.cur_token {
+                Token::Declaration => self.parse_declaration(),
+                Token::Definition => self.parse_function_def().map(StmtAST::Function),
+                Token::Return => self
+                    .parse_return_stmt()
+                    .map(|rt| StmtAST::Return(Box::new(rt))),
+                Token::Identifier(ident) => self.parse_ident_stmt(ident.to_string()),
+                Token::Mult => self.parse_deref_stmt(),
                 Token::Comment(_) => {
                     self.get_next_token();
+                    continue;
                 }
                 Token::RightBrace => {
                     //eat '}'
                     self.get_next_token();
                     break;
                 }
-                Token::If => stmts.push(StmtAST::If(self.parse_if_stmnt())),
-                other => panic!(
-                    "Error in line: {:?}, unexpected token: {:?}, expected statement",
-                    self.lexer.current_line(),
-                    other
-                ),
+                Token::If => self.parse_if_stmnt().map(StmtAST::If),
+                Token::While => self.parse_while_stmt().map(StmtAST::While),
+                Token::Match => self.parse_match_stmt().map(StmtAST::Match),
+                Token::Break => self.parse_loop_jump().map(StmtAST::Break),
+                Token::Continue => self.parse_loop_jump().map(StmtAST::Continue),
+                Token::Print => self.parse_print_stmt().map(StmtAST::Print),
+                Token::EOF => {
+                    let err = self.unexpected_token("`}`");
+                    self.report(err);
+                    break;
+                }
+                _other => Err(self.unexpected_token("statement")),
             };
+            match stmt {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => {
+                    self.report(err);
+                    self.synchronize();
+                }
+            }
+        }
+        BodyAST {
+            stmts,
+            span: start.to(self.prev_span),
         }
-        BodyAST { stmts }
     }
 
-    fn parse_primary_expression(&mut self) -> ExprAST {
-        match &self.cur_token {
-            Token::Identifier(ident) => self.parse_identifier(ident.to_string()),
+    ///the operand of a binary operator, casts bind tighter than any binary operator but looser
+    ///than `&` and `*`
+    fn parse_primary_expression(&mut self) -> ParseResult<ExprAST> {
+        let mut expr = self.parse_postfix_expression()?;
+        while self.cur_token == Token::As {
+            //eat 'as'
+            self.get_next_token();
+            let target = self.parse_type()?;
+            expr = ExprAST::Cast(Box::new(CastAST {
+                span: expr.span().to(self.prev_span),
+                expr,
+                target,
+            }));
+        }
+        Ok(expr)
+    }
+
+    ///a literal, variable or call with its fields and indices, or a unary operator applied to one
+    fn parse_postfix_expression(&mut self) -> ParseResult<ExprAST> {
+        let expr = match &self.cur_token {
+            Token::Identifier(ident) => self.parse_identifier(ident.to_string())?,
             Token::StringLiteral(lit) => {
                 ExprAST::StringLiteral(self.parse_string_literal(lit.to_string()))
             }
             Token::Number(num) => ExprAST::Number(self.parse_number(*num)),
+            Token::CharLiteral(ch) => {
+                let ch = CharAST {
+                    value: *ch,
+                    span: self.cur_span,
+                };
+                self.get_next_token();
+                ExprAST::CharLiteral(ch)
+            }
             Token::True => ExprAST::BoolLiteral(self.parse_bool_expr()),
             Token::False => ExprAST::BoolLiteral(self.parse_bool_expr()),
-            Token::LeftParen => self.parse_paren_expr(),
-            other => panic!(
-                "Error in line: {:?}, unexpected token: {:?}, expected Primary",
-                self.lexer.current_line(),
-                other
-            ),
-        }
+            Token::LeftParen => self.parse_paren_expr()?,
+            Token::LeftBracket => self.parse_array_literal()?,
+            Token::AndInt | Token::Mult | Token::Minus => return self.parse_unary_expr(),
+            _other => return Err(self.unexpected_token("expression")),
+        };
+        self.parse_postfix(expr)
     }
 
-    fn parse_expression(&mut self) -> ExprAST {
-        let lhs = self.parse_primary_expression();
+    fn parse_expression(&mut self) -> ParseResult<ExprAST> {
+        let lhs = self.parse_primary_expression()?;
         self.parse_binary_op_rhs(0, lhs)
     }
 
-    fn parse_binary_op_rhs(&mut self, expr_prec: i8, mut lhs: ExprAST) -> ExprAST {
+    fn parse_binary_op_rhs(&mut self, expr_prec: i8, mut lhs: ExprAST) -> ParseResult<ExprAST> {
         loop {
             let tok_prec = self.operator_precedence();
 
             //the left side has higher precedence, resolve it first
             if tok_prec < expr_prec {
-                return lhs;
+                return Ok(lhs);
             }
 
             let binop = self.cur_token.clone();
@@ -459,43 +1113,102 @@ impl Parser {
             self.get_next_token();
 
             //parse binary expr after binary operator
-            let mut rhs = self.parse_primary_expression();
+            let mut rhs = self.parse_primary_expression()?;
 
             //if prec of operator after rhs is higher than prec of operator between lhs and rhs,
             //give rhs as lhs to the pending op
             let next_prec = self.operator_precedence();
             if tok_prec < next_prec {
-                rhs = self.parse_binary_op_rhs(tok_prec + 1, rhs);
+                rhs = self.parse_binary_op_rhs(tok_prec + 1, rhs)?;
             }
 
+            let span = lhs.span().to(rhs.span());
             lhs = ExprAST::BinaryExpression(Box::new(BinaryExpressionAST {
                 rhs,
                 lhs,
                 op: binop,
+                span,
+                tp: TypeAST::Undefined,
             }));
         }
     }
 
-    pub fn parse(&mut self) -> BodyAST {
+    ///parses the whole program, returns everything that could be parsed together with all syntax
+    ///errors that were found
+    pub fn parse(&mut self) -> (BodyAST, Vec<Diagnostic>) {
+        let start = self.cur_span;
         let mut program_elements = Vec::new();
         loop {
             match &self.cur_token {
                 Token::Comment(_cmt) => {
                     self.get_next_token();
                 }
-                Token::Definition => {
-                    program_elements.push(StmtAST::Function(self.parse_function_def()))
-                }
+                Token::Definition => match self.parse_function_def() {
+                    Ok(func) => program_elements.push(StmtAST::Function(func)),
+                    Err(err) => {
+                        self.report(err);
+                        self.skip_to_item();
+                    }
+                },
+                Token::Struct => match self.parse_struct_def() {
+                    Ok(def) => program_elements.push(StmtAST::Struct(def)),
+                    Err(err) => {
+                        self.report(err);
+                        self.skip_to_item();
+                    }
+                },
+                Token::Enum => match self.parse_enum_def() {
+                    Ok(def) => program_elements.push(StmtAST::Enum(def)),
+                    Err(err) => {
+                        self.report(err);
+                        self.skip_to_item();
+                    }
+                },
+                Token::Const | Token::Static | Token::Declaration => match self.parse_global() {
+                    Ok(global) => program_elements.push(global),
+                    Err(err) => {
+                        self.report(err);
+                        self.skip_to_item();
+                    }
+                },
                 Token::EOF => break,
-                other => panic!(
-                    "Error in line: {:?}, unexpected token: {:?}, expected 'fn' or '//'",
-                    self.lexer.current_line(),
-                    other
-                ),
+                _other => {
+                    let err = self.unexpected_token(
+                        "`fn`, `struct`, `enum`, `const`, `static`, `let` or `//`",
+                    );
+                    self.report(err);
+                    self.skip_to_item();
+                }
             };
         }
-        BodyAST {
+        let body = BodyAST {
             stmts: program_elements,
+            span: start.to(self.cur_span),
+        };
+        (body, std::mem::take(&mut self.errors))
+    }
+
+    ///skips everything up to the next 'fn', 'struct', 'enum', 'const', 'static' or 'let' after an
+    ///error at the top level, bodies that are opened while skipping are skipped as a whole
+    fn skip_to_item(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.get_next_token() {
+                Token::EOF => return,
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace => depth = depth.saturating_sub(1),
+                Token::Definition
+                | Token::Struct
+                | Token::Enum
+                | Token::Const
+                | Token::Static
+                | Token::Declaration
+                    if depth == 0 =>
+                {
+                    return
+                }
+                _ => {}
+            }
         }
     }
 }
@@ -514,7 +1227,9 @@ mod test {
                         }";
         let lexer = Lexer::new(mprogram.into());
         let mut parser = Parser::new(lexer);
-        let body = parser.parse();
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        assert_eq!(body.stmts.len(), 2);
     }
 
     #[test]
@@ -526,7 +1241,9 @@ mod test {
                         }";
         let lexer = Lexer::new(mprogram.into());
         let mut parser = Parser::new(lexer);
-        let body = parser.parse();
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        assert_eq!(body.stmts.len(), 1);
     }
 
     #[test]
@@ -538,7 +1255,9 @@ mod test {
                         }";
         let lexer = Lexer::new(mprogram.into());
         let mut parser = Parser::new(lexer);
-        let body = parser.parse();
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        assert_eq!(body.stmts.len(), 1);
     }
 
     #[test]
@@ -553,6 +1272,444 @@ mod test {
                         }";
         let lexer = Lexer::new(mprogram.into());
         let mut parser = Parser::new(lexer);
-        let body = parser.parse();
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        assert_eq!(body.stmts.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_if_and_empty_call() {
+        let mprogram = "fn foo() -> bool {
+                            return true;
+                        }
+                        fn main() -> void {
+                            if foo() == true {
+                                foo();
+                            }
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[1] else {
+            panic!("expected function")
+        };
+        assert!(matches!(main.body.stmts[0], StmtAST::If(_)));
+    }
+
+    #[test]
+    fn test_parse_while() {
+        let mprogram = "fn main() -> void {
+                            while true {
+                                if false {
+                                    continue;
+                                }
+                                break;
+                            }
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::While(while_st) = &main.body.stmts[0] else {
+            panic!("expected while")
+        };
+        assert!(matches!(while_st.body.stmts[0], StmtAST::If(_)));
+        assert!(matches!(while_st.body.stmts[1], StmtAST::Break(span) if span.line == 6));
+    }
+
+    #[test]
+    fn test_parse_else_if() {
+        let mprogram = "fn main() -> void {
+                            if true {
+                            } else if false {
+                            } else {
+                                foo();
+                            }
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::If(if_st) = &main.body.stmts[0] else {
+            panic!("expected if")
+        };
+        let Some(ElseAST::If(else_if)) = &if_st.else_branch else {
+            panic!("expected else if")
+        };
+        let Some(ElseAST::Body(else_body)) = &else_if.else_branch else {
+            panic!("expected else")
+        };
+        assert_eq!(else_body.stmts.len(), 1);
+        assert_eq!(if_st.span.to(else_body.span), if_st.span);
+    }
+
+    #[test]
+    fn test_parse_print() {
+        let mprogram = "fn main() -> void {
+                            print('\\n');
+                            print \"a\";
+                            print(\"a\\tb\");
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "expected `(`, found `\"a\"`");
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let [StmtAST::Print(newline), StmtAST::Print(tab)] = main.body.stmts.as_slice() else {
+            panic!("expected two prints")
+        };
+        assert!(matches!(
+            newline.expr,
+            ExprAST::CharLiteral(CharAST { value: b'\n', .. })
+        ));
+        assert!(matches!(&tab.expr, ExprAST::StringLiteral(lit) if lit.str == "a\tb"));
+        assert_eq!(tab.format, PrintFormat::Unresolved);
+    }
+
+    #[test]
+    fn test_parse_structs() {
+        let mprogram = "struct Point {
+                            // comment
+                            x: i8,
+                            y: Point,
+                        }
+                        fn main() -> void {
+                            let p: Point = Point { x: 1, y: p.y };
+                            p.y.x = 2;
+                            if p.x == 1 {
+                                print((Point { x: 1 }).x);
+                            }
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Struct(def) = &body.stmts[0] else {
+            panic!("expected struct")
+        };
+        assert_eq!(
+            def.fields[1].field_type,
+            TypeAST::Custom("Point".to_string())
+        );
+        let StmtAST::Function(main) = &body.stmts[1] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        assert!(matches!(&decl.value, ExprAST::StructLiteral(lit) if lit.fields.len() == 2));
+        let StmtAST::Assign(assign) = &main.body.stmts[1] else {
+            panic!("expected assignment")
+        };
+        let ExprAST::FieldAccess(access) = &assign.target else {
+            panic!("expected field access")
+        };
+        assert_eq!(access.field, "x");
+        assert!(matches!(&access.expr, ExprAST::FieldAccess(inner) if inner.field == "y"));
+        //the body of the if is not a struct literal
+        assert!(matches!(main.body.stmts[2], StmtAST::If(_)));
+    }
+
+    #[test]
+    fn test_parse_enums_and_match() {
+        let mprogram = "enum Op {
+                            Nop,
+                            // comment
+                            Load = 0 - 1,
+                            Jump = -3,
+                            Halt,
+                        }
+                        fn main() -> void {
+                            let op: Op = Op::Jump;
+                            match op {
+                                Op::Nop | Op::Halt => {}
+                                -1 => {
+                                    print('x');
+                                },
+                                _ => {}
+                            }
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        //the discriminant has to be a number, the enum is dropped
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "expected `,`, `=` or `}`, found `-`");
+        assert_eq!(body.stmts.len(), 1);
+
+        let mprogram = mprogram.replace("0 - 1", "2");
+        let (body, errors) = Parser::new(Lexer::new(mprogram.into())).parse();
+        assert!(errors.is_empty());
+        let StmtAST::Enum(def) = &body.stmts[0] else {
+            panic!("expected enum")
+        };
+        assert_eq!(def.discriminants(), [0, 2, -3, -2]);
+        assert_eq!(def.repr(), TypeAST::I8);
+        let StmtAST::Function(main) = &body.stmts[1] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        assert!(matches!(&decl.value, ExprAST::EnumVariant(v) if v.variant == "Jump"));
+        let StmtAST::Match(match_st) = &main.body.stmts[1] else {
+            panic!("expected match")
+        };
+        assert_eq!(match_st.arms.len(), 3);
+        assert_eq!(match_st.arms[0].patterns.len(), 2);
+        assert!(matches!(
+            match_st.arms[1].patterns[0],
+            PatternAST::Number(NumberAST { num: -1, .. })
+        ));
+        assert!(matches!(
+            match_st.arms[2].patterns[0],
+            PatternAST::Wildcard(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_arrays() {
+        let mprogram = "fn main() -> void {
+                            let grid: [[bool; 4]; 2] = [[false; 4], [true, false, true, false]];
+                            grid[1][f(0)] = true;
+                            p.cells[2].x = grid[0][1];
+                            let empty: [i8; 0] = [];
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        assert_eq!(decl.decl.var_type.to_string(), "[[bool; 4]; 2]");
+        let ExprAST::ArrayLiteral(lit) = &decl.value else {
+            panic!("expected array literal")
+        };
+        assert!(matches!(&lit.elements[0], ExprAST::ArrayRepeat(rep) if rep.len.num == 4));
+        assert!(matches!(&lit.elements[1], ExprAST::ArrayLiteral(row) if row.elements.len() == 4));
+        let StmtAST::Assign(assign) = &main.body.stmts[1] else {
+            panic!("expected assignment")
+        };
+        let ExprAST::Index(index) = &assign.target else {
+            panic!("expected index")
+        };
+        assert!(matches!(index.index, ExprAST::Call(_)));
+        assert!(matches!(&index.expr, ExprAST::Index(_)));
+        let StmtAST::Assign(assign) = &main.body.stmts[2] else {
+            panic!("expected assignment")
+        };
+        assert!(
+            matches!(&assign.target, ExprAST::FieldAccess(access) if matches!(access.expr, ExprAST::Index(_)))
+        );
+        assert_eq!(main.body.stmts.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_pointers() {
+        let mprogram = "fn swap(a: *i8, b: **Point) -> void {
+                            *a = *a * **b.x;
+                            let c: *i8 = &p.cells[2] + 1;
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(swap) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        assert_eq!(swap.fn_signt.args[1].var_type.to_string(), "**Point");
+        let StmtAST::Assign(assign) = &swap.body.stmts[0] else {
+            panic!("expected assignment")
+        };
+        assert!(matches!(&assign.target, ExprAST::Unary(unary) if unary.op == Token::Mult));
+        //the operand of `*` includes the field access
+        let ExprAST::BinaryExpression(mult) = &assign.value else {
+            panic!("expected binary expression")
+        };
+        assert_eq!(mult.op, Token::Mult);
+        let ExprAST::Unary(deref) = &mult.rhs else {
+            panic!("expected dereference")
+        };
+        assert!(
+            matches!(&deref.expr, ExprAST::Unary(inner) if matches!(inner.expr, ExprAST::FieldAccess(_)))
+        );
+        let StmtAST::DeclAssign(decl) = &swap.body.stmts[1] else {
+            panic!("expected declaration")
+        };
+        let ExprAST::BinaryExpression(add) = &decl.value else {
+            panic!("expected binary expression")
+        };
+        assert!(
+            matches!(&add.lhs, ExprAST::Unary(addr) if addr.op == Token::AndInt && addr.expr.is_place())
+        );
+    }
+
+    #[test]
+    fn test_parse_casts() {
+        let mprogram = "fn main() -> void {
+                            let c: u32 = a + *p as u16 as u32 * 2;
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        let ExprAST::BinaryExpression(add) = &decl.value else {
+            panic!("expected binary expression")
+        };
+        let ExprAST::BinaryExpression(mult) = &add.rhs else {
+            panic!("expected binary expression")
+        };
+        //the casts apply to the dereferenced pointer, the outer one last
+        let ExprAST::Cast(outer) = &mult.lhs else {
+            panic!("expected cast")
+        };
+        assert_eq!(outer.target, TypeAST::U32);
+        assert!(
+            matches!(&outer.expr, ExprAST::Cast(inner) if matches!(inner.expr, ExprAST::Unary(_)))
+        );
+    }
+
+    #[test]
+    fn test_parse_negation() {
+        let mprogram = "fn main() -> void {
+                            let c: i16 = -a * 2 - -1;
+                        }";
+        let (body, errors) = Parser::new(Lexer::new(mprogram.into())).parse();
+        assert!(errors.is_empty());
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        let StmtAST::DeclAssign(decl) = &main.body.stmts[0] else {
+            panic!("expected declaration")
+        };
+        let ExprAST::BinaryExpression(sub) = &decl.value else {
+            panic!("expected binary expression")
+        };
+        assert_eq!(sub.op, Token::Minus);
+        //the minus binds tighter than the multiplication
+        let ExprAST::BinaryExpression(mult) = &sub.lhs else {
+            panic!("expected binary expression")
+        };
+        assert!(matches!(&mult.lhs, ExprAST::Unary(neg) if neg.op == Token::Minus));
+        assert!(matches!(&sub.rhs, ExprAST::Unary(neg) if matches!(neg.expr, ExprAST::Number(_))));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let mprogram = "fn main() -> void {
+                            let a: u8 = 10
+                            let b = 20;
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse();
+        let err = &errors[0];
+        assert_eq!(err.code, diagnostics::UNEXPECTED_TOKEN);
+        assert_eq!(err.message, "expected `;`, found `let`");
+        assert_eq!(err.primary.span.line, 3);
+    }
+
+    #[test]
+    fn test_parse_number_out_of_range() {
+        let mprogram = "fn main() -> u8 {
+                            let a = 99999999999999999999;
+                            return 0;
+                        }";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (_, errors) = parser.parse();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, diagnostics::NUMBER_OUT_OF_RANGE);
+        assert_eq!(errors[0].message, "integer literal out of range");
+        assert_eq!(errors[0].primary.span.line, 2);
+        assert_eq!(errors[0].primary.span.col, 37);
+    }
+
+    #[test]
+    fn test_parse_error_recovery() {
+        let mprogram = "fn main() -> void {
+                            let a: u8 = 10
+                            let b = 20;
+                            if a == {
+                                b = 3;
+                            }
+                            let c = b;
+                            c = ;
+                        }
+                        fn (a: u8) {}
+                        fn foo() -> u8 {
+                            return 1;
+                        }
+                        fn bar() {
+                            let d = 5;";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        let lines: Vec<u32> = errors.iter().map(|err| err.primary.span.line).collect();
+        assert_eq!(lines, vec![3, 4, 8, 10, 15]);
+        //main, foo and bar are kept, the unnamed function is dropped
+        assert_eq!(body.stmts.len(), 3);
+        let StmtAST::Function(main) = &body.stmts[0] else {
+            panic!("expected function")
+        };
+        //`let b = 20;` and `let c = b;` survive in main
+        assert_eq!(main.body.stmts.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_globals() {
+        let mprogram = "const MAX: u8 = 10;
+                        static mut COUNT: u16 = MAX as u16;
+                        let ready = true;
+                        const BAD = 1;
+                        fn (a: u8) {
+                            let x = 1;
+                        }
+                        static S: i8;
+                        let done: bool = false;";
+        let lexer = Lexer::new(mprogram.into());
+        let mut parser = Parser::new(lexer);
+        let (body, errors) = parser.parse();
+        let lines: Vec<u32> = errors.iter().map(|err| err.primary.span.line).collect();
+        assert_eq!(lines, vec![4, 5, 8]);
+        assert_eq!(errors[0].message, "expected `:`, found `=`");
+        //the body of the unnamed function is skipped, its `let` is not a global
+        assert_eq!(body.stmts.len(), 4);
+        let StmtAST::Const(max) = &body.stmts[0] else {
+            panic!("expected constant")
+        };
+        assert_eq!(
+            (max.decl.name.as_str(), &max.decl.var_type),
+            ("MAX", &TypeAST::U8)
+        );
+        let StmtAST::DeclAssign(count) = &body.stmts[1] else {
+            panic!("expected global variable")
+        };
+        assert!(count.decl.is_mut && matches!(count.value, ExprAST::Cast(_)));
+        let StmtAST::DeclAssign(ready) = &body.stmts[2] else {
+            panic!("expected global variable")
+        };
+        assert!(!ready.decl.is_mut && ready.decl.var_type == TypeAST::Undefined);
+        assert!(matches!(&body.stmts[3], StmtAST::DeclAssign(done) if done.decl.name == "done"));
     }
 }
@@ -0,0 +1,57 @@
+//! minimal gzip writer, the data is stored in uncompressed deflate blocks
+//!
+//! every gzip reader accepts stored blocks, programs are small enough that the missing
+//! compression doesn't matter
+
+//largest amount of data in one stored block
+const MAX_BLOCK: usize = 0xffff;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    //magic, deflate, no flags, no modification time, no extra flags, unknown os
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        //an empty final block
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        out.push(last as u8);
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crc_and_framing() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        let data = vec![7; MAX_BLOCK + 1];
+        let gz = compress(&data);
+        //header, two blocks with their headers, crc and size
+        assert_eq!(gz.len(), 10 + 5 + MAX_BLOCK + 5 + 1 + 8);
+        assert_eq!(gz[10], 0);
+        assert_eq!(gz[10 + 5 + MAX_BLOCK], 1);
+        assert_eq!(gz[gz.len() - 4..], [0x00, 0x00, 0x01, 0x00]);
+    }
+}
@@ -0,0 +1,330 @@
+//! export of an assembled image as a redstone rom that can be pasted into a minecraft world
+//!
+//! every bit of the image becomes one block, the rom layout decides where the block of a bit is
+//! placed and which blocks stand for 0 and 1. two formats are written, both are gzip compressed
+//! nbt:
+//! - vanilla structure files, loaded with structure blocks
+//! - sponge schematics (version 2), loaded with worldedit
+
+mod gzip;
+mod nbt;
+
+use std::collections::HashSet;
+
+use nbt::Tag;
+
+///where the bits of the rom are placed and the blocks they are made of
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RomLayout {
+    pub word_bytes: u32,
+    pub bit_step: [i32; 3],
+    pub address_step: [i32; 3],
+    //number of addresses per row, 0 puts all addresses in one row
+    pub rows: u32,
+    pub row_step: [i32; 3],
+    pub one: String,
+    pub zero: String,
+    pub data_version: i32,
+}
+
+///position of a block and the bit it stands for
+type Block = ([i32; 3], bool);
+
+///largest size along each axis that a structure block in minecraft 1.20.1 loads, larger
+///structures have to be placed with `/place template`
+pub const STRUCTURE_BLOCK_LIMIT: i32 = 48;
+
+///the layout that is used if no layout file is given
+const DEFAULT_LAYOUT: &str = include_str!("../../layouts/rom.layout");
+
+fn parse_int(value: &str) -> Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number `{}`", value))
+}
+
+fn parse_vector(value: &str) -> Result<[i32; 3], String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [x, y, z] = parts.as_slice() else {
+        return Err(format!("expected `x y z`, found `{}`", value));
+    };
+    Ok([parse_int(x)?, parse_int(y)?, parse_int(z)?])
+}
+
+///splits `minecraft:lever[face=floor,powered=true]` into the name and the properties
+fn parse_block_state(state: &str) -> Result<(String, Vec<(String, String)>), String> {
+    let Some((name, props)) = state.split_once('[') else {
+        return Ok((state.to_string(), Vec::new()));
+    };
+    let props = props
+        .strip_suffix(']')
+        .ok_or_else(|| format!("invalid block state `{}`", state))?;
+    let props = props
+        .split(',')
+        .map(|prop| {
+            prop.split_once('=')
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .ok_or_else(|| format!("invalid block state `{}`", state))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((name.to_string(), props))
+}
+
+impl RomLayout {
+    ///parses a layout description, errors contain the line they occurred in
+    pub fn parse(description: &str) -> Result<Self, String> {
+        let mut layout = RomLayout {
+            word_bytes: 1,
+            bit_step: [0, 0, 1],
+            address_step: [1, 0, 0],
+            rows: 0,
+            row_step: [0, 1, 0],
+            one: "minecraft:redstone_torch".to_string(),
+            zero: "minecraft:air".to_string(),
+            data_version: 3465,
+        };
+        let mut section = String::new();
+        for (i, line) in description.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            layout
+                .parse_line(&mut section, line)
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+        }
+        if layout.word_bytes == 0 {
+            return Err("word_bytes has to be at least 1".to_string());
+        }
+        parse_block_state(&layout.one)?;
+        parse_block_state(&layout.zero)?;
+        Ok(layout)
+    }
+
+    fn parse_line(&mut self, section: &mut String, line: &str) -> Result<(), String> {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            *section = name.trim().to_string();
+            return Ok(());
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("expected `key = value`, found `{}`", line));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let unsigned = |value: &str| {
+            u32::try_from(parse_int(value)?).map_err(|_| format!("`{}` is negative", value))
+        };
+        match (section.as_str(), key) {
+            ("layout", "word_bytes") => self.word_bytes = unsigned(value)?,
+            ("layout", "bit_step") => self.bit_step = parse_vector(value)?,
+            ("layout", "address_step") => self.address_step = parse_vector(value)?,
+            ("layout", "rows") => self.rows = unsigned(value)?,
+            ("layout", "row_step") => self.row_step = parse_vector(value)?,
+            ("blocks", "one") => self.one = value.to_string(),
+            ("blocks", "zero") => self.zero = value.to_string(),
+            ("minecraft", "data_version") => self.data_version = parse_int(value)?,
+            (section, key) => return Err(format!("unknown key `{}` in [{}]", key, section)),
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let description =
+            std::fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+        Self::parse(&description)
+    }
+
+    ///positions of all bits of the image moved to start at 0 0 0, and the size of the rom
+    fn place(&self, image: &[u8]) -> Result<(Vec<Block>, [i32; 3]), String> {
+        let word_bits = self.word_bytes as usize * 8;
+        let rows = if self.rows == 0 { u32::MAX } else { self.rows } as i64;
+        let mut blocks = Vec::new();
+        for (i, byte) in image.iter().enumerate() {
+            for bit in 0..8 {
+                let index = i * 8 + bit;
+                let (addr, bit_nr) = ((index / word_bits) as i64, (index % word_bits) as i64);
+                let mut pos = [0; 3];
+                for (axis, coord) in pos.iter_mut().enumerate() {
+                    *coord = bit_nr * self.bit_step[axis] as i64
+                        + addr % rows * self.address_step[axis] as i64
+                        + addr / rows * self.row_step[axis] as i64;
+                }
+                blocks.push((pos, byte >> bit & 1 == 1));
+            }
+        }
+
+        let min = |axis: usize| blocks.iter().map(|(pos, _)| pos[axis]).min().unwrap_or(0);
+        let origin = [min(0), min(1), min(2)];
+        let mut placed = HashSet::new();
+        let mut size = [0; 3];
+        let mut moved = Vec::new();
+        for (pos, one) in blocks {
+            let mut at = [0; 3];
+            for axis in 0..3 {
+                let coord = pos[axis] - origin[axis];
+                at[axis] = i32::try_from(coord).map_err(|_| "the rom is too large".to_string())?;
+                size[axis] = size[axis].max(at[axis] + 1);
+            }
+            if !placed.insert(at) {
+                return Err(format!(
+                    "the layout places two bits at {} {} {}",
+                    at[0], at[1], at[2]
+                ));
+            }
+            moved.push((at, one));
+        }
+        Ok((moved, size))
+    }
+
+    ///size of the rom of the image in blocks along x, y and z
+    pub fn size(&self, image: &[u8]) -> Result<[i32; 3], String> {
+        self.place(image).map(|(_, size)| size)
+    }
+
+    fn palette_entry(state: &str) -> Tag {
+        //validated when the layout was parsed
+        let (name, props) = parse_block_state(state).unwrap_or_default();
+        let mut entry = vec![("Name".to_string(), Tag::String(name))];
+        if !props.is_empty() {
+            let props = props
+                .into_iter()
+                .map(|(key, value)| (key, Tag::String(value)))
+                .collect();
+            entry.push(("Properties".to_string(), Tag::Compound(props)));
+        }
+        Tag::Compound(entry)
+    }
+
+    ///gzip compressed vanilla structure file of the rom
+    pub fn structure(&self, image: &[u8]) -> Result<Vec<u8>, String> {
+        let (blocks, size) = self.place(image)?;
+        let ints = |values: [i32; 3]| Tag::List(values.into_iter().map(Tag::Int).collect());
+        let blocks = blocks
+            .into_iter()
+            .map(|(pos, one)| {
+                Tag::Compound(vec![
+                    ("state".to_string(), Tag::Int(one as i32)),
+                    ("pos".to_string(), ints(pos)),
+                ])
+            })
+            .collect();
+        let root = Tag::Compound(vec![
+            ("DataVersion".to_string(), Tag::Int(self.data_version)),
+            ("size".to_string(), ints(size)),
+            (
+                "palette".to_string(),
+                Tag::List(vec![
+                    Self::palette_entry(&self.zero),
+                    Self::palette_entry(&self.one),
+                ]),
+            ),
+            ("blocks".to_string(), Tag::List(blocks)),
+            ("entities".to_string(), Tag::List(Vec::new())),
+        ]);
+        Ok(gzip::compress(&root.to_bytes("")))
+    }
+
+    ///gzip compressed sponge schematic of the rom, the space between the bits is filled with air
+    pub fn schematic(&self, image: &[u8]) -> Result<Vec<u8>, String> {
+        let (blocks, [width, height, length]) = self.place(image)?;
+        if [width, height, length]
+            .iter()
+            .any(|len| *len > i16::MAX as i32)
+        {
+            return Err("the rom is too large for a schematic".to_string());
+        }
+        let mut palette: Vec<String> = Vec::new();
+        let mut index_of = |state: &str| match palette.iter().position(|s| s == state) {
+            Some(index) => index as u8,
+            None => {
+                palette.push(state.to_string());
+                palette.len() as u8 - 1
+            }
+        };
+        let air = index_of("minecraft:air");
+        let (zero, one) = (index_of(&self.zero), index_of(&self.one));
+        //every index is below 128, so every varint is one byte
+        let mut data = vec![air; (width * height * length) as usize];
+        for ([x, y, z], bit) in blocks {
+            data[(x + z * width + y * width * length) as usize] = if bit { one } else { zero };
+        }
+        let palette_max = palette.len() as i32;
+        let palette = palette
+            .into_iter()
+            .enumerate()
+            .map(|(i, state)| (state, Tag::Int(i as i32)))
+            .collect();
+        let root = Tag::Compound(vec![
+            ("Version".to_string(), Tag::Int(2)),
+            ("DataVersion".to_string(), Tag::Int(self.data_version)),
+            ("Width".to_string(), Tag::Short(width as i16)),
+            ("Height".to_string(), Tag::Short(height as i16)),
+            ("Length".to_string(), Tag::Short(length as i16)),
+            ("Offset".to_string(), Tag::IntArray(vec![0, 0, 0])),
+            ("PaletteMax".to_string(), Tag::Int(palette_max)),
+            ("Palette".to_string(), Tag::Compound(palette)),
+            ("BlockData".to_string(), Tag::ByteArray(data)),
+        ]);
+        Ok(gzip::compress(&root.to_bytes("Schematic")))
+    }
+}
+
+impl Default for RomLayout {
+    fn default() -> Self {
+        Self::parse(DEFAULT_LAYOUT).expect("invalid default layout")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_placement() {
+        let layout = RomLayout::default();
+        let (blocks, size) = layout.place(&[0b1000_0001; 25]).unwrap();
+        //24 addresses per row, the 25th is one row lower
+        assert_eq!(size, [47, 3, 15]);
+        assert_eq!(blocks[0], ([0, 2, 0], true));
+        assert_eq!(blocks[1], ([0, 2, 2], false));
+        assert_eq!(blocks[8], ([2, 2, 0], true));
+        assert_eq!(blocks[24 * 8 + 7], ([0, 0, 14], true));
+        //the largest rom that still fits into a structure block
+        assert_eq!(layout.size(&[0; 576]), Ok([47, 47, 15]));
+        assert_eq!(layout.size(&[0; 577]), Ok([47, 49, 15]));
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        let overlapping = DEFAULT_LAYOUT.replace("bit_step = 0 0 2", "bit_step = 2 0 0");
+        let layout = RomLayout::parse(&overlapping).unwrap();
+        assert_eq!(
+            layout.place(&[0, 0]),
+            Err("the layout places two bits at 2 0 0".to_string())
+        );
+        let broken = DEFAULT_LAYOUT.replace("rows = 24", "rows = -1");
+        assert_eq!(
+            RomLayout::parse(&broken),
+            Err("line 18: `-1` is negative".to_string())
+        );
+    }
+
+    #[test]
+    fn test_structure_file() {
+        let layout = RomLayout::parse("[blocks]\none = minecraft:lever[powered=true]").unwrap();
+        let gz = layout.structure(&[1]).unwrap();
+        assert_eq!(gz[..3], [0x1f, 0x8b, 8]);
+        //the nbt is stored uncompressed in one block after the gzip header
+        let nbt = &gz[15..gz.len() - 8];
+        let contains = |s: &str| nbt.windows(s.len()).any(|w| w == s.as_bytes());
+        assert!(contains("minecraft:lever"));
+        assert!(contains("Properties"));
+        assert!(contains("powered"));
+
+        let schem = layout.schematic(&[1]).unwrap();
+        let nbt = &schem[15..schem.len() - 8];
+        assert_eq!(
+            nbt[..12],
+            [10, 0, 9, b'S', b'c', b'h', b'e', b'm', b'a', b't', b'i', b'c']
+        );
+    }
+}
@@ -0,0 +1,103 @@
+///a tag of minecraft's named binary tag format, everything is big endian
+#[derive(Debug, PartialEq, Clone)]
+pub enum Tag {
+    Short(i16),
+    Int(i32),
+    ByteArray(Vec<u8>),
+    String(String),
+    //all elements have to be of the same type
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+        }
+    }
+
+    fn write_string(s: &str, out: &mut Vec<u8>) {
+        out.extend((s.len() as u16).to_be_bytes());
+        out.extend(s.as_bytes());
+    }
+
+    fn write_payload(&self, out: &mut Vec<u8>) {
+        match self {
+            Tag::Short(value) => out.extend(value.to_be_bytes()),
+            Tag::Int(value) => out.extend(value.to_be_bytes()),
+            Tag::ByteArray(bytes) => {
+                out.extend((bytes.len() as i32).to_be_bytes());
+                out.extend(bytes);
+            }
+            Tag::String(s) => Self::write_string(s, out),
+            Tag::List(tags) => {
+                //empty lists have the type of TAG_End
+                out.push(tags.first().map_or(0, Tag::id));
+                out.extend((tags.len() as i32).to_be_bytes());
+                for tag in tags {
+                    tag.write_payload(out);
+                }
+            }
+            Tag::Compound(entries) => {
+                for (name, tag) in entries {
+                    tag.write_named(name, out);
+                }
+                out.push(0);
+            }
+            Tag::IntArray(values) => {
+                out.extend((values.len() as i32).to_be_bytes());
+                for value in values {
+                    out.extend(value.to_be_bytes());
+                }
+            }
+        }
+    }
+
+    fn write_named(&self, name: &str, out: &mut Vec<u8>) {
+        out.push(self.id());
+        Self::write_string(name, out);
+        self.write_payload(out);
+    }
+
+    ///encodes the tag as the root of a file
+    pub fn to_bytes(&self, name: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_named(name, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encoding() {
+        let tag = Tag::Compound(vec![
+            ("a".to_string(), Tag::Short(258)),
+            (
+                "l".to_string(),
+                Tag::List(vec![Tag::String("x".to_string())]),
+            ),
+            ("e".to_string(), Tag::List(vec![])),
+        ]);
+        assert_eq!(
+            tag.to_bytes(""),
+            [
+                10, 0, 0, //root compound without a name
+                2, 0, 1, b'a', 1, 2, //short a
+                9, 0, 1, b'l', 8, 0, 0, 0, 1, 0, 1, b'x', //list of strings
+                9, 0, 1, b'e', 0, 0, 0, 0, 0, //empty list
+                0, //end of the compound
+            ]
+        );
+    }
+}